@@ -0,0 +1,48 @@
+use std::fmt::Write;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+//every file under typst-packages/<namespace>/<name>/<version>/: its namespace, name, version and
+//path in the package, then where it is on disk
+fn package_files(dir: &Path, package: &[String], files: &mut Vec<(Vec<String>, PathBuf)>) {
+    let Ok(entries) = fs::read_dir(dir) else {
+        return;
+    };
+    let mut entries: Vec<PathBuf> = entries.filter_map(|e| e.ok()).map(|e| e.path()).collect();
+    entries.sort();
+    for path in entries {
+        let name = path.file_name().unwrap().to_string_lossy().to_string();
+        let mut inner = package.to_vec();
+        inner.push(name);
+        if path.is_dir() {
+            package_files(&path, &inner, files);
+        } else if inner.len() > 3 {
+            files.push((inner, path));
+        }
+    }
+}
+
+//embeds the vendored Typst packages for the pdf feature, whatever files they have
+fn main() {
+    println!("cargo::rerun-if-changed=typst-packages");
+    let root = Path::new(env!("CARGO_MANIFEST_DIR")).join("typst-packages");
+    let mut files = Vec::new();
+    package_files(&root, &[], &mut files);
+
+    let mut out = String::from("&[\n");
+    for (parts, path) in files {
+        writeln!(
+            out,
+            "    ({:?}, {:?}, {:?}, {:?}, include_bytes!({:?})),",
+            parts[0],
+            parts[1],
+            parts[2],
+            parts[3..].join("/"),
+            fs::canonicalize(&path).unwrap()
+        )
+        .unwrap();
+    }
+    out.push(']');
+    let dest = Path::new(&std::env::var("OUT_DIR").unwrap()).join("typst_packages.rs");
+    fs::write(dest, out).unwrap();
+}
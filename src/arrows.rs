@@ -0,0 +1,208 @@
+use crate::{ArrowedState, GlossOccurrance, GlossUuid, GlosserError, Sequence, WordUuid};
+use std::collections::HashSet;
+use std::fmt;
+
+//the editorial rules we follow when deciding what to arrow
+#[derive(Clone, Debug, PartialEq)]
+pub struct ArrowRules {
+    //only arrow glosses which occur at least this many times in the sequence
+    pub min_count: usize,
+    //glosses with a unit from 1 to core_max_unit are core vocabulary and are never arrowed
+    pub core_max_unit: i32,
+    //function words are never arrowed: each entry is matched, ignoring case, against the whole
+    //words of the gloss's pos, so "prep" matches "prep. + gen." but "part" wouldn't match "participle"
+    pub function_word_pos: Vec<String>,
+}
+
+impl Default for ArrowRules {
+    fn default() -> Self {
+        ArrowRules {
+            min_count: 2,
+            core_max_unit: 20,
+            function_word_pos: vec![
+                String::from("art"),
+                String::from("article"),
+                String::from("conj"),
+                String::from("conjunction"),
+                String::from("particle"),
+                String::from("prep"),
+                String::from("preposition"),
+            ],
+        }
+    }
+}
+
+impl ArrowRules {
+    fn is_core(&self, unit: i32) -> bool {
+        unit > 0 && unit <= self.core_max_unit
+    }
+
+    pub(crate) fn is_function_word(&self, pos: &str) -> bool {
+        let pos = pos.to_lowercase();
+        pos.split(|c: char| !c.is_alphabetic())
+            .filter(|word| !word.is_empty())
+            .any(|word| {
+                self.function_word_pos
+                    .iter()
+                    .any(|p| p.trim().to_lowercase() == word)
+            })
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum ArrowIssue {
+    Infrequent {
+        total_count: usize,
+        min_count: usize,
+    },
+    CoreVocabulary {
+        unit: i32,
+    },
+    FunctionWord {
+        pos: String,
+    },
+}
+
+impl fmt::Display for ArrowIssue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ArrowIssue::Infrequent {
+                total_count,
+                min_count,
+            } => write!(
+                f,
+                "occurs {} time(s), fewer than the minimum of {}",
+                total_count, min_count
+            ),
+            ArrowIssue::CoreVocabulary { unit } => {
+                write!(f, "core vocabulary (unit {})", unit)
+            }
+            ArrowIssue::FunctionWord { pos } => write!(f, "function word ({})", pos),
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct ArrowRecommendation {
+    pub gloss_uuid: GlossUuid,
+    pub word_uuid: WordUuid, //the first occurrence of the gloss in a displayed text
+    pub lemma: String,
+    pub total_count: usize,
+    pub reason: String,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct ArrowViolation {
+    pub gloss_uuid: GlossUuid,
+    pub word_uuid: WordUuid,
+    pub lemma: String,
+    pub issues: Vec<ArrowIssue>,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct ArrowReview {
+    pub recommendations: Vec<ArrowRecommendation>,
+    pub violations: Vec<ArrowViolation>,
+}
+
+fn check_rules(go: &GlossOccurrance, rules: &ArrowRules) -> Vec<ArrowIssue> {
+    let mut issues = vec![];
+    if let Some(gloss) = go.gloss {
+        let total_count = go.total_count.unwrap_or(0);
+        if total_count < rules.min_count {
+            issues.push(ArrowIssue::Infrequent {
+                total_count,
+                min_count: rules.min_count,
+            });
+        }
+        if rules.is_core(gloss.unit) {
+            issues.push(ArrowIssue::CoreVocabulary { unit: gloss.unit });
+        }
+        if rules.is_function_word(&gloss.pos) {
+            issues.push(ArrowIssue::FunctionWord {
+                pos: gloss.pos.clone(),
+            });
+        }
+    }
+    issues
+}
+
+impl Sequence {
+    //gloss_occurrances must be the result of process() for this sequence
+    pub fn recommend_arrows(
+        &self,
+        gloss_occurrances: &[Vec<GlossOccurrance>],
+        rules: &ArrowRules,
+    ) -> ArrowReview {
+        let mut recommendations = vec![];
+        let mut violations = vec![];
+
+        //glosses which are already arrowed somewhere in the sequence
+        let arrowed: HashSet<GlossUuid> = self
+            .sequence_description
+            .arrowed_words
+            .iter()
+            .map(|a| a.gloss_uuid)
+            .collect();
+        let mut seen: HashSet<GlossUuid> = HashSet::default();
+
+        for (text_index, text) in gloss_occurrances.iter().enumerate() {
+            let display = self
+                .sequence_description
+                .texts
+                .get(text_index)
+                .is_none_or(|t| t.display);
+            let text_name = self
+                .texts
+                .get(text_index)
+                .map(|t| t.text_name.as_str())
+                .unwrap_or("");
+
+            for go in text {
+                let Some(gloss) = go.gloss else {
+                    continue;
+                };
+                if go.arrowed_state == ArrowedState::Arrowed {
+                    let issues = check_rules(go, rules);
+                    if !issues.is_empty() {
+                        violations.push(ArrowViolation {
+                            gloss_uuid: gloss.uuid,
+                            word_uuid: go.word.uuid,
+                            lemma: gloss.lemma.clone(),
+                            issues,
+                        });
+                    }
+                } else if display
+                    && !arrowed.contains(&gloss.uuid)
+                    && seen.insert(gloss.uuid)
+                    && check_rules(go, rules).is_empty()
+                {
+                    let total_count = go.total_count.unwrap_or(0);
+                    recommendations.push(ArrowRecommendation {
+                        gloss_uuid: gloss.uuid,
+                        word_uuid: go.word.uuid,
+                        lemma: gloss.lemma.clone(),
+                        total_count,
+                        reason: format!("occurs {} times, first in {}", total_count, text_name),
+                    });
+                }
+            }
+        }
+
+        ArrowReview {
+            recommendations,
+            violations,
+        }
+    }
+
+    //arrows each recommended word, stopping at the first one arrow_word rejects
+    pub fn apply_arrow_recommendations(
+        &mut self,
+        recommendations: &[ArrowRecommendation],
+    ) -> Result<(), GlosserError> {
+        for r in recommendations {
+            self.arrow_word(r.word_uuid, r.gloss_uuid, true)?;
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,194 @@
+use crate::pagecounts::page_ranges;
+use crate::{GlosserError, Sequence, Word, WordType};
+use std::fmt;
+
+//the rules a page boundary has to respect.
+//a break at index i ends a page after words[i - 1] and starts the next one with words[i]
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct BreakRules {
+    //in prose, only break after sentence-ending punctuation
+    pub sentence_breaks_in_prose: bool,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum BreakIssue {
+    SplitsVerseLine,
+    OrphanedHeading { word_type: WordType },
+    MidSentence,
+}
+
+impl fmt::Display for BreakIssue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            BreakIssue::SplitsVerseLine => write!(f, "splits a verse line"),
+            BreakIssue::OrphanedHeading { word_type } => {
+                write!(f, "leaves a {:?} at the bottom of the page", word_type)
+            }
+            BreakIssue::MidSentence => write!(f, "falls in the middle of a sentence"),
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct BreakViolation {
+    pub page_index: usize, //the page which ends at the break
+    pub break_index: usize,
+    pub issues: Vec<BreakIssue>,
+    pub suggested_break: Option<usize>, //the nearest legal break which leaves neither page empty
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct TextBreakReport {
+    pub text_index: usize,
+    pub violations: Vec<BreakViolation>,
+}
+
+const SENTENCE_END: [&str; 7] = [".", ";", ";", "!", ".”", ".\"", ".’"];
+
+//types which mark the end of a verse line: the next verse line or a block between lines
+fn ends_verse_line(word_type: WordType) -> bool {
+    matches!(
+        word_type,
+        WordType::VerseLine
+            | WordType::Speaker
+            | WordType::SectionTitle
+            | WordType::WorkTitle
+            | WordType::ParaWithIndent
+            | WordType::ParaNoIndent
+            | WordType::PageBreak
+    )
+}
+
+//whether words[index] is inside verse: the last line-level marker before it started a verse line
+fn in_verse(words: &[Word], index: usize) -> bool {
+    words[..index]
+        .iter()
+        .rev()
+        .find(|w| ends_verse_line(w.word_type))
+        .is_some_and(|w| w.word_type == WordType::VerseLine)
+}
+
+pub fn break_issues(words: &[Word], index: usize, rules: &BreakRules) -> Vec<BreakIssue> {
+    let mut issues = vec![];
+    if index == 0 || index >= words.len() {
+        return issues;
+    }
+    let verse = in_verse(words, index);
+    if verse && !ends_verse_line(words[index].word_type) {
+        issues.push(BreakIssue::SplitsVerseLine);
+    }
+
+    //markers such as paragraphs and section numbers don't separate a heading from what follows
+    let last = words[..index].iter().rev().find(|w| {
+        matches!(
+            w.word_type,
+            WordType::Word | WordType::Punctuation | WordType::Speaker | WordType::SectionTitle
+        )
+    });
+    if let Some(w) = last {
+        if w.word_type == WordType::Speaker || w.word_type == WordType::SectionTitle {
+            issues.push(BreakIssue::OrphanedHeading {
+                word_type: w.word_type,
+            });
+        } else if rules.sentence_breaks_in_prose
+            && !verse
+            && !SENTENCE_END.contains(&w.word.as_str())
+        {
+            issues.push(BreakIssue::MidSentence);
+        }
+    }
+    issues
+}
+
+pub fn is_legal_break(words: &[Word], index: usize, rules: &BreakRules) -> bool {
+    break_issues(words, index, rules).is_empty()
+}
+
+//the legal break closest to index strictly between lower and upper, preferring the earlier one on a tie
+pub fn nearest_legal_break(
+    words: &[Word],
+    index: usize,
+    lower: usize,
+    upper: usize,
+    rules: &BreakRules,
+) -> Option<usize> {
+    let lower = lower + 1;
+    let upper = upper.min(words.len());
+    let candidate = |b: usize| b >= lower && b < upper && is_legal_break(words, b, rules);
+    for distance in 0..words.len() {
+        if let Some(b) = index.checked_sub(distance)
+            && candidate(b)
+        {
+            return Some(b);
+        }
+        if distance > 0 && candidate(index + distance) {
+            return Some(index + distance);
+        }
+        if index.saturating_sub(distance) < lower && index + distance >= upper {
+            break;
+        }
+    }
+    None
+}
+
+//checks the breaks given by words_per_page as make_document uses them, i.e. the starts of all
+//but the first of the pages page_ranges gives
+pub fn check_page_breaks(
+    words: &[Word],
+    words_per_page: &[usize],
+    rules: &BreakRules,
+) -> Vec<BreakViolation> {
+    let breaks: Vec<usize> = page_ranges(words_per_page, words.len())
+        .iter()
+        .skip(1)
+        .map(|page| page.start)
+        .collect();
+
+    let mut violations = vec![];
+    for (page_index, b) in breaks.iter().enumerate() {
+        let issues = break_issues(words, *b, rules);
+        if issues.is_empty() {
+            continue;
+        }
+        let lower = if page_index == 0 {
+            0
+        } else {
+            breaks[page_index - 1]
+        };
+        let upper = breaks.get(page_index + 1).copied().unwrap_or(words.len());
+        violations.push(BreakViolation {
+            page_index,
+            break_index: *b,
+            issues,
+            suggested_break: nearest_legal_break(words, *b, lower, upper, rules),
+        });
+    }
+    violations
+}
+
+impl Sequence {
+    //checks the words_per_page of each displayed text
+    pub fn check_page_breaks(
+        &self,
+        rules: &BreakRules,
+    ) -> Result<Vec<TextBreakReport>, GlosserError> {
+        let mut reports = vec![];
+        for (text_index, t) in self.texts.iter().enumerate() {
+            let Some(desc) = self.sequence_description.texts.get(text_index) else {
+                return Err(GlosserError::NotFound(format!(
+                    "no description for text {}",
+                    text_index
+                )));
+            };
+            if !desc.display {
+                continue;
+            }
+            let words_per_page = desc.page_counts();
+            reports.push(TextBreakReport {
+                text_index,
+                violations: check_page_breaks(&t.words, &words_per_page, rules),
+            });
+        }
+        Ok(reports)
+    }
+}
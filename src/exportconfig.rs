@@ -0,0 +1,287 @@
+use crate::GlosserError;
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+const POINTS_PER_INCH: f32 = 72.0;
+const POINTS_PER_MM: f32 = 72.0 / 25.4;
+
+#[derive(Default, Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub enum Paper {
+    #[default]
+    Letter,
+    A4,
+    A5,
+    Custom {
+        width: f32, //in points
+        height: f32,
+    },
+}
+
+impl Paper {
+    //width and height in points
+    pub fn size(&self) -> (f32, f32) {
+        match self {
+            Paper::Letter => (8.5 * POINTS_PER_INCH, 11.0 * POINTS_PER_INCH),
+            Paper::A4 => (210.0 * POINTS_PER_MM, 297.0 * POINTS_PER_MM),
+            Paper::A5 => (148.0 * POINTS_PER_MM, 210.0 * POINTS_PER_MM),
+            Paper::Custom { width, height } => (*width, *height),
+        }
+    }
+}
+
+#[derive(Default, Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub enum Leader {
+    #[default]
+    Dots,
+    Line,
+    Space,
+}
+
+#[derive(Default, Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub enum IndexBreaks {
+    #[default]
+    Flow, //left to the typesetter, which balances the columns of the last page
+    EntriesPerColumn(usize), //a column break (a page break with one column) after so many entries
+}
+
+//how the notes of the apparatus criticus are labelled
+#[derive(Default, Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub enum AppCritStyle {
+    #[default]
+    AsWritten, //no label, for notes which carry their own reference
+    LineReferenced, //by the verse line or section the note is on
+    Numbered,       //numbered on each page, with the number marked in the text
+}
+
+//the layout of the index of arrowed words in LaTeX, Typst and FODT
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub struct IndexLayout {
+    pub columns: usize,
+    pub letter_headings: bool,
+    pub leader: Leader, //between each lemma and its page number
+    pub breaks: IndexBreaks,
+}
+
+impl Default for IndexLayout {
+    fn default() -> Self {
+        IndexLayout {
+            columns: 1,
+            letter_headings: false,
+            leader: Leader::Dots,
+            breaks: IndexBreaks::Flow,
+        }
+    }
+}
+
+impl IndexLayout {
+    pub fn columns(&self) -> usize {
+        self.columns.max(1)
+    }
+
+    //whether a break comes before the entry at position i
+    pub fn break_before(&self, i: usize) -> bool {
+        match self.breaks {
+            IndexBreaks::EntriesPerColumn(n) => n > 0 && i > 0 && i.is_multiple_of(n),
+            IndexBreaks::Flow => false,
+        }
+    }
+}
+
+//in points; inner is the binding side
+#[derive(Default, Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Margins {
+    pub top: f32,
+    pub bottom: f32,
+    pub inner: f32,
+    pub outer: f32,
+}
+
+impl Margins {
+    pub fn all(margin: f32) -> Margins {
+        Margins {
+            top: margin,
+            bottom: margin,
+            inner: margin,
+            outer: margin,
+        }
+    }
+}
+
+//the even page header Typst and FODT have always printed
+pub(crate) const COURSE_HEADER: &str = "LGI - UPPER LEVEL GREEK";
+
+//what changes from one course or book to the next, accepted by ExportLatex, ExportTypst,
+//ExportHTML and ExportFodt. the defaults keep each format's previous layout and headers, except
+//that FODT's index tab stop now sits at the right margin: 6.9252in on letter paper, not 6.9402in
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct ExportConfig {
+    pub title: Option<String>, //None uses the sequence's name
+    pub subtitle: Option<String>,
+    pub even_header: Option<String>, //None keeps each format's own: the title in LaTeX, "LGI - UPPER LEVEL GREEK" in Typst and FODT
+    pub odd_header: Option<String>,  //None uses the title of the text on the page
+    pub paper: Paper,
+    pub margins: Option<Margins>, //None keeps each format's own margins
+    pub font_size: f32,           //in points
+    pub line_spacing: f32,        //as a multiple of single spacing
+    pub font_families: Vec<String>, //the first is the main font, the rest fallbacks where the format allows them
+    pub font_file: Option<String>,  //for LaTeX, HTML and ODT, so the main font needn't be installed
+    pub index: IndexLayout,
+    pub appcrit_notes: AppCritStyle,
+    pub template: Option<String>, //replaces the format's built-in document start, see fill_template
+    pub date: Option<String>,     //for %DATE%; None uses today's date
+}
+
+impl Default for ExportConfig {
+    fn default() -> Self {
+        ExportConfig {
+            title: None,
+            subtitle: None,
+            even_header: None,
+            odd_header: None,
+            paper: Paper::Letter,
+            margins: None,
+            font_size: 12.0,
+            line_spacing: 1.5,
+            font_families: vec![String::from("IFAO-Grec Unicode")],
+            font_file: Some(String::from("IFAOGrec.ttf")),
+            index: IndexLayout::default(),
+            appcrit_notes: AppCritStyle::AsWritten,
+            template: None,
+            date: None,
+        }
+    }
+}
+
+impl ExportConfig {
+    pub fn title<'a>(&'a self, sequence_name: &'a str) -> &'a str {
+        self.title.as_deref().unwrap_or(sequence_name)
+    }
+
+    //the title followed by the subtitle, for a document's metadata
+    pub fn full_title(&self, sequence_name: &str) -> String {
+        match &self.subtitle {
+            Some(subtitle) if !subtitle.is_empty() => {
+                format!("{}: {}", self.title(sequence_name), subtitle)
+            }
+            _ => self.title(sequence_name).to_string(),
+        }
+    }
+
+    //built_in is the format's own header, used when the config has none
+    pub fn even_header<'a>(&'a self, built_in: &'a str) -> &'a str {
+        self.even_header.as_deref().unwrap_or(built_in)
+    }
+
+    pub fn odd_header<'a>(&'a self, page_title: &'a str) -> &'a str {
+        self.odd_header.as_deref().unwrap_or(page_title)
+    }
+
+    //how wide the text block is next to the default's, US Letter with the format's own margins:
+    //the factor for widths laid out for the default. default_margin is the format's margin on
+    //each side for a paper size, used when the config has no margins
+    pub(crate) fn text_width_scale(&self, default_margin: impl Fn(Paper) -> f32) -> f32 {
+        let text_width = |paper: Paper, margins: Option<Margins>| {
+            let (width, _) = paper.size();
+            match margins {
+                Some(m) => width - m.inner - m.outer,
+                None => width - 2.0 * default_margin(paper),
+            }
+        };
+        text_width(self.paper, self.margins) / text_width(Paper::Letter, None)
+    }
+
+    pub fn main_font(&self) -> &str {
+        self.font_families
+            .first()
+            .map(|f| f.as_str())
+            .unwrap_or("IFAO-Grec Unicode")
+    }
+
+    //the user's template, or else the format's built-in one, with the placeholders all formats share
+    //filled in: %MAIN_TITLE%, %SUBTITLE%, %SEQUENCE_NAME%, %DATE% (yyyy-mm-dd) and %PAGE_NUM%.
+    //each exporter then fills in its own, e.g. %EVEN_HEADER% or %FONT_SIZE%
+    pub(crate) fn fill_template(
+        &self,
+        built_in: &str,
+        sequence_name: &str,
+        start_page: usize,
+        escape: &dyn Fn(&str) -> String,
+    ) -> String {
+        let date = match &self.date {
+            Some(date) => date.clone(),
+            None => modified_timestamp()[..10].to_string(),
+        };
+        self.template
+            .as_deref()
+            .unwrap_or(built_in)
+            .replace("%MAIN_TITLE%", &escape(self.title(sequence_name)))
+            .replace(
+                "%SUBTITLE%",
+                &escape(self.subtitle.as_deref().unwrap_or("")),
+            )
+            .replace("%SEQUENCE_NAME%", &escape(sequence_name))
+            .replace("%DATE%", &escape(&date))
+            .replace("%PAGE_NUM%", start_page.to_string().as_str())
+    }
+}
+
+//the current time in UTC, e.g. 2024-01-31T12:00:00Z: the form dcterms:modified requires, and
+//where %DATE% comes from when no date is configured
+pub(crate) fn modified_timestamp() -> String {
+    let secs = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0);
+    //http://howardhinnant.github.io/date_algorithms.html#civil_from_days
+    let z = (secs / 86400) as i64 + 719468;
+    let era = z.div_euclid(146097);
+    let doe = z - era * 146097;
+    let yoe = (doe - doe / 1460 + doe / 36524 - doe / 146096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + i64::from(month <= 2);
+    let rem = secs % 86400;
+    format!(
+        "{:04}-{:02}-{:02}T{:02}:{:02}:{:02}Z",
+        year,
+        month,
+        day,
+        rem / 3600,
+        rem % 3600 / 60,
+        rem % 60
+    )
+}
+
+//checks a document start defines everything an exporter's pages use. required pairs the name of
+//each definition with a pattern matching where it is defined
+pub(crate) fn validate_template(
+    template: &str,
+    required: &[(&str, &str)],
+) -> Result<(), GlosserError> {
+    let missing: Vec<&str> = required
+        .iter()
+        .filter(|(_, pattern)| {
+            !Regex::new(pattern)
+                .expect("valid definition pattern")
+                .is_match(template)
+        })
+        .map(|(name, _)| *name)
+        .collect();
+    if missing.is_empty() {
+        Ok(())
+    } else {
+        Err(GlosserError::InvalidInput(format!(
+            "template does not define: {}",
+            missing.join(", ")
+        )))
+    }
+}
+
+//a length in points as inches, e.g. "8.5in"
+pub fn inches(points: f32) -> String {
+    let s = format!("{:.4}", points / POINTS_PER_INCH);
+    format!("{}in", s.trim_end_matches('0').trim_end_matches('.'))
+}
@@ -0,0 +1,598 @@
+use super::ExportDocument;
+use crate::exportconfig::modified_timestamp;
+use crate::exporthtml::{ExportHTML, escape_html, escape_html_text};
+use crate::model::{GlossaryEntry, PageText, sort_index};
+use crate::{
+    ArrowedState, ArrowedWordsIndex, GlossOccurrance, GlossPageOptions, GlosserError, Sequence,
+    render_page,
+};
+use quick_xml::Reader;
+use quick_xml::events::{BytesStart, Event};
+use std::collections::{HashMap, HashSet};
+use std::io::{Cursor, Read, Write};
+use uuid::Uuid;
+use zip::write::SimpleFileOptions;
+use zip::{CompressionMethod, ZipArchive, ZipWriter};
+
+const CONTAINER_XML: &str = r##"<?xml version="1.0" encoding="UTF-8"?>
+<container version="1.0" xmlns="urn:oasis:names:tc:opendocument:xmlns:container">
+    <rootfiles>
+        <rootfile full-path="OEBPS/content.opf" media-type="application/oebps-package+xml"/>
+    </rootfiles>
+</container>
+"##;
+
+const STYLE_CSS: &str = r##"@font-face {
+    font-family: "IFAOGrec";
+    font-weight: normal;
+    src: url("fonts/IFAOGrec.ttf");
+}
+@font-face {
+    font-family: "IFAOGrec";
+    font-weight: bold;
+    src: url("fonts/IFAOGrecBold.ttf");
+}
+body { font-family: "IFAOGrec", serif; line-height: 1.5; }
+.PageNumber { text-align: right; font-size: 0.8em; }
+.TextTitle { text-align: center; margin-bottom: 1em; }
+.SectionTitle { text-align: center; margin: 1em 0; }
+.Section, .SubSection { font-weight: bold; }
+.VerseLine { display: flex; }
+.VerseSpeaker { width: 3em; }
+.VerseText { flex-grow: 1; }
+.VerseLineNumber { width: 3em; text-align: right; }
+.ParaIndented { text-indent: 2em; }
+.InlineSpeaker, .Speaker { font-weight: bold; }
+.AppCritDiv { margin: 1em 0; font-size: 0.9em; }
+.GlossContainer { border-top: 1px solid black; margin-top: 1em; padding-top: 0.5em; }
+.listword { margin-left: 2em; text-indent: -2em; }
+.listarrow { font-weight: bold; }
+.listheadword { font-weight: bold; }
+.listfrequency { font-size: 0.8em; }
+"##;
+
+//the file name in the package of a page
+fn page_file(page_number: usize) -> String {
+    format!("page-{}.xhtml", page_number)
+}
+
+fn xhtml_document(title: &str, body_type: &str, body: &str) -> String {
+    format!(
+        r##"<?xml version="1.0" encoding="UTF-8"?>
+<!DOCTYPE html>
+<html xmlns="http://www.w3.org/1999/xhtml" xmlns:epub="http://www.idpf.org/2007/ops" xml:lang="grc" lang="grc">
+<head>
+    <meta charset="UTF-8"/>
+    <title>{}</title>
+    <link rel="stylesheet" type="text/css" href="style.css"/>
+</head>
+<body epub:type="{}">
+{}
+</body>
+</html>
+"##,
+        escape_html_text(title),
+        body_type,
+        body
+    )
+}
+
+//the pieces of the epub's xhtml. make_epub puts each page in a document of its own
+pub struct ExportEpub {}
+impl ExportDocument for ExportEpub {
+    fn gloss_entry(&self, gloss_occurrance: &GlossOccurrance, lemma: Option<&str>) -> String {
+        if gloss_occurrance.arrowed_state != ArrowedState::Invisible
+            && let Some(lemma_unwrapped) = lemma
+            && let Some(gloss_unwrapped) = gloss_occurrance.gloss
+        {
+            let word_id = gloss_occurrance.word.uuid;
+            format!(
+                r##"<div id="gloss-word-{}" class="listword"><span class="listarrow">{}</span> <a class="listheadword" href="#text-word-{}">{}</a> <span class="listdef">{}</span> <span class="listfrequency">({} of {})</span></div>
+"##,
+                word_id,
+                if gloss_occurrance.arrowed_state == ArrowedState::Arrowed {
+                    "→"
+                } else {
+                    ""
+                },
+                word_id,
+                escape_html(lemma_unwrapped),
+                escape_html(&gloss_unwrapped.def),
+                gloss_occurrance.running_count.unwrap_or(0),
+                gloss_occurrance.total_count.unwrap_or(0)
+            )
+        } else {
+            String::from("")
+        }
+    }
+
+    fn make_text(&self, page_text: &PageText) -> String {
+        ExportHTML::default().make_text(page_text)
+    }
+
+    fn page_gloss_start(&self) -> String {
+        String::from("<aside class=\"GlossContainer\">\n")
+    }
+
+    fn page_start(&self, title: &str, page_number: usize) -> String {
+        format!(
+            "<section class=\"Page\" id=\"page-{}\">\n<div class=\"PageNumber\" epub:type=\"pagebreak\" title=\"{}\">{}</div>\n<div class=\"PageTitle\">{}</div>\n",
+            page_number,
+            page_number,
+            page_number,
+            escape_html_text(title)
+        )
+    }
+
+    fn page_end(&self) -> String {
+        String::from("</aside>\n</section>\n")
+    }
+
+    fn document_end(&self) -> String {
+        String::from("\n</body>\n</html>\n")
+    }
+
+    fn document_start(&self, title: &str, _start_page: usize) -> String {
+        let doc = xhtml_document(title, "bodymatter", "");
+        doc[..doc.find("\n</body>").unwrap_or(doc.len())].to_string()
+    }
+
+    fn make_index(&self, arrowed_words_index: &[ArrowedWordsIndex]) -> String {
+        let mut res = String::from(
+            "<section id=\"index\" epub:type=\"index\">\n<h1>Index</h1>\n<ol class=\"Index\">\n",
+        );
+        for gloss in arrowed_words_index {
+            res.push_str(
+                format!(
+                    "<li>{}, <a href=\"{}\">{}</a></li>\n",
+                    escape_html_text(&gloss.gloss_lemma),
+                    page_file(gloss.page_number),
+                    gloss.page_number
+                )
+                .as_str(),
+            );
+        }
+        res.push_str("</ol>\n</section>\n");
+        res
+    }
+
+    fn make_glossary(&self, glossary: &[GlossaryEntry]) -> String {
+        let mut res = String::from(
+            "<section id=\"glossary\" epub:type=\"glossary\">\n<h1>Glossary</h1>\n<dl class=\"Glossary\">\n",
+        );
+        for entry in glossary {
+            res.push_str(
+                format!(
+                    "<dt>{}</dt>\n<dd>({}) {} ({})</dd>\n",
+                    escape_html(&entry.lemma),
+                    escape_html_text(&entry.pos),
+                    escape_html(&entry.def),
+                    match entry.arrowed_page {
+                        Some(page) => format!(
+                            "{}×, <a href=\"{}\">→ p. {}</a>",
+                            entry.total_count,
+                            page_file(page),
+                            page
+                        ),
+                        None => entry.frequency(),
+                    }
+                )
+                .as_str(),
+            );
+        }
+        res.push_str("</dl>\n</section>\n");
+        res
+    }
+
+    fn blank_page(&self) -> String {
+        String::from("")
+    }
+}
+
+struct ManifestItem {
+    id: String,
+    href: String,
+    media_type: &'static str,
+    properties: Option<&'static str>,
+    content: Vec<u8>,
+    in_spine: bool,
+}
+
+fn xhtml_item(id: String, href: String, content: String) -> ManifestItem {
+    ManifestItem {
+        id,
+        href,
+        media_type: "application/xhtml+xml",
+        properties: None,
+        content: content.into_bytes(),
+        in_spine: true,
+    }
+}
+
+impl Sequence {
+    //an epub 3 package of the document: a document per page, each text's contents before its pages
+    //and the index at the back. blank pages are left out since e-readers reflow the text
+    pub fn make_epub(
+        &self,
+        gloss_occurrances: &[Vec<GlossOccurrance>],
+        options: &GlossPageOptions,
+    ) -> Result<Vec<u8>, GlosserError> {
+        let export = ExportEpub {};
+        let title = &self.sequence_description.name;
+        let appcrit_hash = self.make_appcrit_hash();
+        let map = self.make_page_map();
+        let mut index = vec![];
+        let mut items = vec![];
+        let mut toc = String::from("");
+
+        for (text_index, t) in self.texts.iter().enumerate() {
+            let mut contents = String::from("");
+            let mut pages = vec![];
+            for (page_number, text_page) in map.text_pages(text_index) {
+                let page = self.make_text_page_model(
+                    gloss_occurrances,
+                    text_page,
+                    page_number,
+                    &appcrit_hash,
+                    &mut index,
+                    options,
+                );
+                contents.push_str(
+                    format!(
+                        "<li><a href=\"{}\">{}</a></li>\n",
+                        page_file(page_number),
+                        page_number
+                    )
+                    .as_str(),
+                );
+                pages.push(xhtml_item(
+                    format!("page-{}", page_number),
+                    page_file(page_number),
+                    xhtml_document(
+                        &format!("{} {}", t.text_name, page_number),
+                        "bodymatter",
+                        &render_page(&page, &export),
+                    ),
+                ));
+            }
+            if pages.is_empty() {
+                continue;
+            }
+
+            let text_file = format!("text-{}.xhtml", text_index);
+            let name = escape_html_text(&t.text_name);
+            toc.push_str(
+                format!(
+                    "<li><a href=\"{}\">{}</a>\n<ol>\n{}</ol>\n</li>\n",
+                    text_file, name, contents
+                )
+                .as_str(),
+            );
+            items.push(xhtml_item(
+                format!("text-{}", text_index),
+                text_file,
+                xhtml_document(
+                    &t.text_name,
+                    "chapter",
+                    &format!(
+                        "<nav epub:type=\"toc\">\n<h1>{}</h1>\n<ol>\n{}</ol>\n</nav>",
+                        name, contents
+                    ),
+                ),
+            ));
+            items.append(&mut pages);
+        }
+
+        sort_index(&mut index);
+        if !index.is_empty() {
+            items.push(xhtml_item(
+                String::from("index"),
+                String::from("index.xhtml"),
+                xhtml_document("Index", "backmatter", &export.make_index(&index)),
+            ));
+            toc.push_str("<li><a href=\"index.xhtml\">Index</a></li>\n");
+        }
+        let glossary = if options.glossary {
+            self.make_glossary(gloss_occurrances, &map)
+        } else {
+            vec![]
+        };
+        if !glossary.is_empty() {
+            items.push(xhtml_item(
+                String::from("glossary"),
+                String::from("glossary.xhtml"),
+                xhtml_document("Glossary", "backmatter", &export.make_glossary(&glossary)),
+            ));
+            toc.push_str("<li><a href=\"glossary.xhtml\">Glossary</a></li>\n");
+        }
+
+        items.push(ManifestItem {
+            properties: Some("nav"),
+            in_spine: false,
+            ..xhtml_item(
+                String::from("nav"),
+                String::from("nav.xhtml"),
+                xhtml_document(
+                    title,
+                    "frontmatter",
+                    &format!(
+                        "<nav epub:type=\"toc\" id=\"toc\">\n<h1>{}</h1>\n<ol>\n{}</ol>\n</nav>",
+                        escape_html_text(title),
+                        toc
+                    ),
+                ),
+            )
+        });
+        items.push(ManifestItem {
+            id: String::from("css"),
+            href: String::from("style.css"),
+            media_type: "text/css",
+            properties: None,
+            content: STYLE_CSS.as_bytes().to_vec(),
+            in_spine: false,
+        });
+        for (id, href, font) in [
+            (
+                "font-regular",
+                "fonts/IFAOGrec.ttf",
+                include_bytes!("../IFAOGrec.ttf") as &[u8],
+            ),
+            (
+                "font-bold",
+                "fonts/IFAOGrecBold.ttf",
+                include_bytes!("../IFAOGrecBold.ttf") as &[u8],
+            ),
+        ] {
+            items.push(ManifestItem {
+                id: String::from(id),
+                href: String::from(href),
+                media_type: "font/ttf",
+                properties: None,
+                content: font.to_vec(),
+                in_spine: false,
+            });
+        }
+
+        let mut manifest = String::from("");
+        let mut spine = String::from("");
+        for item in &items {
+            manifest.push_str(
+                format!(
+                    "        <item id=\"{}\" href=\"{}\" media-type=\"{}\"{}/>\n",
+                    item.id,
+                    item.href,
+                    item.media_type,
+                    item.properties
+                        .map(|p| format!(" properties=\"{}\"", p))
+                        .unwrap_or_default()
+                )
+                .as_str(),
+            );
+            if item.in_spine {
+                spine.push_str(format!("        <itemref idref=\"{}\"/>\n", item.id).as_str());
+            }
+        }
+        let opf = format!(
+            r##"<?xml version="1.0" encoding="UTF-8"?>
+<package xmlns="http://www.idpf.org/2007/opf" version="3.0" unique-identifier="book-id" xml:lang="en">
+    <metadata xmlns:dc="http://purl.org/dc/elements/1.1/">
+        <dc:identifier id="book-id">urn:uuid:{}</dc:identifier>
+        <dc:title>{}</dc:title>
+        <dc:language>grc</dc:language>
+        <meta property="dcterms:modified">{}</meta>
+    </metadata>
+    <manifest>
+{}    </manifest>
+    <spine>
+{}    </spine>
+</package>
+"##,
+            //the same for every export of the sequence, so readers see one book
+            Uuid::new_v5(&Uuid::NAMESPACE_OID, title.as_bytes()),
+            escape_html_text(title),
+            modified_timestamp(),
+            manifest,
+            spine
+        );
+
+        let zip_error = |e: zip::result::ZipError| GlosserError::Other(format!("Zip error: {}", e));
+        let io_error = |e: std::io::Error| GlosserError::Other(format!("Zip error: {}", e));
+        let mut zip = ZipWriter::new(Cursor::new(Vec::new()));
+        //the mimetype has to be first and uncompressed
+        zip.start_file(
+            "mimetype",
+            SimpleFileOptions::default().compression_method(CompressionMethod::Stored),
+        )
+        .map_err(zip_error)?;
+        zip.write_all(b"application/epub+zip").map_err(io_error)?;
+        let options = SimpleFileOptions::default();
+        zip.start_file("META-INF/container.xml", options)
+            .map_err(zip_error)?;
+        zip.write_all(CONTAINER_XML.as_bytes()).map_err(io_error)?;
+        zip.start_file("OEBPS/content.opf", options)
+            .map_err(zip_error)?;
+        zip.write_all(opf.as_bytes()).map_err(io_error)?;
+        for item in &items {
+            zip.start_file(format!("OEBPS/{}", item.href), options)
+                .map_err(zip_error)?;
+            zip.write_all(&item.content).map_err(io_error)?;
+        }
+        zip.finish().map(|c| c.into_inner()).map_err(zip_error)
+    }
+}
+
+fn attribute(e: &BytesStart, name: &[u8]) -> Option<String> {
+    e.attributes()
+        .flatten()
+        .find(|a| a.key.as_ref() == name)
+        .map(|a| String::from_utf8_lossy(&a.value).to_string())
+}
+
+//reads every element of an xml document, failing if it is not well formed
+fn xml_elements(xml: &str, file: &str) -> Result<Vec<(String, BytesStart<'static>)>, GlosserError> {
+    let mut reader = Reader::from_str(xml);
+    reader.config_mut().check_end_names = true;
+    let mut elements = vec![];
+    loop {
+        match reader.read_event() {
+            Ok(Event::Start(e)) | Ok(Event::Empty(e)) => elements.push((
+                String::from_utf8_lossy(e.name().as_ref()).to_string(),
+                e.into_owned(),
+            )),
+            Ok(Event::Eof) => break,
+            Ok(_) => (),
+            Err(e) => {
+                return Err(GlosserError::InvalidInput(format!(
+                    "{} is not well formed: {}",
+                    file, e
+                )));
+            }
+        }
+    }
+    Ok(elements)
+}
+
+//checks the structure of an epub package: the mimetype, the container, that the package
+//document's manifest, spine and metadata are complete, that every content document is well
+//formed and that its links point into the package
+pub fn validate_epub(data: &[u8]) -> Result<(), GlosserError> {
+    let invalid = |msg: String| GlosserError::InvalidInput(msg);
+    let mut archive = ZipArchive::new(Cursor::new(data))
+        .map_err(|e| GlosserError::Other(format!("Zip error: {}", e)))?;
+    let mut read = |name: &str| -> Result<String, GlosserError> {
+        let mut content = String::new();
+        archive
+            .by_name(name)
+            .map_err(|_| invalid(format!("{} is missing", name)))?
+            .read_to_string(&mut content)
+            .map_err(|e| invalid(format!("{} can't be read: {}", name, e)))?;
+        Ok(content)
+    };
+
+    let mut names = HashSet::new();
+    {
+        let mut archive = ZipArchive::new(Cursor::new(data))
+            .map_err(|e| GlosserError::Other(format!("Zip error: {}", e)))?;
+        for i in 0..archive.len() {
+            let file = archive
+                .by_index(i)
+                .map_err(|e| GlosserError::Other(format!("Zip error: {}", e)))?;
+            if i == 0
+                && (file.name() != "mimetype" || file.compression() != CompressionMethod::Stored)
+            {
+                return Err(invalid(String::from(
+                    "the first file is not an uncompressed mimetype",
+                )));
+            }
+            names.insert(file.name().to_string());
+        }
+    }
+    if read("mimetype")? != "application/epub+zip" {
+        return Err(invalid(String::from("wrong mimetype")));
+    }
+
+    let container = read("META-INF/container.xml")?;
+    let opf_path = xml_elements(&container, "container.xml")?
+        .iter()
+        .find(|(name, _)| name == "rootfile")
+        .and_then(|(_, e)| attribute(e, b"full-path"))
+        .ok_or_else(|| invalid(String::from("container.xml has no rootfile")))?;
+    let base = match opf_path.rfind('/') {
+        Some(i) => &opf_path[..=i],
+        None => "",
+    };
+
+    let opf = xml_elements(&read(&opf_path)?, &opf_path)?;
+    let package = opf
+        .iter()
+        .find(|(name, _)| name == "package")
+        .ok_or_else(|| invalid(String::from("no package element")))?;
+    if attribute(&package.1, b"version").as_deref() != Some("3.0") {
+        return Err(invalid(String::from("the package is not epub 3")));
+    }
+    let unique_identifier = attribute(&package.1, b"unique-identifier").unwrap_or_default();
+    if !opf.iter().any(|(name, e)| {
+        name == "dc:identifier" && attribute(e, b"id").as_deref() == Some(&unique_identifier)
+    }) {
+        return Err(invalid(String::from(
+            "the unique-identifier is not a dc:identifier",
+        )));
+    }
+    for required in ["dc:title", "dc:language"] {
+        if !opf.iter().any(|(name, _)| name == required) {
+            return Err(invalid(format!("the metadata has no {}", required)));
+        }
+    }
+    if !opf.iter().any(|(name, e)| {
+        name == "meta" && attribute(e, b"property").as_deref() == Some("dcterms:modified")
+    }) {
+        return Err(invalid(String::from(
+            "the metadata has no dcterms:modified",
+        )));
+    }
+
+    let mut manifest: HashMap<String, (String, String)> = HashMap::default(); //id to href and media type
+    let mut nav_count = 0;
+    for (_, e) in opf.iter().filter(|(name, _)| name == "item") {
+        let (Some(id), Some(href), Some(media_type)) = (
+            attribute(e, b"id"),
+            attribute(e, b"href"),
+            attribute(e, b"media-type"),
+        ) else {
+            return Err(invalid(String::from(
+                "a manifest item lacks an id, href or media-type",
+            )));
+        };
+        if !names.contains(&format!("{}{}", base, href)) {
+            return Err(invalid(format!(
+                "{} is in the manifest but not the package",
+                href
+            )));
+        }
+        if attribute(e, b"properties").is_some_and(|p| p.split(' ').any(|p| p == "nav")) {
+            nav_count += 1;
+        }
+        if manifest.insert(id.clone(), (href, media_type)).is_some() {
+            return Err(invalid(format!("the manifest id {} is repeated", id)));
+        }
+    }
+    if nav_count != 1 {
+        return Err(invalid(String::from(
+            "the manifest needs exactly one nav document",
+        )));
+    }
+
+    let spine: Vec<String> = opf
+        .iter()
+        .filter(|(name, _)| name == "itemref")
+        .filter_map(|(_, e)| attribute(e, b"idref"))
+        .collect();
+    if spine.is_empty() {
+        return Err(invalid(String::from("the spine is empty")));
+    }
+    if let Some(idref) = spine.iter().find(|idref| !manifest.contains_key(*idref)) {
+        return Err(invalid(format!(
+            "the spine refers to unknown item {}",
+            idref
+        )));
+    }
+
+    let hrefs: HashSet<&String> = manifest.values().map(|(href, _)| href).collect();
+    for (href, media_type) in manifest.values() {
+        if media_type != "application/xhtml+xml" {
+            continue;
+        }
+        for (_, e) in xml_elements(&read(&format!("{}{}", base, href))?, href)? {
+            for link in [attribute(&e, b"href"), attribute(&e, b"src")]
+                .into_iter()
+                .flatten()
+            {
+                let file = link.split('#').next().unwrap_or("");
+                if !file.is_empty() && !hrefs.contains(&file.to_string()) {
+                    return Err(invalid(format!("{} links to missing {}", href, link)));
+                }
+            }
+        }
+    }
+    Ok(())
+}
@@ -3,35 +3,45 @@ use super::ExportDocument;
 use crate::ArrowedState;
 use crate::ArrowedWordsIndex;
 use crate::GlossOccurrance;
-use crate::HashMap;
-use crate::WordType;
-use crate::WordUuid;
-use regex::Regex;
+use crate::GlosserError;
+use crate::exportconfig::{
+    AppCritStyle, COURSE_HEADER, ExportConfig, Leader, inches, validate_template,
+};
+use crate::markup::{MarkupTag, parse_markup_lenient, render_markup};
+use crate::model::{GlossaryEntry, Inline, PageText, ParagraphStart, TextBlock, index_letter};
 
 //https://stackoverflow.com/questions/79173197/how-to-escape-string-for-typst
-fn escape_fodt(s: &str) -> String {
+pub(crate) fn escape_fodt_text(s: &str) -> String {
     s.replace("&", "&amp;")
         .replace("\"", "&quot;")
         .replace("'", "&apos;")
         .replace(">", "&gt;")
         .replace("<", "&lt;")
-        .replace("&lt;b&gt;", r###"<text:span text:style-name="T1">"###)
-        .replace("&lt;/b&gt;", "</text:span>")
-        .replace("&lt;i&gt;", r###"<text:span text:style-name="T2">"###)
-        .replace("&lt;/i&gt;", "</text:span>")
-        .replace("&lt;sup&gt;", r###"<text:span text:style-name="T3">"###)
-        .replace("&lt;/sup&gt;", "</text:span>")
+}
+
+fn escape_fodt(s: &str) -> String {
+    render_markup(
+        &parse_markup_lenient(s),
+        &escape_fodt_text,
+        &|tag, inner| {
+            let style = match tag {
+                MarkupTag::Bold => "T1",
+                MarkupTag::Italic => "T2",
+                MarkupTag::Superscript => "T3",
+            };
+            format!(
+                r###"<text:span text:style-name="{}">{}</text:span>"###,
+                style, inner
+            )
+        },
+    )
 }
 
 fn complete_verse_line(
-    verse_speaker: Option<String>,
+    verse_speaker: Option<&str>,
     verse_line: &str,
     verse_line_number: &str,
 ) -> String {
-    if verse_line.is_empty() {
-        return String::from("");
-    }
-    let escaped_num = escape_fodt(verse_line_number);
     format!(
         r###"
         <table:table-row>
@@ -46,373 +56,154 @@ fn complete_verse_line(
          </table:table-cell>
         </table:table-row>
 "###,
-        verse_speaker.as_ref().unwrap_or(&String::from("")),
+        escape_fodt(verse_speaker.unwrap_or("")),
         &verse_line,
-        if let Ok(i) = verse_line_number.to_string().parse::<i32>() {
-            if i % 5 == 0 { verse_line_number } else { "" }
-        } else {
-            escaped_num.as_str()
-        }
+        escape_fodt(verse_line_number)
     )
 }
 
-pub struct ExportFodt {}
-impl ExportDocument for ExportFodt {
-    fn gloss_entry(&self, gloss_occurrance: &GlossOccurrance, lemma: Option<&str>) -> String {
-        if gloss_occurrance.arrowed_state != ArrowedState::Invisible
-            && let Some(lemma_unwrapped) = lemma
-            && let Some(gloss_unwrapped) = gloss_occurrance.gloss
-        {
-            format!(
-                r###"
-    <table:table-row table:style-name="GlossTableRow">
-      <table:table-cell table:style-name="GlossTableCell" office:value-type="string">
-        <text:p text:style-name="P8">{}</text:p>
-      </table:table-cell>
-      <table:table-cell table:style-name="GlossTableCell" office:value-type="string">
-        <text:p text:style-name="GlossTableLemma">{}</text:p>
-      </table:table-cell>
-      <table:table-cell table:style-name="GlossTableCell" office:value-type="string">
-        <text:p text:style-name="GlossTableDef">{}</text:p>
-      </table:table-cell>
-    </table:table-row>
-"###,
-                if gloss_occurrance.arrowed_state == ArrowedState::Arrowed {
-                    r##"→"##
-                } else {
-                    ""
-                },
-                escape_fodt(lemma_unwrapped),
-                escape_fodt(&gloss_unwrapped.def)
-            )
-        } else {
-            String::from("")
-        }
-    }
-
-    fn make_text(
-        &self,
-        gloss_occurrances: &[GlossOccurrance],
-        appcrit_hash: &HashMap<WordUuid, String>,
-    ) -> String {
-        let re = Regex::new("([0-9]+)[.]([0-9]+)").unwrap();
-        let mut res = String::from("");
-        let mut prev_non_space = true;
-        //let mut last_type = WordType::InvalidType;
-        let mut is_verse_section = false;
-        let mut verse_speaker: Option<String> = None;
-        let mut verse_line = String::from("");
-        let mut verse_line_number = String::from("");
-
-        let mut appcrits_page: Vec<String> = vec![];
-
-        let mut para_open = false;
-
-        for w in gloss_occurrances {
-            if let Some(ap) = appcrit_hash.get(&w.word.uuid) {
-                appcrits_page.push(ap.clone());
-            }
-
-            match w.word.word_type {
-                WordType::VerseLine => {
-                    if para_open {
-                        para_open = false;
-                        res.push_str(
-                            r###"
-    </text:p>
-"###,
-                        );
-                    }
-                    if !is_verse_section {
-                        res.push_str(
-                            r###"
-    <table:table table:name="VerseTable" table:style-name="VerseTable">
-        <table:table-column table:style-name="VerseTable.A"/>
-        <table:table-column table:style-name="VerseTable.B"/>
-        <table:table-column table:style-name="VerseTable.C"/>
-"###,
-                        );
-                        is_verse_section = true;
-                    } else {
-                        //previous verse line is complete
-                        res.push_str(
-                            complete_verse_line(verse_speaker, &verse_line, &verse_line_number)
-                                .as_str(),
-                        );
-                        verse_speaker = None;
-                        verse_line = String::from("");
-                    }
-                    verse_line_number = w.word.word.replace("[line]", "");
-                }
-                WordType::WorkTitle => {
-                    if para_open {
-                        para_open = false;
-                        res.push_str(
-                            r###"
-    </text:p>
-"###,
-                        );
-                    }
-                    res.push_str(
-                        format!(
-                            r###"
-    <text:p text:style-name="WorkTitleCenter">{}</text:p>
-    <text:p text:style-name="Standard"></text:p>
-                        "###,
-                            escape_fodt(&w.word.word)
-                        )
-                        .as_str(),
-                    )
-                }
-                WordType::Word | WordType::Punctuation => {
-                    //0 | 1
-                    let punc = vec![
-                        ".", ",", "·", "·", ";", ";", ">", "]", ")", ",\"", ".”", ".\"", "·\"",
-                        "·\"", ".’",
-                    ];
-                    let s = format!(
-                        "{}{}",
-                        if punc.contains(&w.word.word.as_str()) || prev_non_space {
-                            ""
-                        } else {
-                            " "
-                        },
-                        escape_fodt(&w.word.word)
-                    );
-                    if is_verse_section {
-                        verse_line.push_str(&s);
-                    } else {
-                        if !para_open {
-                            para_open = true;
-                            res.push_str(
-                                r###"
-    <text:p text:style-name="Standard">
-"###,
-                            );
-                        }
-                        res.push_str(&s);
-                    }
-                    prev_non_space = w.word.word == "<" || w.word.word == "[" || w.word.word == "(";
-                }
-                WordType::ParaWithIndent => {
-                    if para_open {
-                        res.push_str(
-                            r###"
-    </text:p>
-"###,
-                        );
-                    }
-                    para_open = true;
-                    res.push_str(
-                        r###"
-    <text:p text:style-name="PIndented">
-"###,
-                    )
-                }
-                WordType::ParaNoIndent => {
-                    if para_open {
-                        res.push_str(
-                            r###"
-    </text:p>
-"###,
-                        );
-                    }
-                    para_open = true;
-                    res.push_str(
-                        r###"
-    <text:p text:style-name="Standard">
-"###,
-                    )
+fn make_inlines(inlines: &[Inline], style: AppCritStyle) -> String {
+    let mut res = String::from("");
+    for inline in inlines {
+        match inline {
+            Inline::Word { word, space_before } => {
+                if *space_before {
+                    res.push(' ');
                 }
-                WordType::SectionTitle => {
-                    if para_open {
-                        para_open = false;
-                        res.push_str(
-                            r###"
-    </text:p>
-"###,
-                        );
-                    }
+                res.push_str(&escape_fodt(&word.word));
+            }
+            Inline::Section { number, .. } => res.push_str(
+                format!(
+                    r###" <text:span text:style-name="T1">{}</text:span> "###,
+                    number
+                )
+                .as_str(),
+            ),
+            Inline::Speaker(speaker) => res.push_str(
+                format!(
+                    r###"<text:span text:style-name="T1">{}</text:span> "###,
+                    escape_fodt(speaker)
+                )
+                .as_str(),
+            ),
+            Inline::AppCritMark(number) => {
+                if style == AppCritStyle::Numbered {
                     res.push_str(
                         format!(
-                            r###"
-    <text:p text:style-name="P18">{}</text:p>
-"###,
-                            escape_fodt(&w.word.word)
+                            r###"<text:span text:style-name="T3">{}</text:span>"###,
+                            number
                         )
                         .as_str(),
                     )
                 }
-                WordType::Section => {
-                    let section_input = w.word.word.replace("[section]", "");
-
-                    let matches = re.captures(&section_input);
-
-                    let s = if let Some(matches) = matches {
-                        let section = matches.get(1).unwrap().as_str();
-                        let subsection = matches.get(2).unwrap().as_str();
-
-                        //To Do: for the next three formats move space to start of line
-                        if subsection == "1" {
-                            format!(
-                                r###" <text:span text:style-name="T1">{}</text:span> "###,
-                                section
-                            )
-                        } else {
-                            format!(
-                                r###" <text:span text:style-name="T1">{}</text:span> "###,
-                                subsection
-                            )
-                        }
-                    } else {
-                        format!(
-                            r###" <text:span text:style-name="T1">{}</text:span> "###,
-                            section_input
-                        )
-                    };
-
-                    res.push_str(s.as_str());
-                    prev_non_space = true;
-                }
-                WordType::Speaker => {
-                    //fix me can't add this in middle of a versetable
-                    if is_verse_section {
-                        res.push_str(
-                            complete_verse_line(
-                                verse_speaker.clone(),
-                                &verse_line,
-                                &verse_line_number,
-                            )
-                            .as_str(),
-                        );
-                        verse_speaker = None;
-                        verse_line = String::from("");
-                        res.push_str(
-                            r###"
-    </table:table>
-"###,
-                        );
-                    }
-
-                    res.push_str(
-                        format!(
-                            r###"
-    <text:p text:style-name="Standard">{}</text:p>
-"###,
-                            w.word.word
-                        )
-                        .as_str(),
-                    );
-                    if is_verse_section {
-                        res.push_str(
-                            r###"
-    <table:table table:name="VerseTable" table:style-name="VerseTable">
-        <table:table-column table:style-name="VerseTable.A"/>
-        <table:table-column table:style-name="VerseTable.B"/>
-        <table:table-column table:style-name="VerseTable.C"/>
-"###,
-                        );
-                    }
-                }
-                WordType::InlineSpeaker => {
-                    if is_verse_section {
-                        verse_speaker = Some(w.word.word.clone());
-                    } else {
-                        if !para_open {
-                            para_open = true;
-                            res.push_str(
-                                r###"
-    <text:p text:style-name="Standard">
-"###,
-                            )
-                        }
-                        res.push_str(
-                            format!(
-                                r###"<text:span text:style-name="T1">{}</text:span> "###,
-                                w.word.word
-                            )
-                            .as_str(),
-                        );
-                    }
-                }
-                WordType::InlineVerseSpeaker => {
-                    verse_speaker = Some(w.word.word.clone());
-                }
-                _ => (),
             }
-            //last_type = w.word_type.clone();
-        }
-
-        if is_verse_section {
-            //previous verse line is complete
-            res.push_str(
-                complete_verse_line(verse_speaker, &verse_line, &verse_line_number).as_str(),
-            );
-
-            res.push_str(
-                r###"
-</table:table>
-"###,
-            );
-        } else if para_open {
-            res.push_str("</text:p>\n");
-        }
-
-        if !appcrits_page.is_empty() {
-            res.push_str("<text:p></text:p>\n");
-        }
-        for ap in appcrits_page {
-            res.push_str(format!("<text:p>{}</text:p>\n", escape_fodt(&ap)).as_str());
         }
-
-        res
-    }
-
-    fn page_gloss_start(&self) -> String {
-        String::from(
-            r###"
-    <text:p text:style-name="P1"/>
-    <text:p text:style-name="P1"/>
-    <table:table table:name="Table1" table:style-name="GlossTable">
-      <table:table-column table:style-name="GlossTable.A"/>
-      <table:table-column table:style-name="GlossTable.B"/>
-      <table:table-column table:style-name="GlossTable.C"/>
-"###,
-        )
     }
+    res
+}
 
-    fn page_start(&self, _title: &str, _page_number: usize) -> String {
-        String::from(
-            r###"
-
-            "###,
-        )
-    }
+const DEFAULT_MARGIN: f32 = 0.7874 * 72.0;
+const INDEX_COLUMN_GAP: f32 = 0.3 * 72.0;
 
-    fn page_end(&self) -> String {
-        String::from(
-            r###"
-    </table:table>
-    <text:p text:style-name="PageBreakStyle"/>
-"###,
-        )
-    }
+//the styles of the index, which depend on its layout: a section with its columns, the entries with
+//a right tab stop at the edge of the column, the letter headings, and variants of both which start
+//a new column or page
+fn index_styles(config: &ExportConfig) -> String {
+    let layout = &config.index;
+    let columns = layout.columns();
+    let (width, _) = config.paper.size();
+    let (inner, outer) = match config.margins {
+        Some(m) => (m.inner, m.outer),
+        None => (DEFAULT_MARGIN, DEFAULT_MARGIN),
+    };
+    let column_width =
+        (width - inner - outer - INDEX_COLUMN_GAP * (columns - 1) as f32) / columns as f32;
+    let leader = match layout.leader {
+        Leader::Dots => r#" style:leader-style="dotted" style:leader-text=".""#,
+        Leader::Line => r#" style:leader-style="solid""#,
+        Leader::Space => "",
+    };
+    let break_before = if columns > 1 { "column" } else { "page" };
+    format!(
+        r###"<style:style style:name="IndexSection" style:family="section">
+           <style:section-properties text:dont-balance-text-columns="false" style:editable="false">
+            <style:columns fo:column-count="{columns}" fo:column-gap="{gap}"/>
+           </style:section-properties>
+          </style:style>
+          <style:style style:name="IndexOfArrowedWords" style:family="paragraph" style:parent-style-name="Standard">
+           <style:paragraph-properties fo:line-height="100%" fo:text-align="start" style:justify-single-word="false" style:writing-mode="lr-tb">
+            <style:tab-stops>
+             <style:tab-stop style:position="{position}" style:type="right"{leader}/>
+            </style:tab-stops>
+           </style:paragraph-properties>
+          </style:style>
+          <style:style style:name="IndexOfArrowedWordsBreak" style:family="paragraph" style:parent-style-name="IndexOfArrowedWords">
+           <style:paragraph-properties fo:break-before="{break_before}"/>
+          </style:style>
+          <style:style style:name="IndexLetter" style:family="paragraph" style:parent-style-name="Standard">
+           <style:paragraph-properties fo:margin-top="0.1in" fo:margin-bottom="0.05in" fo:keep-with-next="always"/>
+           <style:text-properties fo:font-weight="bold" style:font-weight-asian="bold" style:font-weight-complex="bold"/>
+          </style:style>
+          <style:style style:name="IndexLetterBreak" style:family="paragraph" style:parent-style-name="IndexLetter">
+           <style:paragraph-properties fo:break-before="{break_before}"/>
+          </style:style>"###,
+        gap = inches(INDEX_COLUMN_GAP),
+        position = inches(column_width),
+    )
+}
 
-    fn document_end(&self) -> String {
-        String::from(
-            r###"<text:p text:style-name="P5"/>
-  </office:text>
- </office:body>
-</office:document>
-"###,
-        )
-    }
+//the gloss and verse tables, laid out for letter paper and scaled to the text block
+fn table_styles(config: &ExportConfig) -> String {
+    let scale = config.text_width_scale(|_| DEFAULT_MARGIN);
+    let gloss_columns = [0.2563 * 72.0, 3.4236 * 72.0 * scale, 3.5 * 72.0 * scale];
+    let verse_columns = [
+        1.2139 * 72.0 * scale,
+        4.0625 * 72.0 * scale,
+        1.6486 * 72.0 * scale,
+    ];
+    format!(
+        r###"<style:style style:name="GlossTable" style:family="table">
+           <style:table-properties style:width="{gloss_width}" fo:margin-left="-0.2597in" fo:margin-top="0in" fo:margin-bottom="0in" table:align="left" fo:background-color="transparent" style:may-break-between-rows="false" style:writing-mode="lr-tb">
+            <style:background-image/>
+           </style:table-properties>
+          </style:style>
+          <style:style style:name="GlossTable.A" style:family="table-column">
+           <style:table-column-properties style:column-width="{gloss_a}"/>
+          </style:style>
+          <style:style style:name="GlossTable.B" style:family="table-column">
+           <style:table-column-properties style:column-width="{gloss_b}"/>
+          </style:style>
+          <style:style style:name="GlossTable.C" style:family="table-column">
+           <style:table-column-properties style:column-width="{gloss_c}"/>
+          </style:style>
+          <style:style style:name="VerseTable" style:family="table">
+           <style:table-properties style:width="{verse_width}" style:may-break-between-rows="false" table:align="margins" style:writing-mode="lr-tb"/>
+          </style:style>
+          <style:style style:name="VerseTable.A" style:family="table-column">
+           <style:table-column-properties style:column-width="{verse_a}" style:rel-column-width="1748*"/>
+          </style:style>
+          <style:style style:name="VerseTable.B" style:family="table-column">
+           <style:table-column-properties style:column-width="{verse_b}" style:rel-column-width="5850*"/>
+          </style:style>
+          <style:style style:name="VerseTable.C" style:family="table-column">
+           <style:table-column-properties style:column-width="{verse_c}" style:rel-column-width="2374*"/>
+          </style:style>"###,
+        gloss_width = inches(gloss_columns.iter().sum()),
+        gloss_a = inches(gloss_columns[0]),
+        gloss_b = inches(gloss_columns[1]),
+        gloss_c = inches(gloss_columns[2]),
+        verse_width = inches(verse_columns.iter().sum()),
+        verse_a = inches(verse_columns[0]),
+        verse_b = inches(verse_columns[1]),
+        verse_c = inches(verse_columns[2]),
+    )
+}
 
-    fn document_start(&self, title: &str, start_page: usize) -> String {
-        let start = r###"<?xml version="1.0" encoding="UTF-8"?>
+//the built-in document start, which ExportConfig::template replaces
+pub const FODT_TEMPLATE: &str = r###"<?xml version="1.0" encoding="UTF-8"?>
 
         <office:document xmlns:css3t="http://www.w3.org/TR/css3-text/" xmlns:grddl="http://www.w3.org/2003/g/data-view#" xmlns:xhtml="http://www.w3.org/1999/xhtml" xmlns:xsi="http://www.w3.org/2001/XMLSchema-instance" xmlns:xsd="http://www.w3.org/2001/XMLSchema" xmlns:xforms="http://www.w3.org/2002/xforms" xmlns:dom="http://www.w3.org/2001/xml-events" xmlns:script="urn:oasis:names:tc:opendocument:xmlns:script:1.0" xmlns:form="urn:oasis:names:tc:opendocument:xmlns:form:1.0" xmlns:math="http://www.w3.org/1998/Math/MathML" xmlns:meta="urn:oasis:names:tc:opendocument:xmlns:meta:1.0" xmlns:loext="urn:org:documentfoundation:names:experimental:office:xmlns:loext:1.0" xmlns:field="urn:openoffice:names:experimental:ooo-ms-interop:xmlns:field:1.0" xmlns:number="urn:oasis:names:tc:opendocument:xmlns:datastyle:1.0" xmlns:officeooo="http://openoffice.org/2009/office" xmlns:table="urn:oasis:names:tc:opendocument:xmlns:table:1.0" xmlns:chart="urn:oasis:names:tc:opendocument:xmlns:chart:1.0" xmlns:formx="urn:openoffice:names:experimental:ooxml-odf-interop:xmlns:form:1.0" xmlns:svg="urn:oasis:names:tc:opendocument:xmlns:svg-compatible:1.0" xmlns:tableooo="http://openoffice.org/2009/table" xmlns:draw="urn:oasis:names:tc:opendocument:xmlns:drawing:1.0" xmlns:rpt="http://openoffice.org/2005/report" xmlns:dr3d="urn:oasis:names:tc:opendocument:xmlns:dr3d:1.0" xmlns:of="urn:oasis:names:tc:opendocument:xmlns:of:1.2" xmlns:text="urn:oasis:names:tc:opendocument:xmlns:text:1.0" xmlns:style="urn:oasis:names:tc:opendocument:xmlns:style:1.0" xmlns:dc="http://purl.org/dc/elements/1.1/" xmlns:calcext="urn:org:documentfoundation:names:experimental:calc:xmlns:calcext:1.0" xmlns:oooc="http://openoffice.org/2004/calc" xmlns:config="urn:oasis:names:tc:opendocument:xmlns:config:1.0" xmlns:ooo="http://openoffice.org/2004/office" xmlns:xlink="http://www.w3.org/1999/xlink" xmlns:drawooo="http://openoffice.org/2010/draw" xmlns:ooow="http://openoffice.org/2004/writer" xmlns:fo="urn:oasis:names:tc:opendocument:xmlns:xsl-fo-compatible:1.0" xmlns:office="urn:oasis:names:tc:opendocument:xmlns:office:1.0" office:version="1.3" office:mimetype="application/vnd.oasis.opendocument.text">
-         <office:meta><meta:creation-date>2021-03-08T17:40:14</meta:creation-date><meta:initial-creator>Jeremy March</meta:initial-creator><dc:language>en-US</dc:language><dc:creator>Jeremy March</dc:creator><dc:date>2021-04-08T20:17:25.947067138</dc:date><meta:editing-cycles>25</meta:editing-cycles><meta:editing-duration>P1DT5H15M2S</meta:editing-duration><meta:generator>LibreOffice/7.1.2.1$MacOSX_X86_64 LibreOffice_project/094b4116e8de6d2085e9b65d26912d6eac4c74a9</meta:generator><meta:document-statistic meta:table-count="1" meta:image-count="0" meta:object-count="0" meta:page-count="1" meta:paragraph-count="32" meta:word-count="249" meta:character-count="1664" meta:non-whitespace-character-count="1446"/><meta:user-defined meta:name="AppVersion">15.0000</meta:user-defined></office:meta>
+         <office:meta><dc:title>%TITLE%</dc:title><meta:creation-date>2021-03-08T17:40:14</meta:creation-date><meta:initial-creator>Jeremy March</meta:initial-creator><dc:language>en-US</dc:language><dc:creator>Jeremy March</dc:creator><dc:date>2021-04-08T20:17:25.947067138</dc:date><meta:editing-cycles>25</meta:editing-cycles><meta:editing-duration>P1DT5H15M2S</meta:editing-duration><meta:generator>LibreOffice/7.1.2.1$MacOSX_X86_64 LibreOffice_project/094b4116e8de6d2085e9b65d26912d6eac4c74a9</meta:generator><meta:document-statistic meta:table-count="1" meta:image-count="0" meta:object-count="0" meta:page-count="1" meta:paragraph-count="32" meta:word-count="249" meta:character-count="1664" meta:non-whitespace-character-count="1446"/><meta:user-defined meta:name="AppVersion">15.0000</meta:user-defined></office:meta>
          <office:settings>
           <config:config-item-set config:name="ooo:view-settings">
            <config:config-item config:name="ViewAreaTop" config:type="long">1803</config:config-item>
@@ -549,7 +340,7 @@ impl ExportDocument for ExportFodt {
           </office:script>
          </office:scripts>
          <office:font-face-decls>
-          <style:font-face style:name="IFAO-Grec Unicode" svg:font-family="&apos;IFAO-Grec Unicode&apos;"/>
+          <style:font-face style:name="%MAIN_FONT%" svg:font-family="&apos;%MAIN_FONT%&apos;"/>
           <style:font-face style:name="New Athena Unicode" svg:font-family="&apos;New Athena Unicode&apos;"/>
           <style:font-face style:name="Arial Unicode MS" svg:font-family="&apos;Arial Unicode MS&apos;" style:font-family-generic="system" style:font-pitch="variable"/>
           <style:font-face style:name="PingFang SC" svg:font-family="&apos;PingFang SC&apos;" style:font-family-generic="system" style:font-pitch="variable"/>
@@ -561,11 +352,11 @@ impl ExportDocument for ExportFodt {
            <style:paragraph-properties style:text-autospace="ideograph-alpha" style:line-break="strict" style:writing-mode="lr-tb" style:font-independent-line-spacing="false">
             <style:tab-stops/>
            </style:paragraph-properties>
-           <style:text-properties style:use-window-font-color="true" loext:opacity="0%" style:font-name="IFAO-Grec Unicode" fo:font-size="12pt" fo:language="en" fo:country="US" style:letter-kerning="true" style:font-name-asian="Songti SC" style:font-size-asian="12pt" style:language-asian="zh" style:country-asian="CN" style:font-name-complex="Arial Unicode MS" style:font-size-complex="12pt" style:language-complex="hi" style:country-complex="IN"/>
+           <style:text-properties style:use-window-font-color="true" loext:opacity="0%" style:font-name="%MAIN_FONT%" fo:font-size="%FONT_SIZE%" fo:language="en" fo:country="US" style:letter-kerning="true" style:font-name-asian="Songti SC" style:font-size-asian="%FONT_SIZE%" style:language-asian="zh" style:country-asian="CN" style:font-name-complex="Arial Unicode MS" style:font-size-complex="%FONT_SIZE%" style:language-complex="hi" style:country-complex="IN"/>
           </style:default-style>
           <style:default-style style:family="paragraph">
            <style:paragraph-properties fo:hyphenation-ladder-count="no-limit" style:text-autospace="ideograph-alpha" style:punctuation-wrap="hanging" style:line-break="strict" style:tab-stop-distance="0.4925in" style:writing-mode="lr-tb"/>
-           <style:text-properties style:use-window-font-color="true" loext:opacity="0%" style:font-name="IFAO-Grec Unicode" fo:font-size="12pt" fo:language="en" fo:country="US" style:letter-kerning="true" style:font-name-asian="Songti SC" style:font-size-asian="12pt" style:language-asian="zh" style:country-asian="CN" style:font-name-complex="Arial Unicode MS" style:font-size-complex="12pt" style:language-complex="hi" style:country-complex="IN" fo:hyphenate="false" fo:hyphenation-remain-char-count="2" fo:hyphenation-push-char-count="2" loext:hyphenation-no-caps="false"/>
+           <style:text-properties style:use-window-font-color="true" loext:opacity="0%" style:font-name="%MAIN_FONT%" fo:font-size="%FONT_SIZE%" fo:language="en" fo:country="US" style:letter-kerning="true" style:font-name-asian="Songti SC" style:font-size-asian="%FONT_SIZE%" style:language-asian="zh" style:country-asian="CN" style:font-name-complex="Arial Unicode MS" style:font-size-complex="%FONT_SIZE%" style:language-complex="hi" style:country-complex="IN" fo:hyphenate="false" fo:hyphenation-remain-char-count="2" fo:hyphenation-push-char-count="2" loext:hyphenation-no-caps="false"/>
           </style:default-style>
           <style:default-style style:family="table">
            <style:table-properties table:border-model="collapsing"/>
@@ -577,26 +368,26 @@ impl ExportDocument for ExportFodt {
            <style:paragraph-properties fo:margin-left="0in" fo:margin-right="0in" fo:margin-top="0in" fo:margin-bottom="0in" fo:line-height="130%" style:contextual-spacing="false" fo:text-align="justify" style:justify-single-word="false" fo:orphans="2" fo:widows="2" fo:hyphenation-ladder-count="no-limit" fo:text-indent="0in" style:auto-text-indent="false" style:writing-mode="lr-tb">
             <style:tab-stops/>
            </style:paragraph-properties>
-           <style:text-properties style:use-window-font-color="true" loext:opacity="0%" style:font-name="IFAO-Grec Unicode" fo:font-family="&apos;IFAO-Grec Unicode&apos;" fo:font-size="12pt" fo:language="en" fo:country="US" style:letter-kerning="true" style:font-name-asian="Songti SC" style:font-family-asian="&apos;Songti SC&apos;" style:font-family-generic-asian="system" style:font-pitch-asian="variable" style:font-size-asian="12pt" style:language-asian="zh" style:country-asian="CN" style:font-name-complex="IFAO-Grec Unicode" style:font-family-complex="&apos;IFAO-Grec Unicode&apos;" style:font-family-generic-complex="system" style:font-pitch-complex="variable" style:font-size-complex="12pt" style:language-complex="hi" style:country-complex="IN" fo:hyphenate="false" fo:hyphenation-remain-char-count="2" fo:hyphenation-push-char-count="2" loext:hyphenation-no-caps="false"/>
+           <style:text-properties style:use-window-font-color="true" loext:opacity="0%" style:font-name="%MAIN_FONT%" fo:font-family="&apos;%MAIN_FONT%&apos;" fo:font-size="%FONT_SIZE%" fo:language="en" fo:country="US" style:letter-kerning="true" style:font-name-asian="Songti SC" style:font-family-asian="&apos;Songti SC&apos;" style:font-family-generic-asian="system" style:font-pitch-asian="variable" style:font-size-asian="%FONT_SIZE%" style:language-asian="zh" style:country-asian="CN" style:font-name-complex="%MAIN_FONT%" style:font-family-complex="&apos;%MAIN_FONT%&apos;" style:font-family-generic-complex="system" style:font-pitch-complex="variable" style:font-size-complex="%FONT_SIZE%" style:language-complex="hi" style:country-complex="IN" fo:hyphenate="false" fo:hyphenation-remain-char-count="2" fo:hyphenation-push-char-count="2" loext:hyphenation-no-caps="false"/>
           </style:style>
 
           <style:style style:name="Heading" style:family="paragraph" style:parent-style-name="Standard" style:next-style-name="Text_20_body" style:default-outline-level="" style:class="text">
            <style:paragraph-properties fo:margin-top="0.1665in" fo:margin-bottom="0.0835in" style:contextual-spacing="false" fo:keep-with-next="always"/>
-           <style:text-properties style:font-name="IFAO-Grec Unicode" fo:font-family="&apos;IFAO-Grec Unicode&apos;" fo:font-size="14pt" style:font-name-asian="PingFang SC" style:font-family-asian="&apos;PingFang SC&apos;" style:font-family-generic-asian="system" style:font-pitch-asian="variable" style:font-size-asian="14pt" style:font-name-complex="Arial Unicode MS" style:font-family-complex="&apos;Arial Unicode MS&apos;" style:font-family-generic-complex="system" style:font-pitch-complex="variable" style:font-size-complex="14pt"/>
+           <style:text-properties style:font-name="%MAIN_FONT%" fo:font-family="&apos;%MAIN_FONT%&apos;" fo:font-size="14pt" style:font-name-asian="PingFang SC" style:font-family-asian="&apos;PingFang SC&apos;" style:font-family-generic-asian="system" style:font-pitch-asian="variable" style:font-size-asian="14pt" style:font-name-complex="Arial Unicode MS" style:font-family-complex="&apos;Arial Unicode MS&apos;" style:font-family-generic-complex="system" style:font-pitch-complex="variable" style:font-size-complex="14pt"/>
           </style:style>
           <style:style style:name="Text_20_body" style:display-name="Text body" style:family="paragraph" style:parent-style-name="Standard" style:default-outline-level="" style:class="text">
            <style:paragraph-properties fo:margin-top="0in" fo:margin-bottom="0in" style:contextual-spacing="false" fo:line-height="100%"/>
           </style:style>
           <style:style style:name="List" style:family="paragraph" style:parent-style-name="Text_20_body" style:default-outline-level="" style:class="list">
-           <style:text-properties style:font-name="IFAO-Grec Unicode" fo:font-family="&apos;IFAO-Grec Unicode&apos;" style:font-name-complex="Arial Unicode MS" style:font-family-complex="&apos;Arial Unicode MS&apos;" style:font-family-generic-complex="system" style:font-pitch-complex="variable"/>
+           <style:text-properties style:font-name="%MAIN_FONT%" fo:font-family="&apos;%MAIN_FONT%&apos;" style:font-name-complex="Arial Unicode MS" style:font-family-complex="&apos;Arial Unicode MS&apos;" style:font-family-generic-complex="system" style:font-pitch-complex="variable"/>
           </style:style>
           <style:style style:name="Caption" style:family="paragraph" style:parent-style-name="Standard" style:default-outline-level="" style:class="extra">
            <style:paragraph-properties fo:margin-top="0.0835in" fo:margin-bottom="0.0835in" style:contextual-spacing="false" text:number-lines="false" text:line-number="0"/>
-           <style:text-properties style:font-name="IFAO-Grec Unicode" fo:font-family="&apos;IFAO-Grec Unicode&apos;" fo:font-size="12pt" fo:font-style="italic" style:font-size-asian="12pt" style:font-style-asian="italic" style:font-name-complex="Arial Unicode MS" style:font-family-complex="&apos;Arial Unicode MS&apos;" style:font-family-generic-complex="system" style:font-pitch-complex="variable" style:font-size-complex="12pt" style:font-style-complex="italic"/>
+           <style:text-properties style:font-name="%MAIN_FONT%" fo:font-family="&apos;%MAIN_FONT%&apos;" fo:font-size="%FONT_SIZE%" fo:font-style="italic" style:font-size-asian="%FONT_SIZE%" style:font-style-asian="italic" style:font-name-complex="Arial Unicode MS" style:font-family-complex="&apos;Arial Unicode MS&apos;" style:font-family-generic-complex="system" style:font-pitch-complex="variable" style:font-size-complex="%FONT_SIZE%" style:font-style-complex="italic"/>
           </style:style>
           <style:style style:name="Index" style:family="paragraph" style:parent-style-name="Standard" style:default-outline-level="" style:class="index">
            <style:paragraph-properties text:number-lines="false" text:line-number="0"/>
-           <style:text-properties style:font-name="IFAO-Grec Unicode" fo:font-family="&apos;IFAO-Grec Unicode&apos;" style:font-name-complex="Arial Unicode MS" style:font-family-complex="&apos;Arial Unicode MS&apos;" style:font-family-generic-complex="system" style:font-pitch-complex="variable"/>
+           <style:text-properties style:font-name="%MAIN_FONT%" fo:font-family="&apos;%MAIN_FONT%&apos;" style:font-name-complex="Arial Unicode MS" style:font-family-complex="&apos;Arial Unicode MS&apos;" style:font-family-generic-complex="system" style:font-pitch-complex="variable"/>
           </style:style>
           <style:style style:name="GlossTableLemma" style:family="paragraph" style:parent-style-name="Standard" style:default-outline-level="">
            <style:paragraph-properties fo:margin-left="0.4in" fo:margin-right="0in" fo:line-height="100%" fo:text-align="start" style:justify-single-word="false" fo:orphans="0" fo:widows="0" fo:text-indent="-0.4in" style:auto-text-indent="false" style:writing-mode="lr-tb">
@@ -607,7 +398,7 @@ impl ExportDocument for ExportFodt {
            <style:paragraph-properties fo:margin-top="0in" fo:margin-bottom="0in" style:contextual-spacing="false" fo:line-height="100%" fo:text-align="start" style:justify-single-word="false" fo:orphans="0" fo:widows="0" style:writing-mode="lr-tb"/>
           </style:style>
           <style:style style:name="GlossInlineSections" style:family="paragraph" style:parent-style-name="Standard" style:default-outline-level="">
-           <style:paragraph-properties fo:line-height="150%" fo:text-align="start" style:justify-single-word="false" style:writing-mode="lr-tb"/>
+           <style:paragraph-properties fo:line-height="%LINE_HEIGHT%" fo:text-align="start" style:justify-single-word="false" style:writing-mode="lr-tb"/>
           </style:style>
           <style:style style:name="Table_20_Contents" style:display-name="Table Contents" style:family="paragraph" style:parent-style-name="Standard" style:class="extra">
            <style:paragraph-properties fo:orphans="0" fo:widows="0" text:number-lines="false" text:line-number="0"/>
@@ -683,38 +474,13 @@ impl ExportDocument for ExportFodt {
           </style:default-page-layout>
          </office:styles>
          <office:automatic-styles>
-          <style:style style:name="GlossTable" style:family="table">
-           <style:table-properties style:width="7.1799in" fo:margin-left="-0.2597in" fo:margin-top="0in" fo:margin-bottom="0in" table:align="left" fo:background-color="transparent" style:may-break-between-rows="false" style:writing-mode="lr-tb">
-            <style:background-image/>
-           </style:table-properties>
-          </style:style>
-          <style:style style:name="GlossTable.A" style:family="table-column">
-           <style:table-column-properties style:column-width="0.2563in"/>
-          </style:style>
-          <style:style style:name="GlossTable.B" style:family="table-column">
-           <style:table-column-properties style:column-width="3.4236in"/>
-          </style:style>
-          <style:style style:name="GlossTable.C" style:family="table-column">
-           <style:table-column-properties style:column-width="3.5in"/>
-          </style:style>
+%TABLE_STYLES%
           <style:style style:name="GlossTableCell" style:family="table-cell">
            <style:table-cell-properties fo:padding-left="0in" fo:padding-right="0.1201in" fo:padding-top="0.1097in" fo:padding-bottom="0in" fo:border="none"/>
           </style:style>
           <style:style style:name="GlossTableRow" style:family="table-row">
            <style:table-row-properties fo:keep-together="always"/>
           </style:style>
-          <style:style style:name="VerseTable" style:family="table">
-           <style:table-properties style:width="6.925in" style:may-break-between-rows="false" table:align="margins" style:writing-mode="lr-tb"/>
-          </style:style>
-          <style:style style:name="VerseTable.A" style:family="table-column">
-           <style:table-column-properties style:column-width="1.2139in" style:rel-column-width="1748*"/>
-          </style:style>
-          <style:style style:name="VerseTable.B" style:family="table-column">
-           <style:table-column-properties style:column-width="4.0625in" style:rel-column-width="5850*"/>
-          </style:style>
-          <style:style style:name="VerseTable.C" style:family="table-column">
-           <style:table-column-properties style:column-width="1.6486in" style:rel-column-width="2374*"/>
-          </style:style>
           <style:style style:name="VerseTable.A1" style:family="table-cell">
            <style:table-cell-properties fo:padding="0.0201in" fo:border="none" style:writing-mode="page"/>
           </style:style>
@@ -731,15 +497,15 @@ impl ExportDocument for ExportFodt {
            <style:paragraph-properties fo:margin-left="0.5in" fo:margin-right="0in" fo:line-height="100%" fo:text-align="end" style:justify-single-word="false" fo:orphans="0" fo:widows="0" fo:text-indent="-0.5in" style:auto-text-indent="false" style:writing-mode="lr-tb"/>
           </style:style>
           <style:style style:name="P5" style:family="paragraph" style:parent-style-name="Standard">
-           <style:paragraph-properties fo:line-height="150%" fo:text-align="start" style:justify-single-word="false" style:writing-mode="lr-tb"/>
+           <style:paragraph-properties fo:line-height="%LINE_HEIGHT%" fo:text-align="start" style:justify-single-word="false" style:writing-mode="lr-tb"/>
           </style:style>
           <style:style style:name="WorkTitleCenter" style:family="paragraph" style:parent-style-name="Standard">
-           <style:paragraph-properties fo:line-height="150%" fo:text-align="center" style:justify-single-word="false" style:writing-mode="lr-tb"/>
-           <style:text-properties style:font-name="IFAO-Grec Unicode" fo:font-weight="bold" style:font-weight-asian="bold" style:font-weight-complex="bold"/>
+           <style:paragraph-properties fo:line-height="%LINE_HEIGHT%" fo:text-align="center" style:justify-single-word="false" style:writing-mode="lr-tb"/>
+           <style:text-properties style:font-name="%MAIN_FONT%" fo:font-weight="bold" style:font-weight-asian="bold" style:font-weight-complex="bold"/>
           </style:style>
           <style:style style:name="P7" style:family="paragraph" style:parent-style-name="Standard">
-           <style:paragraph-properties fo:line-height="150%" fo:text-align="start" style:justify-single-word="false" style:writing-mode="lr-tb"/>
-           <style:text-properties style:font-name="IFAO-Grec Unicode"/>
+           <style:paragraph-properties fo:line-height="%LINE_HEIGHT%" fo:text-align="start" style:justify-single-word="false" style:writing-mode="lr-tb"/>
+           <style:text-properties style:font-name="%MAIN_FONT%"/>
           </style:style>
           <style:style style:name="P8" style:family="paragraph" style:parent-style-name="GlossTableLemma">
            <style:paragraph-properties fo:margin-left="0in" fo:margin-right="0in" fo:line-height="100%" fo:text-align="end" style:justify-single-word="false" fo:orphans="0" fo:widows="0" fo:text-indent="0in" style:auto-text-indent="false" style:writing-mode="lr-tb"/>
@@ -749,7 +515,7 @@ impl ExportDocument for ExportFodt {
            <style:text-properties officeooo:paragraph-rsid="0026e103"/>
           </style:style>
           <style:style style:name="T1" style:family="text">
-           <style:text-properties style:font-name="IFAO-Grec Unicode" fo:font-weight="bold" style:font-weight-asian="bold" style:font-weight-complex="bold"/>
+           <style:text-properties style:font-name="%MAIN_FONT%" fo:font-weight="bold" style:font-weight-asian="bold" style:font-weight-complex="bold"/>
           </style:style>
           <style:style style:name="T2" style:family="text">
            <style:text-properties fo:font-style="italic" style:font-style-asian="italic" style:font-style-complex="italic"/>
@@ -757,6 +523,9 @@ impl ExportDocument for ExportFodt {
           <style:style style:name="T3" style:family="text">
            <style:text-properties style:text-position="super 58%"/>
           </style:style>
+          <style:style style:name="Glossary" style:family="paragraph" style:parent-style-name="Standard">
+           <style:paragraph-properties fo:margin-left="0.25in" fo:text-indent="-0.25in" fo:line-height="100%"/>
+          </style:style>
           <style:style style:name="P18" style:family="paragraph" style:parent-style-name="GlossInlineSections">
            <style:paragraph-properties fo:text-align="center" style:justify-single-word="false"/>
            <style:text-properties officeooo:rsid="00283989" officeooo:paragraph-rsid="00283989"/>
@@ -772,14 +541,7 @@ impl ExportDocument for ExportFodt {
           <style:style style:name="HeaderLeft" style:family="paragraph" style:parent-style-name="Header">
            <style:text-properties officeooo:rsid="00283989" officeooo:paragraph-rsid="00283989"/>
           </style:style>
-          <style:style style:name="IndexOfArrowedWords" style:family="paragraph" style:parent-style-name="Standard">
-           <style:paragraph-properties fo:line-height="100%" fo:text-align="start" style:justify-single-word="false" style:writing-mode="lr-tb">
-            <style:tab-stops>
-             <style:tab-stop style:position="6.9402in" style:type="right" style:leader-style="dotted" style:leader-text="."/>
-            </style:tab-stops>
-           </style:paragraph-properties>
-           <style:text-properties officeooo:rsid="002d888b" officeooo:paragraph-rsid="002d888b"/>
-          </style:style>
+          %INDEX_STYLES%
           <style:style style:name="StartPageNumberStyle" style:family="paragraph" style:parent-style-name="Standard" style:master-page-name="Standard">
            <style:paragraph-properties fo:text-align="justify" style:justify-single-word="false" style:page-number="%PAGE_NUM%">
             <style:tab-stops/>
@@ -787,7 +549,7 @@ impl ExportDocument for ExportFodt {
            <style:text-properties officeooo:paragraph-rsid="0026e103"/>
           </style:style>
           <style:page-layout style:name="pm1">
-           <style:page-layout-properties fo:page-width="8.5in" fo:page-height="11in" style:num-format="1" style:print-orientation="portrait" fo:margin-top="0.7874in" fo:margin-bottom="0.7874in" fo:margin-left="0.7874in" fo:margin-right="0.7874in" style:writing-mode="lr-tb" style:layout-grid-color="#c0c0c0" style:layout-grid-lines="136" style:layout-grid-base-height="0.0693in" style:layout-grid-ruby-height="0in" style:layout-grid-mode="none" style:layout-grid-ruby-below="false" style:layout-grid-print="false" style:layout-grid-display="false" style:layout-grid-base-width="0.1665in" style:layout-grid-snap-to="true" style:footnote-max-height="0in">
+           <style:page-layout-properties fo:page-width="%PAPER_WIDTH%" fo:page-height="%PAPER_HEIGHT%" style:num-format="1" style:print-orientation="portrait" %MARGINS% style:writing-mode="lr-tb" style:layout-grid-color="#c0c0c0" style:layout-grid-lines="136" style:layout-grid-base-height="0.0693in" style:layout-grid-ruby-height="0in" style:layout-grid-mode="none" style:layout-grid-ruby-below="false" style:layout-grid-print="false" style:layout-grid-display="false" style:layout-grid-base-width="0.1665in" style:layout-grid-snap-to="true" style:footnote-max-height="0in">
             <style:footnote-sep style:width="0.0071in" style:distance-before-sep="0.0398in" style:distance-after-sep="0.0398in" style:line-style="solid" style:adjustment="left" style:rel-width="25%" style:color="#000000"/>
            </style:page-layout-properties>
            <style:header-style/>
@@ -797,10 +559,10 @@ impl ExportDocument for ExportFodt {
          <office:master-styles>
           <style:master-page style:name="Standard" style:page-layout-name="pm1">
           <style:header>
-           <text:p text:style-name="HeaderRight">%MAIN_TITLE%</text:p>
+           <text:p text:style-name="HeaderRight">%ODD_HEADER%</text:p>
           </style:header>
           <style:header-left>
-           <text:p text:style-name="HeaderLeft">LGI - UPPER LEVEL GREEK</text:p>
+           <text:p text:style-name="HeaderLeft">%EVEN_HEADER%</text:p>
           </style:header-left>
           <style:header-first>
            <text:p text:style-name="HeaderLeft"></text:p>
@@ -825,80 +587,371 @@ impl ExportDocument for ExportFodt {
            <text:p text:style-name="StartPageNumberStyle"><text:soft-page-break/></text:p>
 "###;
 
-        start
-            .replace("%MAIN_TITLE%", title)
-            .replace("%PAGE_NUM%", start_page.to_string().as_str())
+//the styles the rendered pages use
+const REQUIRED_DEFINITIONS: [(&str, &str); 30] = [
+    ("Standard", r#"style:name="Standard""#),
+    ("PIndented", r#"style:name="PIndented""#),
+    ("P1", r#"style:name="P1""#),
+    ("P5", r#"style:name="P5""#),
+    ("P8", r#"style:name="P8""#),
+    ("P18", r#"style:name="P18""#),
+    ("PageBreakStyle", r#"style:name="PageBreakStyle""#),
+    ("WorkTitleCenter", r#"style:name="WorkTitleCenter""#),
+    (
+        "IndexSection",
+        r#"%INDEX_STYLES%|style:name="IndexSection""#,
+    ),
+    (
+        "IndexOfArrowedWords",
+        r#"%INDEX_STYLES%|style:name="IndexOfArrowedWords""#,
+    ),
+    (
+        "IndexOfArrowedWordsBreak",
+        r#"%INDEX_STYLES%|style:name="IndexOfArrowedWordsBreak""#,
+    ),
+    ("IndexLetter", r#"%INDEX_STYLES%|style:name="IndexLetter""#),
+    (
+        "IndexLetterBreak",
+        r#"%INDEX_STYLES%|style:name="IndexLetterBreak""#,
+    ),
+    ("Table_20_Contents", r#"style:name="Table_20_Contents""#),
+    ("T1", r#"style:name="T1""#),
+    ("T2", r#"style:name="T2""#),
+    ("T3", r#"style:name="T3""#),
+    ("GlossTable", r#"%TABLE_STYLES%|style:name="GlossTable""#),
+    (
+        "GlossTable.A",
+        r#"%TABLE_STYLES%|style:name="GlossTable.A""#,
+    ),
+    (
+        "GlossTable.B",
+        r#"%TABLE_STYLES%|style:name="GlossTable.B""#,
+    ),
+    (
+        "GlossTable.C",
+        r#"%TABLE_STYLES%|style:name="GlossTable.C""#,
+    ),
+    ("GlossTableRow", r#"style:name="GlossTableRow""#),
+    ("GlossTableCell", r#"style:name="GlossTableCell""#),
+    ("GlossTableLemma", r#"style:name="GlossTableLemma""#),
+    ("GlossTableDef", r#"style:name="GlossTableDef""#),
+    ("VerseTable", r#"%TABLE_STYLES%|style:name="VerseTable""#),
+    (
+        "VerseTable.A",
+        r#"%TABLE_STYLES%|style:name="VerseTable.A""#,
+    ),
+    (
+        "VerseTable.B",
+        r#"%TABLE_STYLES%|style:name="VerseTable.B""#,
+    ),
+    (
+        "VerseTable.C",
+        r#"%TABLE_STYLES%|style:name="VerseTable.C""#,
+    ),
+    ("VerseTable.A1", r#"style:name="VerseTable.A1""#),
+];
+
+#[derive(Default)]
+pub struct ExportFodt {
+    pub config: ExportConfig,
+}
+
+impl ExportFodt {
+    //checks the configured template, if any, defines everything the pages use
+    pub fn validate_template(&self) -> Result<(), GlosserError> {
+        validate_template(
+            self.config.template.as_deref().unwrap_or(FODT_TEMPLATE),
+            &REQUIRED_DEFINITIONS,
+        )
+    }
+}
+
+impl ExportDocument for ExportFodt {
+    fn gloss_entry(&self, gloss_occurrance: &GlossOccurrance, lemma: Option<&str>) -> String {
+        if gloss_occurrance.arrowed_state != ArrowedState::Invisible
+            && let Some(lemma_unwrapped) = lemma
+            && let Some(gloss_unwrapped) = gloss_occurrance.gloss
+        {
+            format!(
+                r###"
+    <table:table-row table:style-name="GlossTableRow">
+      <table:table-cell table:style-name="GlossTableCell" office:value-type="string">
+        <text:p text:style-name="P8">{}</text:p>
+      </table:table-cell>
+      <table:table-cell table:style-name="GlossTableCell" office:value-type="string">
+        <text:p text:style-name="GlossTableLemma">{}</text:p>
+      </table:table-cell>
+      <table:table-cell table:style-name="GlossTableCell" office:value-type="string">
+        <text:p text:style-name="GlossTableDef">{}</text:p>
+      </table:table-cell>
+    </table:table-row>
+"###,
+                if gloss_occurrance.arrowed_state == ArrowedState::Arrowed {
+                    r##"→"##
+                } else {
+                    ""
+                },
+                escape_fodt(lemma_unwrapped),
+                escape_fodt(&gloss_unwrapped.def)
+            )
+        } else {
+            String::from("")
+        }
+    }
+
+    fn make_text(&self, page_text: &PageText) -> String {
+        let mut res = String::from("");
+
+        for block in &page_text.blocks {
+            match block {
+                TextBlock::Verse(lines) => {
+                    res.push_str(
+                        r###"
+    <table:table table:name="VerseTable" table:style-name="VerseTable">
+        <table:table-column table:style-name="VerseTable.A"/>
+        <table:table-column table:style-name="VerseTable.B"/>
+        <table:table-column table:style-name="VerseTable.C"/>
+"###,
+                    );
+                    for line in lines {
+                        res.push_str(
+                            complete_verse_line(
+                                line.speaker,
+                                &make_inlines(&line.inlines, self.config.appcrit_notes),
+                                line.display_number(),
+                            )
+                            .as_str(),
+                        );
+                    }
+                    res.push_str(
+                        r###"
+</table:table>
+"###,
+                    );
+                }
+                TextBlock::WorkTitle(title) => res.push_str(
+                    format!(
+                        r###"
+    <text:p text:style-name="WorkTitleCenter">{}</text:p>
+    <text:p text:style-name="Standard"></text:p>
+                        "###,
+                        escape_fodt(title)
+                    )
+                    .as_str(),
+                ),
+                TextBlock::SectionTitle(title) => res.push_str(
+                    format!(
+                        r###"
+    <text:p text:style-name="P18">{}</text:p>
+"###,
+                        escape_fodt(title)
+                    )
+                    .as_str(),
+                ),
+                TextBlock::Speaker(speaker) => res.push_str(
+                    format!(
+                        r###"
+    <text:p text:style-name="Standard">{}</text:p>
+"###,
+                        escape_fodt(speaker)
+                    )
+                    .as_str(),
+                ),
+                TextBlock::Paragraph { start, inlines } => res.push_str(
+                    format!(
+                        r###"
+    <text:p text:style-name="{}">
+{}
+    </text:p>
+"###,
+                        if *start == ParagraphStart::Indented {
+                            "PIndented"
+                        } else {
+                            "Standard"
+                        },
+                        make_inlines(inlines, self.config.appcrit_notes)
+                    )
+                    .as_str(),
+                ),
+            }
+        }
+
+        if !page_text.appcrits.is_empty() {
+            res.push_str("<text:p></text:p>\n");
+        }
+        for ap in &page_text.appcrits {
+            match ap.label(self.config.appcrit_notes) {
+                Some(label) => res.push_str(
+                    format!(
+                        r###"<text:p><text:span text:style-name="T1">{}</text:span> {}</text:p>"###,
+                        label,
+                        escape_fodt(&ap.text)
+                    )
+                    .as_str(),
+                ),
+                None => {
+                    res.push_str(format!("<text:p>{}</text:p>", escape_fodt(&ap.text)).as_str())
+                }
+            }
+            res.push('\n');
+        }
+
+        res
+    }
+
+    fn page_gloss_start(&self) -> String {
+        String::from(
+            r###"
+    <text:p text:style-name="P1"/>
+    <text:p text:style-name="P1"/>
+    <table:table table:name="Table1" table:style-name="GlossTable">
+      <table:table-column table:style-name="GlossTable.A"/>
+      <table:table-column table:style-name="GlossTable.B"/>
+      <table:table-column table:style-name="GlossTable.C"/>
+"###,
+        )
+    }
+
+    fn page_start(&self, _title: &str, _page_number: usize) -> String {
+        String::from(
+            r###"
+
+            "###,
+        )
+    }
+
+    fn page_end(&self) -> String {
+        String::from(
+            r###"
+    </table:table>
+    <text:p text:style-name="PageBreakStyle"/>
+"###,
+        )
+    }
+
+    fn document_end(&self) -> String {
+        String::from(
+            r###"<text:p text:style-name="P5"/>
+  </office:text>
+ </office:body>
+</office:document>
+"###,
+        )
+    }
+
+    fn document_start(&self, title: &str, start_page: usize) -> String {
+        let config = &self.config;
+        let (width, height) = config.paper.size();
+        let margins = match config.margins {
+            Some(m) => format!(
+                r#"fo:margin-top="{}" fo:margin-bottom="{}" fo:margin-left="{}" fo:margin-right="{}""#,
+                inches(m.top),
+                inches(m.bottom),
+                inches(m.inner),
+                inches(m.outer)
+            ),
+            None => String::from(
+                r#"fo:margin-top="0.7874in" fo:margin-bottom="0.7874in" fo:margin-left="0.7874in" fo:margin-right="0.7874in""#,
+            ),
+        };
+        let index_styles = index_styles(config);
+
+        config
+            .fill_template(FODT_TEMPLATE, title, start_page, &escape_fodt_text)
+            .replace("%MAIN_FONT%", &escape_fodt_text(config.main_font()))
+            .replace("%FONT_SIZE%", &format!("{}pt", config.font_size))
+            .replace(
+                "%LINE_HEIGHT%",
+                &format!("{}%", (config.line_spacing * 100.0).round()),
+            )
+            .replace("%TITLE%", &escape_fodt_text(&config.full_title(title)))
+            .replace("%PAPER_WIDTH%", &inches(width))
+            .replace("%PAPER_HEIGHT%", &inches(height))
+            .replace("%MARGINS%", &margins)
+            .replace("%INDEX_STYLES%", &index_styles)
+            .replace("%TABLE_STYLES%", &table_styles(config))
+            .replace("%ODD_HEADER%", &escape_fodt_text(config.odd_header(title)))
+            .replace(
+                "%EVEN_HEADER%",
+                &escape_fodt_text(config.even_header(COURSE_HEADER)),
+            )
     }
 
     fn make_index(&self, arrowed_words_index: &[ArrowedWordsIndex]) -> String {
+        let layout = &self.config.index;
         let mut res = String::from(
             r###"
         <text:p text:style-name="PageBreakStyle"/>
+        <text:section text:style-name="IndexSection" text:name="Index">
 "###,
         );
 
-        //let mut gloss_per_page = 0;
-        for a in arrowed_words_index {
+        let mut letter = None;
+        for (i, a) in arrowed_words_index.iter().enumerate() {
+            //the break goes on whichever paragraph comes first after it
+            let mut break_before = layout.break_before(i);
+            if layout.letter_headings {
+                let gloss_letter = index_letter(&a.gloss_lemma);
+                if letter != Some(gloss_letter) {
+                    res.push_str(
+                        format!(
+                            r###"
+        <text:p text:style-name="{}">{}</text:p>
+        "###,
+                            if break_before {
+                                "IndexLetterBreak"
+                            } else {
+                                "IndexLetter"
+                            },
+                            escape_fodt_text(&gloss_letter.to_string())
+                        )
+                        .as_str(),
+                    );
+                    letter = Some(gloss_letter);
+                    break_before = false;
+                }
+            }
             res.push_str(
                 format!(
                     r###"
-        <text:p text:style-name="IndexOfArrowedWords">{}<text:tab/>{}</text:p>
+        <text:p text:style-name="{}">{}<text:tab/>{}</text:p>
         "###,
+                    if break_before {
+                        "IndexOfArrowedWordsBreak"
+                    } else {
+                        "IndexOfArrowedWords"
+                    },
                     escape_fodt(&a.gloss_lemma),
                     a.page_number
                 )
                 .as_str(),
             );
-            //     gloss_per_page += 1;
-            //     if gloss_per_page > 43 {
-            //         gloss_per_page = 0;
-            //         res.push_str(
-            //             String::from(
-            //                 r###"
-            //         <text:p text:style-name="PageBreakStyle"/>
-            // "###,
-            //             )
-            //             .as_str(),
-            //         );
-            //     }
         }
+        res.push_str("\n        </text:section>\n");
         res
-        /*
-        const ARROWED_INDEX_TEMPLATE: &str = r##"
-        #set page(
-          header: context {
-            let page = counter(page).get().first() // Get current page number
-            if calc.odd(page) {
-              align(right, "INDEX OF ARROWED WORDS")
-            } else {
-              align(left, "LGI - UPPER LEVEL GREEK")
-            }
-          }
-        )
-        #indextable(
-        "##;
+    }
 
-        let mut latex = String::from(ARROWED_INDEX_TEMPLATE);
-        let mut gloss_per_page = 0;
-        for gloss in arrowed_words_index {
-            latex.push_str(
+    fn make_glossary(&self, glossary: &[GlossaryEntry]) -> String {
+        let mut res = String::from(
+            r###"
+        <text:p text:style-name="PageBreakStyle"/>
+"###,
+        );
+        for entry in glossary {
+            res.push_str(
                 format!(
-                    "[{} #box(width: 1fr, repeat[.])],[#box(width: 1fr, repeat[.]) {}],",
-                    gloss.gloss_lemma, gloss.page_number
+                    r###"
+        <text:p text:style-name="Glossary"><text:span text:style-name="T1">{}</text:span> ({}) {} ({})</text:p>
+        "###,
+                    escape_fodt(&entry.lemma),
+                    escape_fodt_text(&entry.pos),
+                    escape_fodt(&entry.def),
+                    entry.frequency()
                 )
                 .as_str(),
             );
-
-            gloss_per_page += 1;
-            if gloss_per_page > 43 {
-                gloss_per_page = 0;
-                //latex.push_str("\n#pagebreak()\n");
-                //latex.push_str("\\noindent \n");
-            }
         }
-        latex.push_str("\n)");
-        */
-        //latex
+        res
     }
 
     fn blank_page(&self) -> String {
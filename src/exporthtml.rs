@@ -2,273 +2,114 @@ use super::ExportDocument;
 use crate::ArrowedState;
 use crate::ArrowedWordsIndex;
 use crate::GlossOccurrance;
-use crate::HashMap;
-use crate::WordType;
-use crate::WordUuid;
-use regex::Regex;
+use crate::GlosserError;
+use crate::exportconfig::{AppCritStyle, ExportConfig, inches, validate_template};
+use crate::markup::{parse_markup_lenient, render_markup};
+use crate::model::{GlossaryEntry, Inline, PageText, ParagraphStart, TextBlock, index_letter};
+
+pub(crate) fn escape_html_text(s: &str) -> String {
+    s.replace("&", "&amp;")
+        .replace("\"", "&quot;")
+        .replace("'", "&#39;")
+        .replace(">", "&gt;")
+        .replace("<", "&lt;")
+}
+
+//for gloss lemmas and definitions, which keep their markup
+pub(crate) fn escape_html(s: &str) -> String {
+    render_markup(
+        &parse_markup_lenient(s),
+        &escape_html_text,
+        &|tag, inner| format!("<{}>{}</{}>", tag.name(), inner, tag.name()),
+    )
+}
 
 fn complete_verse_line(
-    verse_speaker: Option<String>,
+    verse_speaker: Option<&str>,
     verse_line: &str,
     verse_line_number: &str,
 ) -> String {
     format!(
         "<div class='VerseLine'><div class='VerseSpeaker'>{}</div><div class='VerseText'>{}</div><div class='VerseLineNumber'>{}</div></div>\n",
-        verse_speaker.as_ref().unwrap_or(&String::from("")),
+        escape_html_text(verse_speaker.unwrap_or("")),
         &verse_line,
-        if let Ok(i) = verse_line_number.parse::<i32>() {
-            if i % 5 == 0 { verse_line_number } else { "" }
-        } else {
-            &verse_line_number
-        }
+        escape_html_text(verse_line_number)
     )
 }
 
-pub struct ExportHTML {}
-impl ExportDocument for ExportHTML {
-    fn gloss_entry(&self, gloss_occurrance: &GlossOccurrance, lemma: Option<&str>) -> String {
-        let mut gloss_id = String::from("");
-        let mut pos = String::from("");
-        let mut def = String::from("");
-        let mut is_glossed = String::from("");
-        if let Some(gloss) = gloss_occurrance.gloss {
-            gloss_id = gloss.uuid.to_string();
-            pos = gloss.pos.clone();
-            def = gloss.def.clone();
-            is_glossed = String::from("hqListWord");
-        }
-
-        let real_lemma = if let Some(my_lemma) = lemma {
-            my_lemma.to_string()
-        } else {
-            gloss_occurrance.word.word.to_string()
-        };
-        let word_id = gloss_occurrance.word.uuid;
-        let running_count = gloss_occurrance.running_count.unwrap_or(0);
-        let total_count = gloss_occurrance.total_count.unwrap_or(0);
-        let arrowed_state_class = match gloss_occurrance.arrowed_state {
-            ArrowedState::Arrowed => "arrowedHere",
-            ArrowedState::Invisible => "alreadyArrowed",
-            _ => "",
-        };
-        format!(
-            r###"
-<div id="gloss-word-{word_id}" lemmaid="{gloss_id}" class="listword {is_glossed} {arrowed_state_class}" textseq="1" arrowedtextseq="1">
-    <div id="arrow{word_id}" class="listarrow"></div>
-    <div class="glossHangingIndentDiv">
-        <span class="realClickableGloss">
-            <span class="listheadword" id="listheadword{word_id}">{real_lemma}</span>.
-            &nbsp;&nbsp;<span class="listposwrapper">
-                (<span class="listpos" id="listpos{word_id}">{pos}</span>)
-            </span>
-            <span class="listdef" id="listdef{word_id}">{def}</span>
-        </span>
-        <span class="listfrequency" id="gloss-freq-word-{word_id}" glossid="gloss-freq-gloss-{gloss_id}">({running_count}&nbsp;of&nbsp;{total_count})</span>
-    </div>
-</div>
-"###
-        )
-    }
-
-    fn make_text(
-        &self,
-        gloss_occurrances: &[GlossOccurrance],
-        appcrit_hash: &HashMap<WordUuid, String>,
-    ) -> String {
-        let re = Regex::new("([0-9]+)[.]([0-9]+)").unwrap();
-        let mut res = String::from("");
-        let mut prev_non_space = true;
-        //let mut last_type = WordType::InvalidType;
-        let mut is_verse_section = false;
-        let mut verse_speaker: Option<String> = None;
-        let mut verse_line = String::from("");
-        let mut verse_line_number = String::from("");
-
-        let mut appcrits_page: Vec<String> = vec![];
-
-        let mut para_open = false;
-        let mut section_open = false;
-
-        res.push_str("<div class=\"TextContainer\">\n<div class=\"TextContainerInner\">\n");
-
-        //println!("page count {}", gloss_occurrances.len());
-        for w in gloss_occurrances {
-            if let Some(ap) = appcrit_hash.get(&w.word.uuid) {
-                appcrits_page.push(ap.clone());
-            }
-            //println!("word type {:?}", w.word.word_type);
-
-            match w.word.word_type {
-                WordType::VerseLine => {
-                    if !is_verse_section {
-                        //res.push_str(r##"<div class="VerseLine">"##);
-                        is_verse_section = true;
-                    } else {
-                        //previous verse line is complete
-                        res.push_str(
-                            complete_verse_line(verse_speaker, &verse_line, &verse_line_number)
-                                .as_str(),
-                        );
-                        verse_speaker = None;
-                        verse_line = String::from("");
-                    }
-                    verse_line_number = w.word.word.replace("[line]", "");
-                }
-                WordType::WorkTitle => res
-                    .push_str(format!("<div class='TextTitle'>{}</div>\n", &w.word.word).as_str()),
-                WordType::Word | WordType::Punctuation => {
-                    //0 | 1
-                    let punc = vec![
-                        ".", ",", "·", "·", ";", ";", ">", "]", ")", ",\"", ".”", ".\"", "·\"",
-                        "·\"", ".’",
-                    ];
-                    let s = format!(
-                        "{}{}",
-                        if punc.contains(&w.word.word.as_str()) || prev_non_space {
-                            ""
-                        } else {
-                            " "
-                        },
-                        w.word.word
-                    );
-                    let this_word = format!(
-                        "<span id='text-word-{}' class='textword'>{}</span>",
-                        w.word.uuid, s
-                    );
-                    if is_verse_section {
-                        verse_line.push_str(&this_word);
-                    } else {
-                        res.push_str(&this_word);
-                    }
-                    prev_non_space = w.word.word == "<" || w.word.word == "[" || w.word.word == "(";
-                }
-                WordType::ParaWithIndent => {
-                    if para_open {
-                        res.push_str("\n</div><!--Close ParaIndented-->\n");
-                    }
-                    para_open = true;
-                    res.push_str("\n<div class='ParaIndented'>\n");
+fn make_inlines(inlines: &[Inline], style: AppCritStyle) -> String {
+    let mut res = String::from("");
+    for inline in inlines {
+        match inline {
+            Inline::Word { word, space_before } => res.push_str(
+                format!(
+                    "<span id='text-word-{}' class='textword'>{}{}</span>",
+                    word.uuid,
+                    if *space_before { " " } else { "" },
+                    escape_html_text(&word.word)
+                )
+                .as_str(),
+            ),
+            //To Do: for the next thee formats move space to start of line
+            Inline::Section { number, major } => res.push_str(
+                if *major {
+                    format!("<span class='Section'>{}</span>\n", number)
+                } else {
+                    format!("<span class='SubSection'>{}</span>\n", number)
                 }
-                WordType::ParaNoIndent => {
-                    if para_open {
-                        res.push_str("\n</div><!--Close ParaNotIndented-->\n");
-                    }
-                    para_open = true;
-                    res.push_str("\n<div class='ParaNotIndented'>\n");
+                .as_str(),
+            ),
+            Inline::Speaker(speaker) => res.push_str(
+                format!(
+                    " <span class='InlineSpeaker'>{}</span> ",
+                    escape_html_text(speaker)
+                )
+                .as_str(),
+            ),
+            Inline::AppCritMark(number) => {
+                if style == AppCritStyle::Numbered {
+                    res.push_str(format!("<sup class='appcrit-mark'>{}</sup>", number).as_str())
                 }
-                WordType::SectionTitle => res.push_str(
-                    format!("<div class='SectionTitle'>{}</div>\n", &w.word.word).as_str(),
-                ),
-                WordType::Section => {
-                    if section_open {
-                        //res.push_str("\n</span><!--Close Section-->\n");
-                        section_open = false;
-                    }
-                    let section_input = w.word.word.replace("[section]", "");
-
-                    let matches = re.captures(&section_input);
-
-                    let s = if let Some(matches) = matches {
-                        let section = matches.get(1).unwrap().as_str();
-                        let subsection = matches.get(2).unwrap().as_str();
-
-                        //To Do: for the next thee formats move space to start of line
-                        if subsection == "1" {
-                            format!("<span class='Section'>{}</span>\n", section)
-                        } else {
-                            format!("<span class='SubSection'>{}</span>\n", subsection)
-                        }
-                    } else {
-                        format!("<span class='Section'>{}</span>\n", section_input)
-                    };
-
-                    res.push_str(s.as_str());
-                    //if last_type == WordType::InvalidType || last_type == WordType::ParaWithIndent {
-                    //-1 || 6
-                    prev_non_space = true;
-                    // } else {
-                    //     prev_non_space = false;
-                    // }
-                }
-                WordType::Speaker => {
-                    let s = format!("<span class='Speaker'>{}</span> ", w.word.word);
-                    res.push_str(s.as_str());
-                }
-                WordType::InlineSpeaker => {
-                    if is_verse_section {
-                        verse_speaker = Some(w.word.word.clone());
-                    } else {
-                        res.push_str(
-                            format!(" <span class='InlineSpeaker'>{}</span> ", w.word.word)
-                                .as_str(),
-                        );
-                    }
-                }
-                WordType::InlineVerseSpeaker => {
-                    if is_verse_section {
-                        verse_speaker = Some(w.word.word.clone());
-                    } else {
-                        res.push_str(
-                            format!(" <span class='InlineSpeaker'>{}</span> ", w.word.word)
-                                .as_str(),
-                        );
-                    }
-                }
-                _ => (),
             }
         }
-
-        if is_verse_section {
-            //previous verse line is complete
-            res.push_str(
-                complete_verse_line(verse_speaker, &verse_line, &verse_line_number).as_str(),
-            );
-        }
-
-        if para_open {
-            res.push_str("\n</div><!--Close ParaNotIndented-->\n");
-        }
-        if section_open {
-            //res.push_str("\n</div><!--Close Section-->\n");
-        }
-
-        if !appcrits_page.is_empty() {
-            res.push_str("\n\n<div class='AppCritDiv'>\n");
-        }
-        for ap in &appcrits_page {
-            res.push_str(format!("<div class='appcrit'>{}</div>\n", &ap).as_str());
-        }
-        if !appcrits_page.is_empty() {
-            res.push_str("\n</div><!--End App Crit Div-->\n");
-        }
-        res.push_str("</div></div><!--End TextContainer-->\n");
-        res
-    }
-
-    fn page_gloss_start(&self) -> String {
-        String::from("<div class=\"GlossContainer\"><div class='gloss-table'>\n")
-    }
-
-    fn page_start(&self, title: &str, page_number: usize) -> String {
-        format!(
-            "\n<!--PAGE START-->\n<div class='Page'>\n<div class='PageTitle'>{title} - Page {page_number}</div>\n"
-        )
     }
+    res
+}
 
-    fn page_end(&self) -> String {
-        String::from("\n</div>\n</div><!--Gloss table end-->\n</div><!--END PAGE-->\n")
-    }
+//the index of arrowed words, with a box which filters it, ignoring accents and case
+const INDEX_START: &str = r##"
+<div class='Index' id='index'>
+<h2>Index of Arrowed Words</h2>
+<input type='search' id='index-filter' placeholder='Filter' autocomplete='off'>
+"##;
 
-    fn document_end(&self) -> String {
-        String::from("\n</body></html>\n")
-    }
+const INDEX_END: &str = r##"</div>
+<script>
+(function () {
+    var plain = function (s) {
+        return s.normalize("NFD").replace(/[\u0300-\u036f]/g, "").toLowerCase();
+    };
+    document.getElementById("index-filter").addEventListener("input", function () {
+        var filter = plain(this.value.trim());
+        document.querySelectorAll("#index .IndexLetter").forEach(function (letter) {
+            var shown = 0;
+            letter.querySelectorAll(".IndexEntry").forEach(function (entry) {
+                var show = plain(entry.firstChild.textContent).indexOf(filter) !== -1;
+                entry.style.display = show ? "" : "none";
+                shown += show ? 1 : 0;
+            });
+            letter.style.display = shown > 0 ? "" : "none";
+        });
+    });
+})();
+</script>
+"##;
 
-    fn document_start(&self, _title: &str, _start_page: usize) -> String {
-        String::from(
-            r##"<html lang="en">
+//the built-in document start, which ExportConfig::template replaces
+pub const HTML_TEMPLATE: &str = r##"<html lang="en">
     <head>
         <meta charset="UTF-8">
-        <title>Greek Vocab DB2</title>
+        <title>%TITLE%</title>
         <meta http-equiv="content-type" content="text/html; charset=utf-8">
         <meta http-equiv="Cache-Control" content="no-cache, no-store, must-revalidate">
         <meta http-equiv="Pragma" content="no-cache">
@@ -287,13 +128,15 @@ impl ExportDocument for ExportHTML {
                 font-family: "WebIFAO";
                 src:
                   local("IFAO-Grec-Unicode"),
-                  url("./IFAOGrec.ttf") format("truetype");
+                  url("./%FONT_FILE%") format("truetype");
               }
-        BODY { font-family: WebIFAO, WebNewAthenaUnicode, NewAthenaUnicode, helvetica,
+        @page { size: %PAPER%;%MARGIN% }
+        BODY { font-family: %FONTS%WebIFAO, WebNewAthenaUnicode, NewAthenaUnicode, helvetica,
                   arial;
+              font-size: %FONT_SIZE%pt;
               width: 800px;
               margin: 20px auto;
-              line-height: 1.5;
+              line-height: %LINE_SPACING%;
         }
         .Page { border-top: 2px solid black; position: relative; }
         .PageTitle { display:none; margin-bottom: 20px; }
@@ -303,7 +146,15 @@ impl ExportDocument for ExportHTML {
         .VerseLine { display: flex; position: relative; left: 60px;}
         .VerseText { width: 360px; }
         .AppCritDiv { margin: 20px 0px; }
+        .appcrit-label { font-weight: bold; }
+        .appcrit-mark { font-size: 0.7em; }
         .gloss-table { border-top: 2px solid red; margin: 20px 0px; padding: 10px; }
+        .Index { border-top: 2px solid black; }
+        .Index ul { list-style: none; padding-left: 0px; column-count: 2; }
+        .IndexEntry { break-inside: avoid; }
+        .Glossary { border-top: 2px solid black; }
+        .Glossary ul { list-style: none; padding-left: 2em; text-indent: -2em; }
+        .GlossaryLemma { font-weight: bold; }
         .arrowedHere .listarrow::after {
           content: "→";
           top: -2px;
@@ -359,12 +210,263 @@ impl ExportDocument for ExportHTML {
 
         </style>
     </head>
-    <body class="split hide-arrowed">"##,
+    <body class="split hide-arrowed">"##;
+
+//the pages are written straight into the body
+const REQUIRED_DEFINITIONS: [(&str, &str); 1] = [("<body>", r"<body[\s>]")];
+
+#[derive(Default)]
+pub struct ExportHTML {
+    pub config: ExportConfig,
+}
+
+impl ExportHTML {
+    //checks the configured template, if any, defines everything the pages use
+    pub fn validate_template(&self) -> Result<(), GlosserError> {
+        validate_template(
+            self.config.template.as_deref().unwrap_or(HTML_TEMPLATE),
+            &REQUIRED_DEFINITIONS,
         )
     }
+}
+
+impl ExportDocument for ExportHTML {
+    fn gloss_entry(&self, gloss_occurrance: &GlossOccurrance, lemma: Option<&str>) -> String {
+        let mut gloss_id = String::from("");
+        let mut pos = String::from("");
+        let mut def = String::from("");
+        let mut is_glossed = String::from("");
+        if let Some(gloss) = gloss_occurrance.gloss {
+            gloss_id = gloss.uuid.to_string();
+            pos = escape_html_text(&gloss.pos);
+            def = escape_html(&gloss.def);
+            is_glossed = String::from("hqListWord");
+        }
 
-    fn make_index(&self, _arrowed_words_index: &[ArrowedWordsIndex]) -> String {
-        String::from("\n<!--INDEX-->\n")
+        let real_lemma = if let Some(my_lemma) = lemma {
+            escape_html(my_lemma)
+        } else {
+            escape_html(&gloss_occurrance.word.word)
+        };
+        let word_id = gloss_occurrance.word.uuid;
+        let running_count = gloss_occurrance.running_count.unwrap_or(0);
+        let total_count = gloss_occurrance.total_count.unwrap_or(0);
+        let arrowed_state_class = match gloss_occurrance.arrowed_state {
+            ArrowedState::Arrowed => "arrowedHere",
+            ArrowedState::Invisible => "alreadyArrowed",
+            _ => "",
+        };
+        format!(
+            r###"
+<div id="gloss-word-{word_id}" lemmaid="{gloss_id}" class="listword {is_glossed} {arrowed_state_class}" textseq="1" arrowedtextseq="1">
+    <div id="arrow{word_id}" class="listarrow"></div>
+    <div class="glossHangingIndentDiv">
+        <span class="realClickableGloss">
+            <span class="listheadword" id="listheadword{word_id}">{real_lemma}</span>.
+            &nbsp;&nbsp;<span class="listposwrapper">
+                (<span class="listpos" id="listpos{word_id}">{pos}</span>)
+            </span>
+            <span class="listdef" id="listdef{word_id}">{def}</span>
+        </span>
+        <span class="listfrequency" id="gloss-freq-word-{word_id}" glossid="gloss-freq-gloss-{gloss_id}">({running_count}&nbsp;of&nbsp;{total_count})</span>
+    </div>
+</div>
+"###
+        )
+    }
+
+    fn make_text(&self, page_text: &PageText) -> String {
+        let mut res = String::from("");
+
+        res.push_str("<div class=\"TextContainer\">\n<div class=\"TextContainerInner\">\n");
+
+        for block in &page_text.blocks {
+            match block {
+                TextBlock::Verse(lines) => {
+                    for line in lines {
+                        res.push_str(
+                            complete_verse_line(
+                                line.speaker,
+                                &make_inlines(&line.inlines, self.config.appcrit_notes),
+                                line.display_number(),
+                            )
+                            .as_str(),
+                        );
+                    }
+                }
+                TextBlock::WorkTitle(title) => {
+                    res.push_str(format!("<div class='TextTitle'>{}</div>\n", escape_html_text(title)).as_str())
+                }
+                TextBlock::SectionTitle(title) => {
+                    res.push_str(format!(
+                    "<div class='SectionTitle'>{}</div>\n",
+                    escape_html_text(title)
+                ).as_str())
+                }
+                TextBlock::Speaker(speaker) => {
+                    res.push_str(format!(
+                    "<span class='Speaker'>{}</span> ",
+                    escape_html_text(speaker)
+                ).as_str())
+                }
+                TextBlock::Paragraph { start, inlines } => match start {
+                    ParagraphStart::Indented => res.push_str(
+                        format!(
+                            "\n<div class='ParaIndented'>\n{}\n</div><!--Close ParaIndented-->\n",
+                            make_inlines(inlines, self.config.appcrit_notes)
+                        )
+                        .as_str(),
+                    ),
+                    ParagraphStart::NotIndented => res.push_str(
+                        format!(
+                            "\n<div class='ParaNotIndented'>\n{}\n</div><!--Close ParaNotIndented-->\n",
+                            make_inlines(inlines, self.config.appcrit_notes)
+                        )
+                        .as_str(),
+                    ),
+                    //the rest of a paragraph begun on the previous page
+                    ParagraphStart::Continued => res.push_str(&make_inlines(inlines, self.config.appcrit_notes)),
+                },
+            }
+        }
+
+        if !page_text.appcrits.is_empty() {
+            res.push_str("\n\n<div class='AppCritDiv'>\n");
+        }
+        for ap in &page_text.appcrits {
+            match ap.label(self.config.appcrit_notes) {
+                Some(label) => res.push_str(
+                    format!(
+                        "<div class='appcrit'><span class='appcrit-label'>{}</span> {}</div>\n",
+                        label,
+                        escape_html(&ap.text)
+                    )
+                    .as_str(),
+                ),
+                None => res.push_str(
+                    format!("<div class='appcrit'>{}</div>\n", escape_html(&ap.text)).as_str(),
+                ),
+            }
+        }
+        if !page_text.appcrits.is_empty() {
+            res.push_str("\n</div><!--End App Crit Div-->\n");
+        }
+        res.push_str("</div></div><!--End TextContainer-->\n");
+        res
+    }
+
+    fn page_gloss_start(&self) -> String {
+        String::from("<div class=\"GlossContainer\"><div class='gloss-table'>\n")
+    }
+
+    fn page_start(&self, title: &str, page_number: usize) -> String {
+        format!(
+            "\n<!--PAGE START-->\n<div class='Page' id='page-{page_number}'>\n<div class='PageTitle'>{} - Page {page_number}</div>\n",
+            escape_html_text(title)
+        )
+    }
+
+    fn page_end(&self) -> String {
+        String::from("\n</div>\n</div><!--Gloss table end-->\n</div><!--END PAGE-->\n")
+    }
+
+    fn document_end(&self) -> String {
+        String::from("\n</body></html>\n")
+    }
+
+    fn document_start(&self, title: &str, start_page: usize) -> String {
+        let config = &self.config;
+        let (width, height) = config.paper.size();
+        let margin = match config.margins {
+            Some(m) => format!(
+                " margin: {} {} {} {};",
+                inches(m.top),
+                inches(m.outer),
+                inches(m.bottom),
+                inches(m.inner)
+            ),
+            None => String::from(""),
+        };
+        //the bundled font comes after the configured families, for when they aren't installed
+        let fonts: String = config
+            .font_families
+            .iter()
+            .map(|f| format!("\"{}\", ", escape_html_text(f)))
+            .collect();
+
+        config
+            .fill_template(HTML_TEMPLATE, title, start_page, &escape_html_text)
+            .replace("%TITLE%", &escape_html_text(&config.full_title(title)))
+            .replace("%PAPER%", &format!("{} {}", inches(width), inches(height)))
+            .replace("%MARGIN%", &margin)
+            .replace("%FONTS%", &fonts)
+            .replace(
+                "%FONT_FILE%",
+                config.font_file.as_deref().unwrap_or("IFAOGrec.ttf"),
+            )
+            .replace("%FONT_SIZE%", &config.font_size.to_string())
+            .replace("%LINE_SPACING%", &config.line_spacing.to_string())
+    }
+
+    fn make_index(&self, arrowed_words_index: &[ArrowedWordsIndex]) -> String {
+        let mut res = String::from(INDEX_START);
+        let mut letter = None;
+        for gloss in arrowed_words_index {
+            let gloss_letter = index_letter(&gloss.gloss_lemma);
+            if letter != Some(gloss_letter) {
+                if letter.is_some() {
+                    res.push_str("</ul>\n</div>\n");
+                }
+                res.push_str(
+                    format!(
+                        "<div class='IndexLetter'>\n<h3>{}</h3>\n<ul>\n",
+                        escape_html_text(&gloss_letter.to_string())
+                    )
+                    .as_str(),
+                );
+                letter = Some(gloss_letter);
+            }
+            res.push_str(
+                format!(
+                    "<li class='IndexEntry'><a href='#gloss-word-{}'>{}</a> <a class='IndexPage' href='#page-{}'>{}</a></li>\n",
+                    gloss.word_uuid,
+                    escape_html(&gloss.gloss_lemma),
+                    gloss.page_number,
+                    gloss.page_number
+                )
+                .as_str(),
+            );
+        }
+        if letter.is_some() {
+            res.push_str("</ul>\n</div>\n");
+        }
+        res.push_str(INDEX_END);
+        res
+    }
+
+    fn make_glossary(&self, glossary: &[GlossaryEntry]) -> String {
+        let mut res =
+            String::from("\n<div class='Glossary' id='glossary'>\n<h2>Glossary</h2>\n<ul>\n");
+        for entry in glossary {
+            res.push_str(
+                format!(
+                    "<li class='GlossaryEntry'><span class='GlossaryLemma'>{}</span> ({}) {} ({})</li>\n",
+                    escape_html(&entry.lemma),
+                    escape_html_text(&entry.pos),
+                    escape_html(&entry.def),
+                    match entry.arrowed_page {
+                        Some(page) => format!(
+                            "{}×, <a href='#page-{}'>→ p. {}</a>",
+                            entry.total_count, page, page
+                        ),
+                        None => entry.frequency(),
+                    }
+                )
+                .as_str(),
+            );
+        }
+        res.push_str("</ul>\n</div>\n");
+        res
     }
 
     fn blank_page(&self) -> String {
@@ -2,49 +2,192 @@ use super::ExportDocument;
 use crate::ArrowedState;
 use crate::ArrowedWordsIndex;
 use crate::GlossOccurrance;
-use crate::HashMap;
-use crate::WordType;
-use crate::WordUuid;
-use regex::Regex;
+use crate::GlosserError;
+use crate::exportconfig::{AppCritStyle, ExportConfig, Leader, Paper, inches, validate_template};
+use crate::markup::{MarkupTag, parse_markup_lenient, render_markup};
+use crate::model::{GlossaryEntry, Inline, PageText, ParagraphStart, TextBlock, index_letter};
 
 //https://tex.stackexchange.com/questions/34580/escape-character-in-latex
+fn escape_latex_text(s: &str) -> String {
+    let mut res = String::from("");
+    for c in s.chars() {
+        match c {
+            '\\' => res.push_str("\\textbackslash{}"),
+            '~' => res.push_str("\\textasciitilde{}"),
+            '^' => res.push_str("\\textasciicircum{}"),
+            '{' | '}' | '&' | '%' | '$' | '#' | '_' => {
+                res.push('\\');
+                res.push(c);
+            }
+            _ => res.push(c),
+        }
+    }
+    res
+}
+
 fn escape_latex(s: &str) -> String {
-    s.replace("\\", "\\textbackslash")
-        .replace("{", "\\{")
-        .replace("}", "\\}")
-        .replace("<i>", "\\textit{")
-        .replace("</i>", "}")
-        .replace("<b>", "\\textbf{")
-        .replace("<sup>", "\\textsuperscript{")
-        .replace("</sup>", "}")
-        .replace("</b>", "}")
-        .replace("&", "\\&")
-        .replace("%", "\\%")
-        .replace("$", "\\$")
-        .replace("#", "\\#")
-        .replace("_", "\\_")
-        .replace("~", "\\textasciitilde")
-        .replace("^", "\\textasciicircum")
+    render_markup(
+        &parse_markup_lenient(s),
+        &escape_latex_text,
+        &|tag, inner| match tag {
+            MarkupTag::Italic => format!("\\textit{{{}}}", inner),
+            MarkupTag::Bold => format!("\\textbf{{{}}}", inner),
+            MarkupTag::Superscript => format!("\\textsuperscript{{{}}}", inner),
+        },
+    )
 }
 
 fn complete_verse_line(
-    verse_speaker: Option<String>,
+    verse_speaker: Option<&str>,
     verse_line: &str,
     verse_line_number: &str,
 ) -> String {
     format!(
         "{} & {} & {} \\\\\n",
-        verse_speaker.as_ref().unwrap_or(&String::from("")),
+        verse_speaker.unwrap_or(""),
         &verse_line,
-        if let Ok(i) = verse_line_number.parse::<i32>() {
-            if i % 5 == 0 { verse_line_number } else { "" }
-        } else {
-            &verse_line_number
-        }
+        verse_line_number
     )
 }
 
-pub struct ExportLatex {}
+fn make_inlines(inlines: &[Inline], style: AppCritStyle) -> String {
+    let mut res = String::from("");
+    for inline in inlines {
+        match inline {
+            Inline::Word { word, space_before } => {
+                if *space_before {
+                    res.push(' ');
+                }
+                res.push_str(&word.word);
+            }
+            //To Do: for the next thee formats move space to start of line
+            Inline::Section { number, major } => res.push_str(
+                if *major {
+                    format!("\\hspace{{0pt}}\\marginsec{{{}}} ", number)
+                } else {
+                    format!("\\hspace{{0pt}}\\marginseclight{{{}}} ", number)
+                }
+                .as_str(),
+            ),
+            Inline::Speaker(speaker) => {
+                res.push_str(format!("\\par \\textbf{{{}}} ", speaker).as_str())
+            }
+            Inline::AppCritMark(number) => {
+                if style == AppCritStyle::Numbered {
+                    res.push_str(format!("\\textsuperscript{{{}}}", number).as_str())
+                }
+            }
+        }
+    }
+    res
+}
+
+//the built-in document start, which ExportConfig::template replaces
+pub const LATEX_TEMPLATE: &str = r###"\documentclass[twoside,openright,%FONT_SIZE%%PAPER%]{book}
+%\usepackage[margin=1.0in]{geometry}
+\usepackage[twoside, %GEOMETRY%]{geometry} %bindingoffset=0.5in,
+\usepackage[utf8]{inputenc}
+\usepackage{fontspec}
+\usepackage{array}
+\usepackage{booktabs}
+\usepackage{ragged2e}
+\usepackage{setspace}
+\usepackage{navigator}
+
+\newcommand{\GlossLineSpacing}{%LINE_SPACING%}
+
+
+\setmainfont[Scale=MatchUppercase,Ligatures=TeX, BoldFont={*BOLD}, ItalicFont={%MAIN_FONT%}, ItalicFeatures={FakeSlant=0.2}]{%MAIN_FONT%}
+%\setmainlanguage[variant=polytonic]{greek}
+\tolerance=10000 % https://www.texfaq.org/FAQ-overfull
+\setlength{\extrarowheight}{8pt}
+\newcolumntype{L}{>{\setlength{\RaggedRight\parindent}{-2em}\leftskip 2em}p}
+\newcolumntype{D}{>{\setlength{\RaggedRight}}p}
+
+\usepackage{fancyhdr} % http://tug.ctan.org/tex-archive/macros/latex/contrib/fancyhdr/fancyhdr.pdf
+
+\pagestyle{fancy}
+\fancyhf{}
+\renewcommand{\headrulewidth}{0.0pt}
+  \fancyhead[EL]{%EVEN_HEADER%}% Title on Even page, Centered
+  \fancyhead[OR]{}% Author on Odd page, Centered
+\setlength{\headheight}{14.49998pt}
+\cfoot{\thepage}
+
+%\usepackage{enumitem}
+%\SetLabelAlign{margin}{\llap{#1~~}}
+%\usepackage{showframe} % just to show the margins
+%https://tex.stackexchange.com/questions/223701/labels-in-the-left-margin
+
+%https://tex.stackexchange.com/questions/40748/use-sections-inline
+\newcommand{\marginsec}[1]{\vadjust{\vbox to 0pt{\sbox0{\bfseries#1\quad}\kern-0.89em\llap{\box0}}}}
+\newcommand{\marginseclight}[1]{\vadjust{\vbox to 0pt{\sbox0{\footnotesize#1\hspace{0.25em}\quad}\kern-0.85em\llap{\box0}}}}
+\usepackage[none]{hyphenat}
+\usepackage[polutonikogreek,english]{babel} %https://tex.stackexchange.com/questions/13067/utf8x-vs-utf8-inputenc
+\usepackage{microtype}
+\usepackage{multicol}
+\begin{document}
+%FONT_SIZE_COMMAND%%\clearpage
+\setcounter{page}{%PAGE_NUM%}
+%\newpage
+%\mbox{}
+\newpage
+"###;
+
+//what the rendered pages use, with where each is defined
+//for an index in more than one column
+const MULTICOL: &str = r"\\usepackage(?:\[[^\]]*\])?\{[^}]*\bmulticol\b";
+
+const REQUIRED_DEFINITIONS: [(&str, &str); 10] = [
+    (
+        r"\marginsec",
+        r"\\(?:(?:re|provide)?newcommand\*?\s*\{?\s*\\marginsec\b|def\s*\\marginsec\b)",
+    ),
+    (
+        r"\marginseclight",
+        r"\\(?:(?:re|provide)?newcommand\*?\s*\{?\s*\\marginseclight\b|def\s*\\marginseclight\b)",
+    ),
+    (
+        r"\GlossLineSpacing",
+        r"\\(?:(?:re|provide)?newcommand\*?\s*\{?\s*\\GlossLineSpacing\b|def\s*\\GlossLineSpacing\b)",
+    ),
+    ("the L column type", r"\\newcolumntype\s*\{L\}"),
+    ("the D column type", r"\\newcolumntype\s*\{D\}"),
+    ("array", r"\\usepackage(?:\[[^\]]*\])?\{[^}]*\barray\b"),
+    (
+        "setspace",
+        r"\\usepackage(?:\[[^\]]*\])?\{[^}]*\bsetspace\b",
+    ),
+    (
+        "fancyhdr",
+        r"\\usepackage(?:\[[^\]]*\])?\{[^}]*\bfancyhdr\b",
+    ),
+    (
+        "fontspec",
+        r"\\usepackage(?:\[[^\]]*\])?\{[^}]*\bfontspec\b",
+    ),
+    (r"\begin{document}", r"\\begin\{document\}"),
+];
+
+#[derive(Default)]
+pub struct ExportLatex {
+    pub config: ExportConfig,
+}
+
+impl ExportLatex {
+    //checks the configured template, if any, defines everything the pages use
+    pub fn validate_template(&self) -> Result<(), GlosserError> {
+        let mut required = REQUIRED_DEFINITIONS.to_vec();
+        if self.config.index.columns() > 1 {
+            required.push(("multicol", MULTICOL));
+        }
+        validate_template(
+            self.config.template.as_deref().unwrap_or(LATEX_TEMPLATE),
+            &required,
+        )
+    }
+}
+
 impl ExportDocument for ExportLatex {
     fn gloss_entry(&self, gloss_occurrance: &GlossOccurrance, lemma: Option<&str>) -> String {
         if gloss_occurrance.arrowed_state != ArrowedState::Invisible
@@ -66,161 +209,91 @@ impl ExportDocument for ExportLatex {
         }
     }
 
-    fn make_text(
-        &self,
-        gloss_occurrances: &[GlossOccurrance],
-        appcrit_hash: &HashMap<WordUuid, String>,
-    ) -> String {
-        let re = Regex::new("([0-9]+)[.]([0-9]+)").unwrap();
+    fn make_text(&self, page_text: &PageText) -> String {
         let mut res = String::from("");
-        let mut prev_non_space = true;
-        //let mut last_type = WordType::InvalidType;
-        let mut is_verse_section = false;
-        let mut verse_speaker: Option<String> = None;
-        let mut verse_line = String::from("");
-        let mut verse_line_number = String::from("");
-
-        let mut appcrits_page: Vec<String> = vec![];
-
-        //println!("page count {}", gloss_occurrances.len());
-        for w in gloss_occurrances {
-            if let Some(ap) = appcrit_hash.get(&w.word.uuid) {
-                appcrits_page.push(ap.clone());
-            }
-            //println!("word type {:?}", w.word.word_type);
+        let mut in_tabular = false;
 
-            match w.word.word_type {
-                WordType::VerseLine => {
-                    if !is_verse_section {
-                        res.push_str(r##"
+        for block in &page_text.blocks {
+            if let TextBlock::Verse(lines) = block {
+                if !in_tabular {
+                    res.push_str(r##"
 \end{spacing}
 \begin{tabular}%https://tex.stackexchange.com/questions/338009/right-alignment-for-plength-box-in-tabular
   {>{\raggedright\arraybackslash}p{1cm}%
-   >{\raggedright\arraybackslash}p{9.5cm}%
+   >{\raggedright\arraybackslash}p{\dimexpr\textwidth-7.01cm\relax}%
    >{\raggedleft\arraybackslash}p{2cm}%
   }"##);
-                        is_verse_section = true;
-                    } else {
-                        //previous verse line is complete
-                        res.push_str(
-                            complete_verse_line(verse_speaker, &verse_line, &verse_line_number)
-                                .as_str(),
-                        );
-                        verse_speaker = None;
-                        verse_line = String::from("");
-                    }
-                    verse_line_number = w.word.word.replace("[line]", "");
+                    in_tabular = true;
+                }
+                for line in lines {
+                    res.push_str(
+                        complete_verse_line(
+                            line.speaker,
+                            &make_inlines(&line.inlines, self.config.appcrit_notes),
+                            line.display_number(),
+                        )
+                        .as_str(),
+                    );
                 }
-                WordType::WorkTitle => res.push_str(
+                continue;
+            }
+            if in_tabular {
+                res.push_str("~\\\\\n\\end{tabular}\n\\begin{spacing}{\\GlossLineSpacing}\n");
+                in_tabular = false;
+            }
+            match block {
+                TextBlock::WorkTitle(title) => res.push_str(
                     format!(
                         "\\begin{{center}}\\noindent\\textbf{{{}}}\\par\\end{{center}}\n",
-                        escape_latex(&w.word.word)
+                        escape_latex(title)
                     )
                     .as_str(),
                 ),
-                WordType::Word | WordType::Punctuation => {
-                    //0 | 1
-                    let punc = vec![
-                        ".", ",", "·", "·", ";", ";", ">", "]", ")", ",\"", ".”", ".\"", "·\"",
-                        "·\"", ".’",
-                    ];
-                    let s = format!(
-                        "{}{}",
-                        if punc.contains(&w.word.word.as_str()) || prev_non_space {
-                            ""
-                        } else {
-                            " "
-                        },
-                        w.word.word
-                    );
-                    if is_verse_section {
-                        verse_line.push_str(&s);
-                    } else {
-                        res.push_str(&s);
-                    }
-                    prev_non_space = w.word.word == "<" || w.word.word == "[" || w.word.word == "(";
+                TextBlock::SectionTitle(title) | TextBlock::Speaker(title) => {
+                    res.push_str(format!("\\begin{{center}}{}\\end{{center}}", title).as_str())
                 }
-                WordType::ParaWithIndent => res.push_str("\n\\par\n"),
-                WordType::ParaNoIndent => res.push_str("\n\\noindent\n"),
-                WordType::SectionTitle => {
-                    let s = format!("\\begin{{center}}{}\\end{{center}}", w.word.word);
-                    res.push_str(s.as_str());
-                }
-                WordType::Section => {
-                    let section_input = w.word.word.replace("[section]", "");
-
-                    let matches = re.captures(&section_input);
-
-                    let s = if let Some(matches) = matches {
-                        let section = matches.get(1).unwrap().as_str();
-                        let subsection = matches.get(2).unwrap().as_str();
-
-                        //To Do: for the next thee formats move space to start of line
-                        if subsection == "1" {
-                            format!("\\hspace{{0pt}}\\marginsec{{{}}} ", section)
-                        } else {
-                            format!("\\hspace{{0pt}}\\marginseclight{{{}}} ", subsection)
-                        }
-                    } else {
-                        format!("\\hspace{{0pt}}\\marginsec{{{}}} ", section_input)
-                    };
-
-                    res.push_str(s.as_str());
-                    //if last_type == WordType::InvalidType || last_type == WordType::ParaWithIndent {
-                    //-1 || 6
-                    prev_non_space = true;
-                    // } else {
-                    //     prev_non_space = false;
-                    // }
-                }
-                WordType::Speaker => {
-                    let s = format!("\\begin{{center}}{}\\end{{center}}", w.word.word);
-                    res.push_str(s.as_str());
-                }
-                WordType::InlineSpeaker => {
-                    if is_verse_section {
-                        verse_speaker = Some(w.word.word.clone());
-                    } else {
-                        res.push_str(format!("\\par \\textbf{{{}}} ", w.word.word).as_str());
+                TextBlock::Paragraph { start, inlines } => {
+                    match start {
+                        ParagraphStart::Indented => res.push_str("\n\\par\n"),
+                        ParagraphStart::NotIndented => res.push_str("\n\\noindent\n"),
+                        ParagraphStart::Continued => (),
                     }
+                    res.push_str(&make_inlines(inlines, self.config.appcrit_notes));
                 }
-                WordType::InlineVerseSpeaker => {
-                    verse_speaker = Some(w.word.word.clone());
-                }
-                _ => (),
+                TextBlock::Verse(_) => (),
             }
-            //last_type = w.word_type.clone();
         }
 
-        if is_verse_section {
-            //previous verse line is complete
-            res.push_str(
-                complete_verse_line(verse_speaker, &verse_line, &verse_line_number).as_str(),
-            );
-
+        if in_tabular {
             res.push_str("~\\\\\n\\end{tabular}");
         } else {
             res.push_str("\\hspace*{\\fill}\n\\end{spacing}\n");
         }
 
-        if !appcrits_page.is_empty() {
+        if !page_text.appcrits.is_empty() {
             res.push_str("~\\\\\n");
         }
-        for ap in appcrits_page {
-            res.push_str(format!("{}\\\\\n", escape_latex(&ap)).as_str());
+        for ap in &page_text.appcrits {
+            match ap.label(self.config.appcrit_notes) {
+                Some(label) => res.push_str(
+                    format!("\\textbf{{{}}} {}\\\\\n", label, escape_latex(&ap.text)).as_str(),
+                ),
+                None => res.push_str(format!("{}\\\\\n", escape_latex(&ap.text)).as_str()),
+            }
         }
         res
     }
 
     fn page_gloss_start(&self) -> String {
         String::from(
-            "\\begin{table}[b!]\\leftskip -0.84cm\n\\begin{tabular}{ m{0.2cm} L{3.25in} D{3.1in} }\n",
+            "\\begin{table}[b!]\\leftskip -0.84cm\n\\begin{tabular}{ m{0.2cm} L{\\dimexpr0.5\\textwidth\\relax} D{\\dimexpr0.5\\textwidth-0.15in\\relax} }\n",
         )
     }
 
     fn page_start(&self, title: &str, _page_number: usize) -> String {
         format!(
-            "\\fancyhead[OR]{{{title}}}\n\\begin{{spacing}}{{\\GlossLineSpacing}}\n\\noindent\n"
+            "\\fancyhead[OR]{{{}}}\n\\begin{{spacing}}{{\\GlossLineSpacing}}\n\\noindent\n",
+            escape_latex_text(self.config.odd_header(title))
         )
     }
 
@@ -233,59 +306,62 @@ impl ExportDocument for ExportLatex {
     }
 
     fn document_start(&self, title: &str, start_page: usize) -> String {
-        let start = r###"\documentclass[twoside,openright,12pt,letterpaper]{book}
-%\usepackage[margin=1.0in]{geometry}
-\usepackage[twoside, margin=1.0in]{geometry} %bindingoffset=0.5in,
-\usepackage[utf8]{inputenc}
-\usepackage{fontspec}
-\usepackage{array}
-\usepackage{booktabs}
-\usepackage{ragged2e}
-\usepackage{setspace}
-\usepackage{navigator}
-
-\newcommand{\GlossLineSpacing}{1.5}
-
-
-\setmainfont[Scale=MatchUppercase,Ligatures=TeX, BoldFont={*BOLD}, ItalicFont={IFAOGrec.ttf}, ItalicFeatures={FakeSlant=0.2}]{IFAOGrec.ttf}
-%\setmainlanguage[variant=polytonic]{greek}
-\tolerance=10000 % https://www.texfaq.org/FAQ-overfull
-\setlength{\extrarowheight}{8pt}
-\newcolumntype{L}{>{\setlength{\RaggedRight\parindent}{-2em}\leftskip 2em}p}
-\newcolumntype{D}{>{\setlength{\RaggedRight}}p}
-
-\usepackage{fancyhdr} % http://tug.ctan.org/tex-archive/macros/latex/contrib/fancyhdr/fancyhdr.pdf
-
-\pagestyle{fancy}
-\fancyhf{}
-\renewcommand{\headrulewidth}{0.0pt}
-  \fancyhead[EL]{%MAIN_TITLE%}% Title on Even page, Centered
-  \fancyhead[OR]{}% Author on Odd page, Centered
-\setlength{\headheight}{14.49998pt}
-\cfoot{\thepage}
-
-%\usepackage{enumitem}
-%\SetLabelAlign{margin}{\llap{#1~~}}
-%\usepackage{showframe} % just to show the margins
-%https://tex.stackexchange.com/questions/223701/labels-in-the-left-margin
-
-%https://tex.stackexchange.com/questions/40748/use-sections-inline
-\newcommand{\marginsec}[1]{\vadjust{\vbox to 0pt{\sbox0{\bfseries#1\quad}\kern-0.89em\llap{\box0}}}}
-\newcommand{\marginseclight}[1]{\vadjust{\vbox to 0pt{\sbox0{\footnotesize#1\hspace{0.25em}\quad}\kern-0.85em\llap{\box0}}}}
-\usepackage[none]{hyphenat}
-\usepackage[polutonikogreek,english]{babel} %https://tex.stackexchange.com/questions/13067/utf8x-vs-utf8-inputenc
-\usepackage{microtype}
-\begin{document}
-%\clearpage
-\setcounter{page}{%PAGE_NUM%}
-%\newpage
-%\mbox{}
-\newpage
-"###;
+        let config = &self.config;
+        //the book class only has 10, 11 and 12pt; other sizes are set at the start of the document
+        let (class_size, font_size_command) = match config.font_size {
+            10.0 | 11.0 | 12.0 => (format!("{}pt", config.font_size), String::from("")),
+            size => (
+                String::from("12pt"),
+                format!(
+                    "\\fontsize{{{}pt}}{{{}pt}}\\selectfont\n",
+                    size,
+                    (size * 12.0).round() / 10.0
+                ),
+            ),
+        };
+        let paper = match config.paper {
+            Paper::Letter => ",letterpaper",
+            Paper::A4 => ",a4paper",
+            Paper::A5 => ",a5paper",
+            Paper::Custom { .. } => "",
+        };
+        let mut geometry = match config.margins {
+            Some(m) => format!(
+                "top={}, bottom={}, inner={}, outer={}",
+                inches(m.top),
+                inches(m.bottom),
+                inches(m.inner),
+                inches(m.outer)
+            ),
+            None => String::from("margin=1.0in"),
+        };
+        if let Paper::Custom { width, height } = config.paper {
+            geometry.push_str(
+                format!(
+                    ", paperwidth={}, paperheight={}",
+                    inches(width),
+                    inches(height)
+                )
+                .as_str(),
+            );
+        }
+        let main_font = config
+            .font_file
+            .as_deref()
+            .unwrap_or_else(|| config.main_font());
 
-        start
-            .replace("%MAIN_TITLE%", title)
-            .replace("%PAGE_NUM%", start_page.to_string().as_str())
+        config
+            .fill_template(LATEX_TEMPLATE, title, start_page, &escape_latex_text)
+            .replace("%FONT_SIZE%", &class_size)
+            .replace("%FONT_SIZE_COMMAND%", &font_size_command)
+            .replace("%PAPER%", paper)
+            .replace("%GEOMETRY%", &geometry)
+            .replace("%LINE_SPACING%", &config.line_spacing.to_string())
+            .replace("%MAIN_FONT%", main_font)
+            .replace(
+                "%EVEN_HEADER%",
+                &escape_latex_text(config.even_header(config.title(title))),
+            )
     }
 
     fn make_index(&self, arrowed_words_index: &[ArrowedWordsIndex]) -> String {
@@ -296,21 +372,69 @@ impl ExportDocument for ExportLatex {
         \noindent
         "##;
 
+        let layout = &self.config.index;
+        let leader = match layout.leader {
+            Leader::Dots => r" \dotfill ",
+            Leader::Line => r" \hrulefill ",
+            Leader::Space => r" \hfill ",
+        };
         let mut latex = String::from(ARROWED_INDEX_TEMPLATE);
-        let mut gloss_per_page = 0;
-        for gloss in arrowed_words_index {
+        if layout.columns() > 1 {
+            latex.push_str(format!("\\begin{{multicols}}{{{}}}\n", layout.columns()).as_str());
+        }
+        let mut letter = None;
+        for (i, gloss) in arrowed_words_index.iter().enumerate() {
+            if layout.break_before(i) {
+                latex.push_str(if layout.columns() > 1 {
+                    "\\columnbreak\n"
+                } else {
+                    "\\newpage\n"
+                });
+                latex.push_str("\\noindent\n");
+            }
+            if layout.letter_headings {
+                let gloss_letter = index_letter(&gloss.gloss_lemma);
+                if letter != Some(gloss_letter) {
+                    latex.push_str(
+                        format!(
+                            "\\par\\medskip\\noindent\\textbf{{{}}}\\par\\nopagebreak\\noindent\n",
+                            gloss_letter
+                        )
+                        .as_str(),
+                    );
+                    letter = Some(gloss_letter);
+                }
+            }
             //$latex .= explode(",", $a[0], 2)[0] . " \dotfill " . $a[2] . " \\\\ \n";
-            latex.push_str(&gloss.gloss_lemma);
-            latex.push_str(r" \dotfill ");
+            latex.push_str(&escape_latex(&gloss.gloss_lemma));
+            latex.push_str(leader);
             latex.push_str(&gloss.page_number.to_string());
             latex.push_str(" \\\\ \n");
+        }
+        if layout.columns() > 1 {
+            latex.push_str("\\end{multicols}\n");
+        }
+        latex
+    }
 
-            gloss_per_page += 1;
-            if gloss_per_page > 43 {
-                gloss_per_page = 0;
-                latex.push_str("\\newpage \n");
-                latex.push_str("\\noindent \n");
-            }
+    fn make_glossary(&self, glossary: &[GlossaryEntry]) -> String {
+        const GLOSSARY_TEMPLATE: &str = r##"
+        \newpage
+        \fancyhead[OR]{GLOSSARY}
+        "##;
+
+        let mut latex = String::from(GLOSSARY_TEMPLATE);
+        for entry in glossary {
+            latex.push_str(
+                format!(
+                    "\\noindent\\hangindent=2em\\hangafter=1 \\textbf{{{}}} ({}) {} ({})\\par\n",
+                    escape_latex(&entry.lemma),
+                    escape_latex_text(&entry.pos),
+                    escape_latex(&entry.def),
+                    entry.frequency()
+                )
+                .as_str(),
+            );
         }
         latex
     }
@@ -323,7 +447,7 @@ impl ExportDocument for ExportLatex {
 \hspace*{\fill}
 \end{spacing}
 \begin{table}[b!]\leftskip -0.84cm
-\begin{tabular}{ m{0.2cm} L{3.25in} D{3.1in} }
+\begin{tabular}{ m{0.2cm} L{\dimexpr0.5\textwidth\relax} D{\dimexpr0.5\textwidth-0.15in\relax} }
 \end{tabular}
 \end{table}
 \newpage
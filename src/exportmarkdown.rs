@@ -0,0 +1,201 @@
+use super::ExportDocument;
+use crate::ArrowedState;
+use crate::ArrowedWordsIndex;
+use crate::GlossOccurrance;
+use crate::markup::{MarkupTag, parse_markup_lenient, render_markup};
+use crate::model::{GlossaryEntry, Inline, PageText, TextBlock};
+
+fn escape_markdown_text(s: &str) -> String {
+    let mut res = String::from("");
+    for c in s.chars() {
+        if matches!(
+            c,
+            '\\' | '*' | '_' | '`' | '[' | ']' | '<' | '>' | '#' | '|'
+        ) {
+            res.push('\\');
+        }
+        res.push(c);
+    }
+    res
+}
+
+fn escape_markdown(s: &str) -> String {
+    render_markup(
+        &parse_markup_lenient(s),
+        &escape_markdown_text,
+        &|tag, inner| match tag {
+            MarkupTag::Italic => format!("*{}*", inner),
+            MarkupTag::Bold => format!("**{}**", inner),
+            MarkupTag::Superscript => format!("<sup>{}</sup>", inner),
+        },
+    )
+}
+
+fn complete_verse_line(
+    verse_speaker: Option<&str>,
+    verse_line: &str,
+    verse_line_number: &str,
+) -> String {
+    format!(
+        "| {} | {} | {} |\n",
+        escape_markdown(verse_speaker.unwrap_or("")),
+        &verse_line,
+        escape_markdown(verse_line_number)
+    )
+}
+
+fn make_inlines(inlines: &[Inline]) -> String {
+    let mut res = String::from("");
+    for inline in inlines {
+        match inline {
+            Inline::Word { word, space_before } => {
+                if *space_before {
+                    res.push(' ');
+                }
+                res.push_str(&escape_markdown(&word.word));
+            }
+            Inline::Section { number, major } => res.push_str(
+                if *major {
+                    format!(" **{}** ", number)
+                } else {
+                    format!(" *{}* ", number)
+                }
+                .as_str(),
+            ),
+            Inline::Speaker(speaker) => {
+                res.push_str(format!(" **{}** ", escape_markdown(speaker)).as_str())
+            }
+            Inline::AppCritMark(_) => (), //notes are written as they are, without marks
+        }
+    }
+    res.trim().to_string()
+}
+
+pub struct ExportMarkdown {}
+impl ExportDocument for ExportMarkdown {
+    fn gloss_entry(&self, gloss_occurrance: &GlossOccurrance, lemma: Option<&str>) -> String {
+        if gloss_occurrance.arrowed_state != ArrowedState::Invisible
+            && let Some(lemma_unwrapped) = lemma
+            && let Some(gloss_unwrapped) = gloss_occurrance.gloss
+        {
+            format!(
+                "- {}**{}** ({}) {} ({} of {})\n",
+                if gloss_occurrance.arrowed_state == ArrowedState::Arrowed {
+                    "→ "
+                } else {
+                    ""
+                },
+                escape_markdown(lemma_unwrapped),
+                escape_markdown(&gloss_unwrapped.pos),
+                escape_markdown(&gloss_unwrapped.def),
+                gloss_occurrance.running_count.unwrap_or(0),
+                gloss_occurrance.total_count.unwrap_or(0)
+            )
+        } else {
+            String::from("")
+        }
+    }
+
+    fn make_text(&self, page_text: &PageText) -> String {
+        let mut res = String::from("");
+
+        for block in &page_text.blocks {
+            match block {
+                TextBlock::Verse(lines) => {
+                    res.push_str("| | | |\n|---|---|--:|\n");
+                    for line in lines {
+                        res.push_str(
+                            complete_verse_line(
+                                line.speaker,
+                                &make_inlines(&line.inlines),
+                                line.display_number(),
+                            )
+                            .as_str(),
+                        );
+                    }
+                    res.push('\n');
+                }
+                TextBlock::WorkTitle(title) => {
+                    res.push_str(format!("### {}\n\n", escape_markdown(title)).as_str())
+                }
+                TextBlock::SectionTitle(title) => {
+                    res.push_str(format!("#### {}\n\n", escape_markdown(title)).as_str())
+                }
+                TextBlock::Speaker(speaker) => {
+                    res.push_str(format!("**{}**\n\n", escape_markdown(speaker)).as_str())
+                }
+                TextBlock::Paragraph { inlines, .. } => {
+                    res.push_str(format!("{}\n\n", make_inlines(inlines)).as_str())
+                }
+            }
+        }
+
+        for ap in &page_text.appcrits {
+            res.push_str(format!("> {}\n", escape_markdown(&ap.text)).as_str());
+        }
+        if !page_text.appcrits.is_empty() {
+            res.push('\n');
+        }
+        res
+    }
+
+    fn page_gloss_start(&self) -> String {
+        String::from("#### Glosses\n\n")
+    }
+
+    fn page_start(&self, title: &str, page_number: usize) -> String {
+        if title.is_empty() {
+            format!("\n## Page {}\n\n", page_number)
+        } else {
+            format!("\n## {} - Page {}\n\n", escape_markdown(title), page_number)
+        }
+    }
+
+    fn page_end(&self) -> String {
+        String::from("\n---\n")
+    }
+
+    fn document_end(&self) -> String {
+        String::from("")
+    }
+
+    fn document_start(&self, title: &str, _start_page: usize) -> String {
+        format!("# {}\n", escape_markdown(title))
+    }
+
+    fn make_index(&self, arrowed_words_index: &[ArrowedWordsIndex]) -> String {
+        let mut res = String::from("\n## Index\n\n");
+        for gloss in arrowed_words_index {
+            res.push_str(
+                format!(
+                    "- {}, {}\n",
+                    escape_markdown(&gloss.gloss_lemma),
+                    gloss.page_number
+                )
+                .as_str(),
+            );
+        }
+        res
+    }
+
+    fn make_glossary(&self, glossary: &[GlossaryEntry]) -> String {
+        let mut res = String::from("\n## Glossary\n\n");
+        for entry in glossary {
+            res.push_str(
+                format!(
+                    "- **{}** ({}) {} ({})\n",
+                    escape_markdown(&entry.lemma),
+                    escape_markdown(&entry.pos),
+                    escape_markdown(&entry.def),
+                    entry.frequency()
+                )
+                .as_str(),
+            );
+        }
+        res
+    }
+
+    fn blank_page(&self) -> String {
+        String::from("\n<!-- blank page -->\n")
+    }
+}
@@ -0,0 +1,328 @@
+use crate::exportfodt::escape_fodt_text;
+use crate::{ExportConfig, ExportFodt, GlossOccurrance, GlossPageOptions, GlosserError, Sequence};
+use quick_xml::Reader;
+use quick_xml::events::Event;
+use std::collections::HashSet;
+use std::fs;
+use std::io::{Cursor, Write};
+use std::path::Path;
+use zip::write::SimpleFileOptions;
+use zip::{CompressionMethod, ZipWriter};
+
+const MIMETYPE: &str = "application/vnd.oasis.opendocument.text";
+
+//the bundled regular and bold fonts, embedded when the config's font_file names IFAOGrec.ttf
+const BUNDLED_FONT_FILE: &str = "IFAOGrec.ttf";
+const BUNDLED_FONTS: [(&str, &str, &[u8]); 2] = [
+    (
+        "Fonts/IFAOGrec.ttf",
+        "normal",
+        include_bytes!("../IFAOGrec.ttf"),
+    ),
+    (
+        "Fonts/IFAOGrecBold.ttf",
+        "bold",
+        include_bytes!("../IFAOGrecBold.ttf"),
+    ),
+];
+
+//a font file embedded under Fonts/: its path in the package, its weight and its bytes
+struct EmbeddedFont {
+    path: String,
+    weight: &'static str,
+    data: Vec<u8>,
+}
+
+//the files embedded for the main font: the config's font_file, as for LaTeX and HTML. the bundled
+//fonts stand in for IFAOGrec.ttf; any other file is read from disk. without one nothing is embedded
+fn embedded_fonts(config: &ExportConfig) -> Result<Vec<EmbeddedFont>, GlosserError> {
+    match config.font_file.as_deref() {
+        None => Ok(vec![]),
+        Some(BUNDLED_FONT_FILE) => Ok(BUNDLED_FONTS
+            .into_iter()
+            .map(|(path, weight, data)| EmbeddedFont {
+                path: path.to_string(),
+                weight,
+                data: data.to_vec(),
+            })
+            .collect()),
+        Some(file) => {
+            let name = Path::new(file)
+                .file_name()
+                .and_then(|n| n.to_str())
+                .ok_or_else(|| {
+                    GlosserError::InvalidInput(format!("font file {} has no file name", file))
+                })?;
+            let data = fs::read(file).map_err(|e| {
+                GlosserError::NotFound(format!("font file {} could not be read: {}", file, e))
+            })?;
+            Ok(vec![EmbeddedFont {
+                path: format!("Fonts/{}", name),
+                weight: "normal",
+                data,
+            }])
+        }
+    }
+}
+
+//the element <office:name ...>...</office:name> of the flat document, or "" if it has none
+fn section<'a>(fodt: &'a str, name: &str) -> &'a str {
+    let open = format!("<office:{}", name);
+    let close = format!("</office:{}>", name);
+    let Some(start) = fodt
+        .match_indices(&open)
+        .map(|(i, _)| i)
+        .find(|i| matches!(fodt.as_bytes().get(i + open.len()), Some(b'>' | b' ')))
+    else {
+        return "";
+    };
+    match fodt[start..].find(&close) {
+        Some(end) => &fodt[start..start + end + close.len()],
+        None => "",
+    }
+}
+
+//splits <office:automatic-styles> between styles.xml and content.xml: the page layouts and the
+//styles the master pages' headers and footers use go in styles.xml, the rest in content.xml, so
+//each style is defined once
+fn split_automatic_styles(fodt: &str) -> Result<(String, String), GlosserError> {
+    let automatic_styles = section(fodt, "automatic-styles");
+    let mut master_style_names = HashSet::new();
+    let mut reader = Reader::from_str(section(fodt, "master-styles"));
+    loop {
+        match reader.read_event() {
+            Ok(Event::Start(e)) | Ok(Event::Empty(e)) => {
+                for a in e.attributes().flatten() {
+                    if a.key.as_ref() == b"text:style-name" {
+                        master_style_names.insert(String::from_utf8_lossy(&a.value).into_owned());
+                    }
+                }
+            }
+            Ok(Event::Eof) => break,
+            Ok(_) => (),
+            Err(e) => {
+                return Err(GlosserError::InvalidInput(format!(
+                    "master styles are not well formed: {}",
+                    e
+                )));
+            }
+        }
+    }
+
+    let mut for_styles = String::from("");
+    let mut for_content = String::from("");
+    let mut reader = Reader::from_str(automatic_styles);
+    let mut depth = 0;
+    let mut start = None; //where the top-level element being read starts
+    let mut in_styles = false;
+    loop {
+        let position = reader.buffer_position() as usize;
+        let event = reader.read_event().map_err(|e| {
+            GlosserError::InvalidInput(format!("automatic styles are not well formed: {}", e))
+        })?;
+        match &event {
+            Event::Start(e) | Event::Empty(e) if depth == 1 => {
+                start = Some(position);
+                in_styles = e.name().as_ref() == b"style:page-layout"
+                    || e.attributes().flatten().any(|a| {
+                        a.key.as_ref() == b"style:name"
+                            && master_style_names.contains(&*String::from_utf8_lossy(&a.value))
+                    });
+            }
+            _ => (),
+        }
+        match event {
+            Event::Start(_) => depth += 1,
+            Event::End(_) => depth -= 1,
+            Event::Eof => break,
+            _ => (),
+        }
+        //a top-level element has just ended
+        if depth == 1
+            && matches!(event, Event::End(_) | Event::Empty(_))
+            && let Some(start) = start.take()
+        {
+            let element = &automatic_styles[start..reader.buffer_position() as usize];
+            if in_styles {
+                for_styles.push_str(element);
+            } else {
+                for_content.push_str(element);
+            }
+        }
+    }
+    let wrap = |styles: String| {
+        format!(
+            "<office:automatic-styles>{}</office:automatic-styles>",
+            styles
+        )
+    };
+    Ok((wrap(for_styles), wrap(for_content)))
+}
+
+//the font declarations with the fonts embedded in the main font's face
+fn embedded_font_face_decls(fodt: &str, main_font: &str, fonts: &[EmbeddedFont]) -> String {
+    let decls = section(fodt, "font-face-decls");
+    let open = format!(
+        "<style:font-face style:name=\"{}\"",
+        escape_fodt_text(main_font)
+    );
+    let Some(start) = decls.find(&open) else {
+        return decls.to_string();
+    };
+    //only a face without content of its own, i.e. one ending in />
+    let Some(end) = decls[start..].find('>').map(|e| start + e + 1) else {
+        return decls.to_string();
+    };
+    if fonts.is_empty() || !decls[..end].ends_with("/>") {
+        return decls.to_string();
+    }
+    let mut uris = String::from("");
+    for font in fonts {
+        uris.push_str(
+            format!(
+                r###"<svg:font-face-uri xlink:href="{}" xlink:type="simple" loext:font-style="normal" loext:font-weight="{}"><svg:font-face-format svg:string="truetype"/></svg:font-face-uri>"###,
+                escape_fodt_text(&font.path), font.weight
+            )
+            .as_str(),
+        );
+    }
+    format!(
+        "{}{}><svg:font-face-src>{}</svg:font-face-src></style:font-face>{}",
+        &decls[..start],
+        decls[start..end - 2].trim_end(),
+        uris,
+        &decls[end..]
+    )
+}
+
+fn manifest_xml(fonts: &[EmbeddedFont]) -> String {
+    let mut entries = String::from("");
+    for font in fonts {
+        entries.push_str(
+            format!(
+                " <manifest:file-entry manifest:full-path=\"{}\" manifest:media-type=\"application/x-font-ttf\"/>\n",
+                escape_fodt_text(&font.path)
+            )
+            .as_str(),
+        );
+    }
+    format!(
+        r###"<?xml version="1.0" encoding="UTF-8"?>
+<manifest:manifest xmlns:manifest="urn:oasis:names:tc:opendocument:xmlns:manifest:1.0" manifest:version="1.3">
+ <manifest:file-entry manifest:full-path="/" manifest:version="1.3" manifest:media-type="{}"/>
+ <manifest:file-entry manifest:full-path="content.xml" manifest:media-type="text/xml"/>
+ <manifest:file-entry manifest:full-path="styles.xml" manifest:media-type="text/xml"/>
+ <manifest:file-entry manifest:full-path="meta.xml" manifest:media-type="text/xml"/>
+ <manifest:file-entry manifest:full-path="settings.xml" manifest:media-type="text/xml"/>
+{}</manifest:manifest>
+"###,
+        MIMETYPE, entries
+    )
+}
+
+//splits a flat .fodt document into the files of a packaged .odt: the body and its automatic
+//styles go in content.xml, the styles, master pages and their page layouts in styles.xml. the config's main font is
+//embedded so the document looks the same where it isn't installed
+pub fn fodt_to_odt(fodt: &str, config: &ExportConfig) -> Result<Vec<u8>, GlosserError> {
+    let root_start = fodt
+        .find("<office:document ")
+        .ok_or_else(|| GlosserError::InvalidInput(String::from("not a flat odt document")))?;
+    let root_end = root_start
+        + fodt[root_start..]
+            .find('>')
+            .ok_or_else(|| GlosserError::InvalidInput(String::from("not a flat odt document")))?;
+    //the namespaces and version; the mimetype attribute only belongs on the flat document
+    let attributes = fodt[root_start + "<office:document".len()..root_end]
+        .replace(&format!(" office:mimetype=\"{}\"", MIMETYPE), "");
+
+    let fonts = embedded_fonts(config)?;
+    let font_face_decls = embedded_font_face_decls(fodt, config.main_font(), &fonts);
+    let (styles_automatic_styles, content_automatic_styles) = split_automatic_styles(fodt)?;
+    let document = |root: &str, parts: &[&str]| {
+        format!(
+            "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n<office:{}{}>\n{}\n</office:{}>\n",
+            root,
+            attributes,
+            parts.concat(),
+            root
+        )
+    };
+    let files = [
+        (
+            "content.xml",
+            document(
+                "document-content",
+                &[
+                    section(fodt, "scripts"),
+                    &font_face_decls,
+                    &content_automatic_styles,
+                    section(fodt, "body"),
+                ],
+            ),
+        ),
+        (
+            "styles.xml",
+            document(
+                "document-styles",
+                &[
+                    &font_face_decls,
+                    section(fodt, "styles"),
+                    &styles_automatic_styles,
+                    section(fodt, "master-styles"),
+                ],
+            ),
+        ),
+        (
+            "meta.xml",
+            document("document-meta", &[section(fodt, "meta")]),
+        ),
+        (
+            "settings.xml",
+            document(
+                "document-settings",
+                &[&section(fodt, "settings").replace(
+                    r###"<config:config-item config:name="EmbedFonts" config:type="boolean">false</config:config-item>"###,
+                    r###"<config:config-item config:name="EmbedFonts" config:type="boolean">true</config:config-item>"###,
+                )],
+            ),
+        ),
+        ("META-INF/manifest.xml", manifest_xml(&fonts)),
+    ];
+
+    let zip_error = |e: zip::result::ZipError| GlosserError::Other(format!("Zip error: {}", e));
+    let io_error = |e: std::io::Error| GlosserError::Other(format!("Zip error: {}", e));
+    let mut zip = ZipWriter::new(Cursor::new(Vec::new()));
+    //the mimetype has to be first and uncompressed
+    zip.start_file(
+        "mimetype",
+        SimpleFileOptions::default().compression_method(CompressionMethod::Stored),
+    )
+    .map_err(zip_error)?;
+    zip.write_all(MIMETYPE.as_bytes()).map_err(io_error)?;
+    let options = SimpleFileOptions::default();
+    for (name, content) in files {
+        zip.start_file(name, options).map_err(zip_error)?;
+        zip.write_all(content.as_bytes()).map_err(io_error)?;
+    }
+    for font in &fonts {
+        zip.start_file(font.path.as_str(), options)
+            .map_err(zip_error)?;
+        zip.write_all(&font.data).map_err(io_error)?;
+    }
+    zip.finish().map(|c| c.into_inner()).map_err(zip_error)
+}
+
+impl Sequence {
+    //the document as the given ExportFodt renders it, packaged as an .odt
+    pub fn make_odt(
+        &self,
+        gloss_occurrances: &[Vec<GlossOccurrance>],
+        export: &ExportFodt,
+        options: &GlossPageOptions,
+    ) -> Result<Vec<u8>, GlosserError> {
+        fodt_to_odt(
+            &self.make_document(gloss_occurrances, export, options),
+            &export.config,
+        )
+    }
+}
@@ -0,0 +1,167 @@
+use super::ExportDocument;
+use crate::ArrowedState;
+use crate::ArrowedWordsIndex;
+use crate::GlossOccurrance;
+use crate::markup::{parse_markup_lenient, render_markup};
+use crate::model::{GlossaryEntry, Inline, PageText, TextBlock};
+
+//drops the gloss markup, keeping its text
+fn strip_markup(s: &str) -> String {
+    render_markup(
+        &parse_markup_lenient(s),
+        &|text| text.to_string(),
+        &|_, inner| inner.to_string(),
+    )
+}
+
+fn complete_verse_line(
+    verse_speaker: Option<&str>,
+    verse_line: &str,
+    verse_line_number: &str,
+) -> String {
+    let mut res = String::from("");
+    if let Some(speaker) = verse_speaker {
+        res.push_str(speaker);
+        res.push(' ');
+    }
+    res.push_str(verse_line);
+    if !verse_line_number.is_empty() {
+        res.push_str(format!(" [{}]", verse_line_number).as_str());
+    }
+    res.push('\n');
+    res
+}
+
+fn make_inlines(inlines: &[Inline]) -> String {
+    let mut res = String::from("");
+    for inline in inlines {
+        match inline {
+            Inline::Word { word, space_before } => {
+                if *space_before {
+                    res.push(' ');
+                }
+                res.push_str(&word.word);
+            }
+            Inline::Section { number, .. } => res.push_str(format!(" [{}] ", number).as_str()),
+            Inline::Speaker(speaker) => res.push_str(format!(" {} ", speaker).as_str()),
+            Inline::AppCritMark(_) => (), //notes are written as they are, without marks
+        }
+    }
+    res.trim().to_string()
+}
+
+//one line per paragraph, verse line and gloss so the output diffs well
+pub struct ExportPlainText {}
+impl ExportDocument for ExportPlainText {
+    fn gloss_entry(&self, gloss_occurrance: &GlossOccurrance, lemma: Option<&str>) -> String {
+        if gloss_occurrance.arrowed_state != ArrowedState::Invisible
+            && let Some(lemma_unwrapped) = lemma
+            && let Some(gloss_unwrapped) = gloss_occurrance.gloss
+        {
+            format!(
+                "{} {} ({}) {} ({} of {})\n",
+                if gloss_occurrance.arrowed_state == ArrowedState::Arrowed {
+                    "→"
+                } else {
+                    " "
+                },
+                strip_markup(lemma_unwrapped),
+                gloss_unwrapped.pos,
+                strip_markup(&gloss_unwrapped.def),
+                gloss_occurrance.running_count.unwrap_or(0),
+                gloss_occurrance.total_count.unwrap_or(0)
+            )
+        } else {
+            String::from("")
+        }
+    }
+
+    fn make_text(&self, page_text: &PageText) -> String {
+        let mut res = String::from("");
+
+        for block in &page_text.blocks {
+            match block {
+                TextBlock::Verse(lines) => {
+                    for line in lines {
+                        res.push_str(
+                            complete_verse_line(
+                                line.speaker,
+                                &make_inlines(&line.inlines),
+                                line.display_number(),
+                            )
+                            .as_str(),
+                        );
+                    }
+                    res.push('\n');
+                }
+                TextBlock::WorkTitle(title)
+                | TextBlock::SectionTitle(title)
+                | TextBlock::Speaker(title) => res.push_str(format!("{}\n\n", title).as_str()),
+                TextBlock::Paragraph { inlines, .. } => {
+                    res.push_str(format!("{}\n\n", make_inlines(inlines)).as_str())
+                }
+            }
+        }
+
+        for ap in &page_text.appcrits {
+            res.push_str(format!("{}\n", strip_markup(&ap.text)).as_str());
+        }
+        if !page_text.appcrits.is_empty() {
+            res.push('\n');
+        }
+        res
+    }
+
+    fn page_gloss_start(&self) -> String {
+        String::from("--\n")
+    }
+
+    fn page_start(&self, title: &str, page_number: usize) -> String {
+        if title.is_empty() {
+            format!("\n=== Page {} ===\n\n", page_number)
+        } else {
+            format!("\n=== {} - Page {} ===\n\n", title, page_number)
+        }
+    }
+
+    fn page_end(&self) -> String {
+        String::from("")
+    }
+
+    fn document_end(&self) -> String {
+        String::from("")
+    }
+
+    fn document_start(&self, title: &str, _start_page: usize) -> String {
+        format!("{}\n", title)
+    }
+
+    fn make_index(&self, arrowed_words_index: &[ArrowedWordsIndex]) -> String {
+        let mut res = String::from("\n=== Index ===\n\n");
+        for gloss in arrowed_words_index {
+            res.push_str(format!("{}, {}\n", gloss.gloss_lemma, gloss.page_number).as_str());
+        }
+        res
+    }
+
+    fn make_glossary(&self, glossary: &[GlossaryEntry]) -> String {
+        let mut res = String::from("\n=== Glossary ===\n\n");
+        for entry in glossary {
+            res.push_str(
+                format!(
+                    "{} ({}) {} ({})\n",
+                    strip_markup(&entry.lemma),
+                    entry.pos,
+                    strip_markup(&entry.def),
+                    entry.frequency()
+                )
+                .as_str(),
+            );
+        }
+        res
+    }
+
+    fn blank_page(&self) -> String {
+        String::from("\n=== Blank Page ===\n")
+    }
+}
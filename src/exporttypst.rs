@@ -3,51 +3,189 @@ use super::ExportDocument;
 use crate::ArrowedState;
 use crate::ArrowedWordsIndex;
 use crate::GlossOccurrance;
-use crate::HashMap;
-use crate::WordType;
-use crate::WordUuid;
-use regex::Regex;
+use crate::GlosserError;
+use crate::exportconfig::{
+    AppCritStyle, COURSE_HEADER, ExportConfig, Leader, inches, validate_template,
+};
+use crate::markup::{MarkupTag, parse_markup_lenient, render_markup};
+use crate::model::{GlossaryEntry, Inline, PageText, ParagraphStart, TextBlock, index_letter};
 
 //https://stackoverflow.com/questions/79173197/how-to-escape-string-for-typst
+fn escape_typst_text(s: &str) -> String {
+    let mut res = String::from("");
+    for c in s.chars() {
+        match c {
+            '[' => res.push_str("\\u{005B}"),
+            ']' => res.push_str("\\u{005D}"),
+            '=' => res.push_str("\\u{003D}"), //required when = starts a paragraph, else warning: block may not occur inside of a paragraph and was ignored
+            '\\' | '"' | '$' | '#' | '<' | '>' | '*' | '_' | '`' | '@' => {
+                res.push('\\');
+                res.push(c);
+            }
+            _ => res.push(c),
+        }
+    }
+    res
+}
+
+//a quoted typst string
+fn typst_string(s: &str) -> String {
+    format!("\"{}\"", s.replace('\\', "\\\\").replace('"', "\\\""))
+}
+
 fn escape_typst(s: &str) -> String {
-    s.replace("\"", "\\\"")
-        .replace("$", "\\$")
-        .replace("#", "\\#")
-        .replace("]", "\\u{005D}")
-        .replace("[", "\\u{005B}")
-        .replace("<b>", "#strong[")
-        .replace("</b>", "]")
-        .replace("</i>", "\")")
-        .replace("<i>", "#fakeitalic(\"") //cuti typst package
-        .replace("<sup>", "#super[")
-        .replace("</sup>", "]")
-        .replace(">", "\\>")
-        .replace("<", "\\<")
-        .replace("=", "\\u{003D}") //required when = starts a paragraph, else warning: block may not occur inside of a paragraph and was ignored
+    render_markup(
+        &parse_markup_lenient(s),
+        &escape_typst_text,
+        &|tag, inner| {
+            match tag {
+                MarkupTag::Italic => format!("#fakeitalic[{}]", inner), //cuti typst package
+                MarkupTag::Bold => format!("#strong[{}]", inner),
+                MarkupTag::Superscript => format!("#super[{}]", inner),
+            }
+        },
+    )
 }
 
 fn complete_verse_line(
-    verse_speaker: Option<String>,
+    verse_speaker: Option<&str>,
     verse_line: &str,
     verse_line_number: &str,
 ) -> String {
-    if verse_line.is_empty() {
-        return String::from("");
-    }
-    let escaped_num = escape_typst(verse_line_number);
     format!(
         "[{}],\n[{}],\n[{}],\n\n",
-        verse_speaker.as_ref().unwrap_or(&String::from("")),
+        escape_typst(verse_speaker.unwrap_or("")),
         &verse_line,
-        if let Ok(i) = verse_line_number.to_string().parse::<i32>() {
-            if i % 5 == 0 { verse_line_number } else { "" }
-        } else {
-            escaped_num.as_str()
-        }
+        escape_typst(verse_line_number)
     )
 }
 
-pub struct ExportTypst {}
+fn make_inlines(inlines: &[Inline], style: AppCritStyle) -> String {
+    let mut res = String::from("");
+    for inline in inlines {
+        match inline {
+            Inline::Word { word, space_before } => {
+                if *space_before {
+                    res.push(' ');
+                }
+                res.push_str(&escape_typst(&word.word));
+            }
+            //To Do: for the next three formats move space to start of line
+            Inline::Section { number, major } => res.push_str(
+                if *major {
+                    format!(
+                        "#sidenote(format: it => text(size: 1.2em, it.default))[#strong[{}]] ",
+                        number
+                    )
+                } else {
+                    format!("#sidenote[{}] ", number)
+                }
+                .as_str(),
+            ),
+            Inline::Speaker(speaker) => {
+                res.push_str(format!("\n\n#strong[{}] ", escape_typst(speaker)).as_str())
+            }
+            Inline::AppCritMark(number) => {
+                if style == AppCritStyle::Numbered {
+                    res.push_str(format!("#super[{}]", number).as_str())
+                }
+            }
+        }
+    }
+    res
+}
+
+//the built-in document start, which ExportConfig::template replaces
+pub const TYPST_TEMPLATE: &str = r###"#import "@preview/marge:0.1.0": sidenote
+        #let sidenote = sidenote.with(side: left, padding: 3em)
+
+
+        #import "@preview/cuti:0.4.0": fakeitalic
+
+        #set document(title: %TITLE%)
+        #set page(width: %PAPER_WIDTH%, height: %PAPER_HEIGHT%%MARGIN%)
+        #set page(numbering: "1")
+        #counter(page).update(%PAGE_NUM%)
+        #let evenheader = %EVEN_HEADER%
+        #set page(
+          header: context {
+            let page = counter(page).get().first() // Get current page number
+            if calc.odd(page) {
+              align(right, %ODD_HEADER%)
+            } else {
+              align(left, evenheader)
+            }
+          }
+        )
+
+        #let glosshang = par.with(hanging-indent: 2em, justify: false, leading: 0.7em,)
+        #let glossdef = par.with(justify: false, leading: 0.7em,)
+        #let glosstable = table.with(
+            columns: (0.6cm, 8.0cm * %TABLE_SCALE%, 9.0cm * %TABLE_SCALE%),
+            align: start + top,
+            stroke: none,
+            row-gutter: 0.07cm,)
+        #let versetable = table.with(
+            columns: (1.1cm, 9.0cm * %TABLE_SCALE%, 3.0cm),
+            align: start + top,
+            stroke: none,
+            row-gutter: 0.07cm,)
+        #let placegloss = place.with(bottom, dx: -0.8cm * %TABLE_SCALE%)
+        #let placeverse = box.with(pad: (left: 2cm))
+
+        #let indextable = table.with(
+            columns: (90%, 10%),
+            align: (start + top, end + top),
+            stroke: none,
+            inset: 0%,
+            column-gutter: 0cm,
+            row-gutter: 0.225cm,)
+
+        #set par(
+          justify: true,
+          leading: %LEADING%em,
+          spacing: 2em
+        )
+        #set text(
+          font: %FONTS%,
+          size: %FONT_SIZE%pt,
+        )
+"###;
+
+//what the rendered pages use, with where each is defined
+const REQUIRED_DEFINITIONS: [(&str, &str); 9] = [
+    ("glosshang", r"#let\s+glosshang\b"),
+    ("glossdef", r"#let\s+glossdef\b"),
+    ("glosstable", r"#let\s+glosstable\b"),
+    ("versetable", r"#let\s+versetable\b"),
+    ("placegloss", r"#let\s+placegloss\b"),
+    ("indextable", r"#let\s+indextable\b"),
+    ("evenheader", r"#let\s+evenheader\b"),
+    (
+        "sidenote",
+        r"#(?:let\s+sidenote\b|import\s+[^\n]*:[^\n]*\bsidenote\b)",
+    ),
+    (
+        "fakeitalic",
+        r"#(?:let\s+fakeitalic\b|import\s+[^\n]*:[^\n]*\bfakeitalic\b)",
+    ),
+];
+
+#[derive(Default)]
+pub struct ExportTypst {
+    pub config: ExportConfig,
+}
+
+impl ExportTypst {
+    //checks the configured template, if any, defines everything the pages use
+    pub fn validate_template(&self) -> Result<(), GlosserError> {
+        validate_template(
+            self.config.template.as_deref().unwrap_or(TYPST_TEMPLATE),
+            &REQUIRED_DEFINITIONS,
+        )
+    }
+}
+
 impl ExportDocument for ExportTypst {
     fn gloss_entry(&self, gloss_occurrance: &GlossOccurrance, lemma: Option<&str>) -> String {
         if gloss_occurrance.arrowed_state != ArrowedState::Invisible
@@ -69,165 +207,63 @@ impl ExportDocument for ExportTypst {
         }
     }
 
-    fn make_text(
-        &self,
-        gloss_occurrances: &[GlossOccurrance],
-        appcrit_hash: &HashMap<WordUuid, String>,
-    ) -> String {
-        let re = Regex::new("([0-9]+)[.]([0-9]+)").unwrap();
+    fn make_text(&self, page_text: &PageText) -> String {
         let mut res = String::from("");
-        let mut prev_non_space = true;
-        //let mut last_type = WordType::InvalidType;
-        let mut is_verse_section = false;
-        let mut verse_speaker: Option<String> = None;
-        let mut verse_line = String::from("");
-        let mut verse_line_number = String::from("");
-
-        let mut appcrits_page: Vec<String> = vec![];
-
-        for w in gloss_occurrances {
-            if let Some(ap) = appcrit_hash.get(&w.word.uuid) {
-                appcrits_page.push(ap.clone());
-            }
 
-            match w.word.word_type {
-                WordType::VerseLine => {
-                    if !is_verse_section {
-                        res.push_str(
-                            r###"
+        for block in &page_text.blocks {
+            match block {
+                TextBlock::Verse(lines) => {
+                    res.push_str(
+                        r###"
 #versetable(
 "###,
-                        );
-                        is_verse_section = true;
-                    } else {
-                        //previous verse line is complete
-                        res.push_str(
-                            complete_verse_line(verse_speaker, &verse_line, &verse_line_number)
-                                .as_str(),
-                        );
-                        verse_speaker = None;
-                        verse_line = String::from("");
-                    }
-                    verse_line_number = w.word.word.replace("[line]", "");
-                }
-                WordType::WorkTitle => res.push_str(
-                    format!("\n#align(center)[{}]\n\\\n\\\n", escape_typst(&w.word.word)).as_str(),
-                ),
-                WordType::Word | WordType::Punctuation => {
-                    //0 | 1
-                    let punc = vec![
-                        ".", ",", "·", "·", ";", ";", ">", "]", ")", ",\"", ".”", ".\"", "·\"",
-                        "·\"", ".’",
-                    ];
-                    let s = format!(
-                        "{}{}",
-                        if punc.contains(&w.word.word.as_str()) || prev_non_space {
-                            ""
-                        } else {
-                            " "
-                        },
-                        escape_typst(&w.word.word)
                     );
-                    if is_verse_section {
-                        verse_line.push_str(&s);
-                    } else {
-                        res.push_str(&s);
-                    }
-                    prev_non_space = w.word.word == "<" || w.word.word == "[" || w.word.word == "(";
-                }
-                WordType::ParaWithIndent => res.push_str("\n\n#h(2em)\n"),
-                WordType::ParaNoIndent => res.push_str("\n\n"),
-                WordType::SectionTitle => res.push_str(
-                    format!("\\ #align(center)[{}] \\ ", escape_typst(&w.word.word)).as_str(),
-                ),
-                WordType::Section => {
-                    let section_input = w.word.word.replace("[section]", "");
-
-                    let matches = re.captures(&section_input);
-
-                    let s = if let Some(matches) = matches {
-                        let section = matches.get(1).unwrap().as_str();
-                        let subsection = matches.get(2).unwrap().as_str();
-
-                        //To Do: for the next three formats move space to start of line
-                        if subsection == "1" {
-                            format!(
-                                "#sidenote(format: it => text(size: 1.2em, it.default))[#strong[{}]] ",
-                                section
-                            )
-                        } else {
-                            format!("#sidenote[{}] ", subsection)
-                        }
-                    } else {
-                        format!(
-                            "#sidenote(format: it => text(size: 1.2em, it.default))[#strong[{}]] ",
-                            section_input
-                        )
-                    };
-
-                    res.push_str(s.as_str());
-                    prev_non_space = true;
-                }
-                WordType::Speaker => {
-                    //fix me can't add this in middle of a versetable
-                    if is_verse_section {
+                    for line in lines {
                         res.push_str(
                             complete_verse_line(
-                                verse_speaker.clone(),
-                                &verse_line,
-                                &verse_line_number,
+                                line.speaker,
+                                &make_inlines(&line.inlines, self.config.appcrit_notes),
+                                line.display_number(),
                             )
                             .as_str(),
                         );
-                        verse_speaker = None;
-                        verse_line = String::from("");
-                        res.push(')');
-                    }
-
-                    res.push_str(&w.word.word);
-                    if is_verse_section {
-                        res.push_str(
-                            r###"
-#versetable(
-"###,
-                        );
                     }
+                    res.push_str("\n)\n");
                 }
-                WordType::InlineSpeaker => {
-                    if is_verse_section {
-                        verse_speaker = Some(w.word.word.clone());
-                    } else {
-                        res.push_str(format!("\n\n#strong[{}] ", w.word.word).as_str());
-                    }
+                TextBlock::WorkTitle(title) => res.push_str(
+                    format!("\n#align(center)[{}]\n\\\n\\\n", escape_typst(title)).as_str(),
+                ),
+                TextBlock::SectionTitle(title) => {
+                    res.push_str(format!("\\ #align(center)[{}] \\ ", escape_typst(title)).as_str())
                 }
-                WordType::InlineVerseSpeaker => {
-                    if is_verse_section {
-                        verse_speaker = Some(w.word.word.clone());
-                    } else {
-                        res.push_str(format!("\n\n#strong[{}] ", w.word.word).as_str());
+                TextBlock::Speaker(speaker) => {
+                    res.push_str(format!("\n\n{}\n\n", escape_typst(speaker)).as_str())
+                }
+                TextBlock::Paragraph { start, inlines } => {
+                    match start {
+                        ParagraphStart::Indented => res.push_str("\n\n#h(2em)\n"),
+                        ParagraphStart::NotIndented => res.push_str("\n\n"),
+                        ParagraphStart::Continued => (),
                     }
+                    res.push_str(&make_inlines(inlines, self.config.appcrit_notes));
                 }
-                _ => (),
             }
-            //last_type = w.word_type.clone();
         }
 
-        if is_verse_section {
-            //previous verse line is complete
-            res.push_str(
-                complete_verse_line(verse_speaker, &verse_line, &verse_line_number).as_str(),
-            );
-
-            res.push_str("\n)\n");
-        } else {
+        if !matches!(page_text.blocks.last(), Some(TextBlock::Verse(_))) {
             res.push_str("\n\n");
         }
 
-        if !appcrits_page.is_empty() {
+        if !page_text.appcrits.is_empty() {
             res.push_str("\n\n");
         }
-        for ap in appcrits_page {
-            res.push_str(format!("{} \\\n", escape_typst(&ap)).as_str());
+        for ap in &page_text.appcrits {
+            match ap.label(self.config.appcrit_notes) {
+                Some(label) => res.push_str(
+                    format!("#strong[{}] {} \\\n", label, escape_typst(&ap.text)).as_str(),
+                ),
+                None => res.push_str(format!("{} \\\n", escape_typst(&ap.text)).as_str()),
+            }
         }
         res
     }
@@ -248,14 +284,14 @@ impl ExportDocument for ExportTypst {
               header: context {{
                 let page = counter(page).get().first() // Get current page number
                 if calc.odd(page) {{
-                  align(right, "{}")
+                  align(right, {})
                 }} else {{
-                  align(left, "LGI - UPPER LEVEL GREEK")
+                  align(left, evenheader)
                 }}
               }}
             )
             "###,
-            title
+            typst_string(self.config.odd_header(title))
         )
     }
 
@@ -275,100 +311,148 @@ impl ExportDocument for ExportTypst {
     }
 
     fn document_start(&self, title: &str, start_page: usize) -> String {
-        let start = r###"#import "@preview/marge:0.1.0": sidenote
-        #let sidenote = sidenote.with(side: left, padding: 3em)
-
-
-        #import "@preview/cuti:0.4.0": fakeitalic
+        let config = &self.config;
+        let (width, height) = config.paper.size();
+        let margin = match config.margins {
+            Some(m) => format!(
+                ", margin: (top: {}, bottom: {}, inside: {}, outside: {})",
+                inches(m.top),
+                inches(m.bottom),
+                inches(m.inner),
+                inches(m.outer)
+            ),
+            None => String::from(""),
+        };
+        //the tables are laid out for letter paper; typst's own margins are 2.5/21 of the shorter side
+        let table_scale = config.text_width_scale(|paper| {
+            let (width, height) = paper.size();
+            width.min(height) * 2.5 / 21.0
+        });
+        //typst's leading is the space between lines: 0.9em gives the 1.5 spacing of the other formats
+        let leading = (config.line_spacing * 0.6 * 100.0).round() / 100.0;
+        let fonts = match config.font_families.as_slice() {
+            [font] => typst_string(font),
+            fonts => format!(
+                "({})",
+                fonts
+                    .iter()
+                    .map(|f| typst_string(f))
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            ),
+        };
+
+        config
+            .fill_template(TYPST_TEMPLATE, title, start_page, &escape_typst_text)
+            .replace("%TITLE%", &typst_string(&config.full_title(title)))
+            .replace("%PAPER_WIDTH%", &inches(width))
+            .replace("%PAPER_HEIGHT%", &inches(height))
+            .replace("%MARGIN%", &margin)
+            .replace(
+                "%TABLE_SCALE%",
+                &((table_scale * 10000.0).round() / 10000.0).to_string(),
+            )
+            .replace(
+                "%EVEN_HEADER%",
+                &typst_string(config.even_header(COURSE_HEADER)),
+            )
+            .replace("%ODD_HEADER%", &typst_string(config.odd_header(title)))
+            .replace("%LEADING%", &leading.to_string())
+            .replace("%FONTS%", &fonts)
+            .replace("%FONT_SIZE%", &config.font_size.to_string())
+    }
 
-        #set page(width: 8.5in, height: 11in)
-        #set page(numbering: "1")
-        #counter(page).update(%PAGE_NUM%)
+    fn make_index(&self, arrowed_words_index: &[ArrowedWordsIndex]) -> String {
+        const ARROWED_INDEX_TEMPLATE: &str = r##"
         #set page(
           header: context {
             let page = counter(page).get().first() // Get current page number
             if calc.odd(page) {
-              align(right, "{%MAIN_TITLE%}")
+              align(right, "INDEX OF ARROWED WORDS")
             } else {
-              align(left, "LGI - UPPER LEVEL GREEK")
+              align(left, evenheader)
             }
           }
         )
+        "##;
 
-        #let glosshang = par.with(hanging-indent: 2em, justify: false, leading: 0.7em,)
-        #let glossdef = par.with(justify: false, leading: 0.7em,)
-        #let glosstable = table.with(
-            columns: (0.6cm, 8.0cm, 9.0cm),
-            align: start + top,
-            stroke: none,
-            row-gutter: 0.07cm,)
-        #let versetable = table.with(
-            columns: (1.1cm, 9.0cm, 3.0cm),
-            align: start + top,
-            stroke: none,
-            row-gutter: 0.07cm,)
-        #let placegloss = place.with(bottom, dx: -0.8cm)
-        #let placeverse = box.with(pad: (left: 2cm))
-
-        #let indextable = table.with(
-            columns: (90%, 10%),
-            align: (start + top, end + top),
-            stroke: none,
-            inset: 0%,
-            column-gutter: 0cm,
-            row-gutter: 0.225cm,)
-
-        #set par(
-          justify: true,
-          leading: 0.9em,
-          spacing: 2em
-        )
-        #set text(
-          font: "IFAO-Grec Unicode",
-          size: 12pt,
-        )
-"###;
-
-        start
-            .replace("%MAIN_TITLE%", title)
-            .replace("%PAGE_NUM%", start_page.to_string().as_str())
+        let layout = &self.config.index;
+        let leader = match layout.leader {
+            Leader::Dots => "#box(width: 1fr, repeat[.])",
+            Leader::Line => "#box(width: 1fr, line(length: 100%, stroke: 0.5pt))",
+            Leader::Space => "#h(1fr)",
+        };
+        let mut latex = String::from(ARROWED_INDEX_TEMPLATE);
+        if layout.columns() > 1 {
+            latex.push_str(format!("#columns({})[\n", layout.columns()).as_str());
+        }
+        latex.push_str("#indextable(\n");
+        let mut letter = None;
+        for (i, gloss) in arrowed_words_index.iter().enumerate() {
+            //tables can't hold a break, so each column gets a table of its own
+            if layout.break_before(i) {
+                latex.push_str("\n)\n#colbreak()\n#indextable(\n");
+            }
+            if layout.letter_headings {
+                let gloss_letter = index_letter(&gloss.gloss_lemma);
+                if letter != Some(gloss_letter) {
+                    latex.push_str(
+                        format!(
+                            "table.cell(colspan: 2)[#strong[{}]],",
+                            escape_typst_text(&gloss_letter.to_string())
+                        )
+                        .as_str(),
+                    );
+                    letter = Some(gloss_letter);
+                }
+            }
+            latex.push_str(
+                format!(
+                    "[{} {}],[{} {}],",
+                    escape_typst(&gloss.gloss_lemma),
+                    leader,
+                    leader,
+                    gloss.page_number
+                )
+                .as_str(),
+            );
+        }
+        latex.push_str("\n)");
+        if layout.columns() > 1 {
+            latex.push_str("\n]");
+        }
+        latex
     }
 
-    fn make_index(&self, arrowed_words_index: &[ArrowedWordsIndex]) -> String {
-        const ARROWED_INDEX_TEMPLATE: &str = r##"
+    fn make_glossary(&self, glossary: &[GlossaryEntry]) -> String {
+        const GLOSSARY_TEMPLATE: &str = r##"
+        #pagebreak()
         #set page(
           header: context {
             let page = counter(page).get().first() // Get current page number
             if calc.odd(page) {
-              align(right, "INDEX OF ARROWED WORDS")
+              align(right, "GLOSSARY")
             } else {
-              align(left, "LGI - UPPER LEVEL GREEK")
+              align(left, evenheader)
             }
           }
         )
-        #indextable(
         "##;
 
-        let mut latex = String::from(ARROWED_INDEX_TEMPLATE);
-        let mut gloss_per_page = 0;
-        for gloss in arrowed_words_index {
-            latex.push_str(
+        let mut typst = String::from(GLOSSARY_TEMPLATE);
+        for entry in glossary {
+            typst.push_str(
                 format!(
-                    "[{} #box(width: 1fr, repeat[.])],[#box(width: 1fr, repeat[.]) {}],",
-                    gloss.gloss_lemma, gloss.page_number
+                    "#glosshang[#strong[{}] ({}) {} ({})]\n",
+                    escape_typst(&entry.lemma),
+                    escape_typst_text(&entry.pos),
+                    escape_typst(&entry.def),
+                    entry.frequency()
                 )
                 .as_str(),
             );
-
-            gloss_per_page += 1;
-            if gloss_per_page > 43 {
-                gloss_per_page = 0;
-                //latex.push_str("\n#pagebreak()\n");
-                //latex.push_str("\\noindent \n");
-            }
         }
-        latex.push_str("\n)");
-        latex
+        typst
     }
 
     fn blank_page(&self) -> String {
@@ -2,10 +2,10 @@ use crate::{GlossUuid, get_entity};
 use quick_xml::Reader;
 use quick_xml::events::Event;
 use quick_xml::name::QName;
-use serde::Serialize;
+use serde::{Deserialize, Serialize};
 use uuid::Uuid;
 
-#[derive(Default, Clone, Debug, PartialEq, Serialize)]
+#[derive(Default, Clone, Debug, PartialEq, Serialize, Deserialize)]
 pub struct Gloss {
     pub uuid: GlossUuid,
     pub parent_id: Option<GlossUuid>,
@@ -21,7 +21,7 @@ pub struct Gloss {
     //pub origin: String,
 }
 
-#[derive(Clone, Debug, PartialEq)]
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
 pub struct Glosses {
     pub gloss_name: String,
     pub gloss: Vec<Gloss>,
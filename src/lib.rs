@@ -1,21 +1,47 @@
+pub mod arrows;
+pub mod breaks;
+pub mod exportconfig;
+pub mod exportepub;
 #[allow(dead_code)]
 pub mod exportfodt;
 pub mod exporthtml;
 pub mod exportlatex;
+pub mod exportmarkdown;
+pub mod exportodt;
+pub mod exportplaintext;
 pub mod exporttypst;
 pub mod glosses;
 pub mod import;
 pub mod lines;
+pub mod markup;
+pub mod model;
+pub mod pagecounts;
+pub mod pagemap;
+#[cfg(feature = "pdf")]
+pub mod pdf;
+pub mod range;
+pub mod serialize;
+pub mod tei;
 pub mod texts;
 pub mod update;
+pub mod worklist;
 
+pub use exportconfig::ExportConfig;
+pub use exportepub::ExportEpub;
 pub use exportfodt::ExportFodt;
 pub use exporthtml::ExportHTML;
 pub use exportlatex::ExportLatex;
+pub use exportmarkdown::ExportMarkdown;
+pub use exportplaintext::ExportPlainText;
 pub use exporttypst::ExportTypst;
 
 use glosses::Gloss;
 use glosses::Glosses;
+use model::{
+    DocumentModel, GlossEntry, GlossaryEntry, PageModel, PageText, TextPageModel, sort_index,
+};
+use pagemap::PageContent;
+use range::ProcessSummary;
 use texts::{Text, Word, WordType};
 
 //https://www.reddit.com/r/rust/comments/1ggl7am/how_to_use_typst_as_programmatically_using_rust/
@@ -30,6 +56,7 @@ use icu_provider_blob::BlobDataProvider;
 use quick_xml::Reader;
 use quick_xml::events::Event;
 use quick_xml::name::QName;
+use serde::{Deserialize, Serialize};
 
 use std::collections::BTreeMap;
 use std::collections::{HashMap, HashSet};
@@ -59,7 +86,7 @@ type GlossUuid = Uuid;
 // as we build GlossOccurrance, query the arrowed_words hash which we built ahead of time
 // if found add current seq value to the gloss-count-seq hash table.
 // we also keep track of the running count of each gloss there which then serves as the total count at the end.
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq)]
 pub struct GlossOccurrance<'a> {
     word: &'a Word,
     gloss: Option<&'a Gloss>,
@@ -68,6 +95,7 @@ pub struct GlossOccurrance<'a> {
     arrowed_state: ArrowedState,
 }
 
+#[derive(Clone)]
 pub struct GlossSeqCount {
     count: usize,
     arrowed_seq: Option<usize>,
@@ -77,6 +105,7 @@ pub struct GlossPageOptions {
     pub filter_unique: bool,
     pub filter_invisible: bool,
     pub sort_key: bool,
+    pub glossary: bool, //adds a glossary of every gloss used after the index
 }
 
 #[derive(Debug, PartialEq)]
@@ -139,13 +168,13 @@ impl fmt::Display for GlosserError {
 }
 
 //the word id where a gloss is arrowed
-#[derive(Default, Clone, Debug, PartialEq)]
+#[derive(Default, Clone, Debug, PartialEq, Serialize, Deserialize)]
 pub struct GlossArrow {
     gloss_uuid: GlossUuid,
     word_uuid: WordUuid,
 }
 
-#[derive(Default, Clone, Debug, PartialEq)]
+#[derive(Default, Clone, Debug, PartialEq, Serialize, Deserialize)]
 pub struct SequenceDescription {
     pub name: String,
     pub start_page: usize,
@@ -165,10 +194,12 @@ impl SequenceDescription {
 }
 
 //for the index of arrowed words at the back of the book
+#[derive(Debug, Clone, PartialEq)]
 pub struct ArrowedWordsIndex {
     gloss_lemma: String,
     gloss_sort: String,
     page_number: usize,
+    word_uuid: WordUuid, //the word where the gloss is arrowed
 }
 
 #[derive(Clone, Debug, PartialEq)]
@@ -178,7 +209,7 @@ pub enum ArrowedState {
     Invisible,
 }
 
-#[derive(Default, Clone, Debug, PartialEq)]
+#[derive(Default, Clone, Debug, PartialEq, Serialize, Deserialize)]
 pub struct TextDescription {
     pub display: bool,
     pub text: String, //the file_name of the text xml
@@ -187,7 +218,7 @@ pub struct TextDescription {
     pub end: Option<WordUuid>,
 }
 
-#[derive(Clone, Debug, PartialEq)]
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
 pub struct Sequence {
     pub sequence_description: SequenceDescription,
     pub glosses: Vec<Glosses>,
@@ -314,64 +345,13 @@ impl Sequence {
             for t in &self.texts {
                 let mut text_vec = vec![];
                 for w in &t.words {
-                    let mut gloss: Option<&Gloss> = None;
-                    let gloss_seq = if let Some(g) = w.gloss_uuid {
-                        if let Some(temp_gloss_ref) = glosses_hash.get(&g) {
-                            gloss = Some(temp_gloss_ref);
-                        }
-                        if let Some(arrowed_gloss_uuid) = arrowed_words_hash.get(&w.uuid)
-                            && gloss.is_some()
-                            && *arrowed_gloss_uuid == gloss.unwrap().uuid
-                        {
-                            Some(i)
-                        } else {
-                            None
-                        }
-                    } else {
-                        None
-                    };
-
-                    let mut running_count: Option<usize> = None;
-                    let mut real_gloss_seq: Option<usize> = None;
-                    if let Some(g) = gloss {
-                        if let Some(gsc) = gloss_seq_count.get_mut(&g.uuid) {
-                            // if g.lemma == "περί" {
-                            //     println!("{} {} {}", g.lemma, gsc.count, t.text_name);
-                            // }
-                            gsc.count += 1;
-                            running_count = Some(gsc.count);
-                            real_gloss_seq = gsc.arrowed_seq;
-                            gsc.arrowed_seq = if gsc.arrowed_seq.is_some() {
-                                gsc.arrowed_seq
-                            } else {
-                                gloss_seq
-                            };
-                        } else {
-                            running_count = Some(1);
-                            real_gloss_seq = gloss_seq;
-                            gloss_seq_count.insert(
-                                g.uuid,
-                                GlossSeqCount {
-                                    count: 1,
-                                    arrowed_seq: gloss_seq,
-                                },
-                            );
-                        }
-                    }
-
-                    text_vec.push(GlossOccurrance {
-                        word: w,
-                        gloss, //gloss or None
-                        arrowed_state: if real_gloss_seq.is_some() && real_gloss_seq.unwrap() < i {
-                            ArrowedState::Invisible
-                        } else if gloss_seq.is_some() && gloss_seq.unwrap() == i {
-                            ArrowedState::Arrowed
-                        } else {
-                            ArrowedState::Visible
-                        },
-                        running_count,
-                        total_count: None, //for now, we won't know total count until the end of this loop, so set it then
-                    });
+                    text_vec.push(next_gloss_occurrance(
+                        w,
+                        i,
+                        &glosses_hash,
+                        &arrowed_words_hash,
+                        &mut gloss_seq_count,
+                    ));
                     i += 1;
                 }
 
@@ -640,224 +620,201 @@ impl Sequence {
         export: &impl ExportDocument,
         options: &GlossPageOptions,
     ) -> String {
-        let mut arrowed_words_index: Vec<ArrowedWordsIndex> = vec![];
-        let mut page_number = self.sequence_description.start_page;
+        let mut doc = vec![];
+        self.write_document(gloss_occurrances, export, options, &mut doc)
+            .expect("writing to a Vec does not fail");
+        String::from_utf8(doc).expect("exporters write UTF-8")
+    }
 
-        let mut appcrit_hash = HashMap::default();
-        for t in &self.texts {
-            if let Some(appcrits) = &t.appcrits {
-                for ap in appcrits {
-                    appcrit_hash.insert(ap.word_uuid, ap.entry.clone());
-                }
+    //writes the document page by page as it is rendered, so only one page is held in memory at a time
+    pub fn write_document<W: Write>(
+        &self,
+        gloss_occurrances: &[Vec<GlossOccurrance>],
+        export: &impl ExportDocument,
+        options: &GlossPageOptions,
+        out: &mut W,
+    ) -> std::io::Result<()> {
+        let appcrit_hash = self.make_appcrit_hash();
+        let map = self.make_page_map();
+        let mut index = vec![];
+
+        out.write_all(
+            export
+                .document_start(
+                    &self.sequence_description.name,
+                    self.sequence_description.start_page,
+                )
+                .as_bytes(),
+        )?;
+        for page in &map.pages {
+            match &page.content {
+                PageContent::Blank => out.write_all(export.blank_page().as_bytes())?,
+                PageContent::Text(text_page) => write_page(
+                    &self.make_text_page_model(
+                        gloss_occurrances,
+                        text_page,
+                        page.page_number,
+                        &appcrit_hash,
+                        &mut index,
+                        options,
+                    ),
+                    export,
+                    out,
+                )?,
             }
         }
+        sort_index(&mut index);
+        let glossary = if options.glossary {
+            self.make_glossary(gloss_occurrances, &map)
+        } else {
+            vec![]
+        };
+        write_document_end(&index, &glossary, export, out)
+    }
 
-        let mut doc = export.document_start(&self.sequence_description.name, page_number);
-        //if page_number is even, insert blank page
-        if page_number.is_multiple_of(2) {
-            doc.push_str(export.blank_page().as_str());
-            page_number += 1;
-        }
-        let mut text_index = 0;
-        for (t_idx, t) in self.texts.iter().enumerate() {
-            //set pages vector from comma separated string
-            let mut pages: Vec<usize> = vec![];
-            if !self.sequence_description.texts[t_idx]
-                .words_per_page
-                .is_empty()
-            {
-                pages = self.sequence_description.texts[t_idx]
-                    .words_per_page
-                    .split(',')
-                    .filter_map(|s| s.trim().parse::<usize>().ok())
-                    .collect();
-            }
+    pub fn make_single_page(
+        &self,
+        summary: &ProcessSummary,
+        export: &impl ExportDocument,
+        options: &GlossPageOptions,
+        selected_page_number: usize,
+    ) -> Result<String, GlosserError> {
+        let mut arrowed_words_index: Vec<ArrowedWordsIndex> = vec![];
 
-            let mut index = 0;
-            // should this text be displayed or not?: former used a variabld on the text itself
-            if !self.sequence_description.texts[t_idx].display {
-                text_index += 1;
-                continue;
-            }
+        let appcrit_hash = self.make_appcrit_hash();
 
-            for (i, w) in pages.iter().enumerate() {
-                if i == pages.len() - 1 {
-                    doc.push_str(
-                        make_page(
-                            &gloss_occurrances[text_index][index..],
-                            &appcrit_hash,
-                            export,
-                            if i == 0 { "" } else { &t.text_name },
-                            &mut arrowed_words_index,
-                            page_number,
-                            options,
-                        )
-                        .as_str(),
-                    );
-                    let count = gloss_occurrances[text_index].len() - index;
-                    index += count;
-                } else {
-                    if gloss_occurrances[text_index].len() < index + w {
-                        println!(
-                            "go out of range text: {}, len: {}, range: {}",
-                            text_index,
-                            gloss_occurrances[text_index].len(),
-                            index + w
-                        );
-                        //return String::from("");
-                        continue;
-                    }
-                    // else {
-                    //     println!("go in range text: {}", text_index);
-                    // }
-                    doc.push_str(
-                        make_page(
-                            &gloss_occurrances[text_index][index..index + w],
-                            &appcrit_hash,
-                            export,
-                            if i == 0 { "" } else { &t.text_name },
-                            &mut arrowed_words_index,
-                            page_number,
-                            options,
-                        )
-                        .as_str(),
-                    );
-                    index += w;
-                }
-                page_number += 1;
-            }
-            if !page_number.is_multiple_of(2) {
-                page_number += 1;
-                doc.push_str(export.blank_page().as_str());
+        match self
+            .make_page_map()
+            .page(selected_page_number)
+            .map(|p| &p.content)
+        {
+            //only the page's own text is processed, up to the end of the page
+            Some(PageContent::Text(text_page)) => {
+                let page_occurrances =
+                    self.process_range(summary, text_page.text_index, text_page.words.clone())?;
+                Ok(render_page(
+                    &self.make_text_page_model_from(
+                        &page_occurrances,
+                        text_page,
+                        selected_page_number,
+                        &appcrit_hash,
+                        &mut arrowed_words_index,
+                        options,
+                    ),
+                    export,
+                ))
             }
-            doc.push_str(export.blank_page().as_str());
-            page_number += 1;
-            text_index += 1;
+            Some(PageContent::Blank) => Ok(export.blank_page()),
+            None => Err(GlosserError::NotFound(format!(
+                "page {} is not in the sequence",
+                selected_page_number
+            ))),
         }
-        //make index
-        if !arrowed_words_index.is_empty() {
-            let mut options = CollatorOptions::default();
-            options.strength = Some(Strength::Quaternary);
-            options.case_level = Some(CaseLevel::Off); //whether to distinguish case above the tertiary level
-            let blob_provider = BlobDataProvider::try_new_from_static_blob(include_bytes!(
-                "../greek_collation_blob.postcard"
-            ))
-            .unwrap();
-
-            let collator = Collator::try_new_with_buffer_provider(
-                &blob_provider,
-                locale!("el-u-kn-true").into(), //kn-true means to sort numbers numerically rather than as strings
-                options,
-            )
-            .expect("Greek collation data present");
+    }
+}
 
-            arrowed_words_index.sort_by(|a, b| {
-                collator.as_borrowed().compare(&a.gloss_sort, &b.gloss_sort)
-                // a.gloss_sort
-                //     .to_lowercase()
-                //     .cmp(&b.gloss_sort.to_lowercase())
-            });
+pub fn render_document(model: &DocumentModel, export: &impl ExportDocument) -> String {
+    let mut doc = vec![];
+    write_rendered_document(model, export, &mut doc).expect("writing to a Vec does not fail");
+    String::from_utf8(doc).expect("exporters write UTF-8")
+}
 
-            doc.push_str(export.make_index(&arrowed_words_index).as_str());
+pub fn write_rendered_document<W: Write>(
+    model: &DocumentModel,
+    export: &impl ExportDocument,
+    out: &mut W,
+) -> std::io::Result<()> {
+    out.write_all(
+        export
+            .document_start(&model.title, model.start_page)
+            .as_bytes(),
+    )?;
+    for page in &model.pages {
+        match page {
+            PageModel::Blank { .. } => out.write_all(export.blank_page().as_bytes())?,
+            PageModel::Text(text_page) => write_page(text_page, export, out)?,
         }
-
-        doc.push_str(&export.document_end());
-        doc
     }
+    write_document_end(&model.index, &model.glossary, export, out)
+}
 
-    pub fn make_single_page(
-        &self,
-        gloss_occurrances: &[Vec<GlossOccurrance>],
-        export: &impl ExportDocument,
-        options: &GlossPageOptions,
-        selected_page_number: usize,
-    ) -> String {
-        let mut arrowed_words_index: Vec<ArrowedWordsIndex> = vec![];
-        let mut page_number = self.sequence_description.start_page;
-
-        let appcrit_hash = HashMap::default();
-        // for t in &seq.texts {
-        //     if let Some(appcrits) = &t.appcrits {
-        //         for ap in &appcrits.appcrits {
-        //             appcrit_hash.insert(ap.word_uuid, ap.entry.clone());
-        //         }
-        //     }
-        // }
-
-        let doc = String::from(""); //export.document_start(&seq.sequence_description.name, page_number);
-        //if page_number is even, insert blank page
-
-        let mut text_index = 0;
-        for (t_idx, t) in self.texts.iter().enumerate() {
-            //set pages vector from comma separated string
-            let mut pages: Vec<usize> = vec![];
-            if !self.sequence_description.texts[t_idx]
-                .words_per_page
-                .is_empty()
-            {
-                pages = self.sequence_description.texts[t_idx]
-                    .words_per_page
-                    .split(',')
-                    .filter_map(|s| s.trim().parse::<usize>().ok())
-                    .collect();
-            }
+fn write_document_end<W: Write>(
+    index: &[ArrowedWordsIndex],
+    glossary: &[GlossaryEntry],
+    export: &impl ExportDocument,
+    out: &mut W,
+) -> std::io::Result<()> {
+    if !index.is_empty() {
+        out.write_all(export.make_index(index).as_bytes())?;
+    }
+    if !glossary.is_empty() {
+        out.write_all(export.make_glossary(glossary).as_bytes())?;
+    }
+    out.write_all(export.document_end().as_bytes())
+}
 
-            let mut index = 0;
-            // should this text be displayed or not?: former used a variabld on the text itself
-            if !self.sequence_description.texts[t_idx].display {
-                text_index += 1;
-                continue;
-            }
+//computes the occurrance of the word at sequence index i, updating the running gloss counts
+fn next_gloss_occurrance<'a>(
+    w: &'a Word,
+    i: usize,
+    glosses_hash: &HashMap<GlossUuid, &'a Gloss>,
+    arrowed_words_hash: &HashMap<WordUuid, GlossUuid>,
+    gloss_seq_count: &mut HashMap<GlossUuid, GlossSeqCount>,
+) -> GlossOccurrance<'a> {
+    let mut gloss: Option<&Gloss> = None;
+    let gloss_seq = if let Some(g) = w.gloss_uuid {
+        if let Some(temp_gloss_ref) = glosses_hash.get(&g) {
+            gloss = Some(temp_gloss_ref);
+        }
+        if let Some(arrowed_gloss_uuid) = arrowed_words_hash.get(&w.uuid)
+            && gloss.is_some()
+            && *arrowed_gloss_uuid == gloss.unwrap().uuid
+        {
+            Some(i)
+        } else {
+            None
+        }
+    } else {
+        None
+    };
 
-            for (i, w) in pages.iter().enumerate() {
-                if i == pages.len() - 1 {
-                    if page_number == selected_page_number {
-                        return make_page(
-                            &gloss_occurrances[text_index][index..],
-                            &appcrit_hash,
-                            export,
-                            if i == 0 { "" } else { &t.text_name },
-                            &mut arrowed_words_index,
-                            page_number,
-                            options,
-                        );
-                    }
-                    let count = gloss_occurrances[text_index].len() - index;
-                    index += count;
-                } else {
-                    if gloss_occurrances[text_index].len() < index + w {
-                        println!(
-                            "go out of range text: {}, len: {}, range: {}",
-                            text_index,
-                            gloss_occurrances[text_index].len(),
-                            index + w
-                        );
-                        //return String::from("");
-                        continue;
-                    }
-                    // else {
-                    //     println!("go in range text: {}", text_index);
-                    // }
-                    if page_number == selected_page_number {
-                        return make_page(
-                            &gloss_occurrances[text_index][index..index + w],
-                            &appcrit_hash,
-                            export,
-                            if i == 0 { "" } else { &t.text_name },
-                            &mut arrowed_words_index,
-                            page_number,
-                            options,
-                        );
-                    }
-                    index += w;
-                }
-                page_number += 1;
-            }
-            text_index += 1;
+    let mut running_count: Option<usize> = None;
+    let mut real_gloss_seq: Option<usize> = None;
+    if let Some(g) = gloss {
+        if let Some(gsc) = gloss_seq_count.get_mut(&g.uuid) {
+            gsc.count += 1;
+            running_count = Some(gsc.count);
+            real_gloss_seq = gsc.arrowed_seq;
+            gsc.arrowed_seq = if gsc.arrowed_seq.is_some() {
+                gsc.arrowed_seq
+            } else {
+                gloss_seq
+            };
+        } else {
+            running_count = Some(1);
+            real_gloss_seq = gloss_seq;
+            gloss_seq_count.insert(
+                g.uuid,
+                GlossSeqCount {
+                    count: 1,
+                    arrowed_seq: gloss_seq,
+                },
+            );
         }
+    }
 
-        //doc.push_str(&export.document_end());
-        doc //error
+    GlossOccurrance {
+        word: w,
+        gloss, //gloss or None
+        arrowed_state: if real_gloss_seq.is_some() && real_gloss_seq.unwrap() < i {
+            ArrowedState::Invisible
+        } else if gloss_seq.is_some() && gloss_seq.unwrap() == i {
+            ArrowedState::Arrowed
+        } else {
+            ArrowedState::Visible
+        },
+        running_count,
+        total_count: None, //for now, we won't know total count until the end of this loop, so set it then
     }
 }
 
@@ -865,18 +822,33 @@ pub trait ExportDocument {
     fn document_start(&self, title: &str, start_page: usize) -> String;
     fn blank_page(&self) -> String;
     fn page_start(&self, title: &str, page_number: usize) -> String;
-    fn make_text(
-        &self,
-        gloss_occurrances: &[GlossOccurrance],
-        appcrit_hash: &HashMap<WordUuid, String>,
-    ) -> String;
+    fn make_text(&self, page_text: &PageText) -> String;
     fn page_gloss_start(&self) -> String;
     fn gloss_entry(&self, gloss_occurrance: &GlossOccurrance, lemma: Option<&str>) -> String;
     fn page_end(&self) -> String;
     fn make_index(&self, arrowed_words_index: &[ArrowedWordsIndex]) -> String;
+    fn make_glossary(&self, glossary: &[GlossaryEntry]) -> String;
     fn document_end(&self) -> String;
 }
 
+//the collator used everywhere glosses or lemmas are sorted
+fn greek_collator() -> Collator {
+    let mut options = CollatorOptions::default();
+    options.strength = Some(Strength::Quaternary);
+    options.case_level = Some(CaseLevel::Off); //whether to distinguish case above the tertiary level
+    let blob_provider = BlobDataProvider::try_new_from_static_blob(include_bytes!(
+        "../greek_collation_blob.postcard"
+    ))
+    .unwrap();
+
+    Collator::try_new_with_buffer_provider(
+        &blob_provider,
+        locale!("el-u-kn-true").into(), //kn-true means to sort numbers numerically rather than as strings
+        options,
+    )
+    .expect("Greek collation data present")
+}
+
 //used for index
 fn get_small_lemma(s: &str) -> String {
     let a = s.split(",");
@@ -894,7 +866,7 @@ fn get_small_lemma(s: &str) -> String {
 }
 
 fn filter_and_sort_glosses<'a>(
-    gloss_occurrances: &'a [GlossOccurrance],
+    gloss_occurrances: &[GlossOccurrance<'a>],
     arrowed_words_index: &mut Vec<ArrowedWordsIndex>,
     page_number: usize,
     options: &GlossPageOptions,
@@ -912,6 +884,7 @@ fn filter_and_sort_glosses<'a>(
                         gloss_lemma: get_small_lemma(&gg.lemma),
                         gloss_sort: gg.sort_key.to_owned(),
                         page_number,
+                        word_uuid: g.word.uuid,
                     });
                 }
                 if options.filter_unique {
@@ -931,20 +904,7 @@ fn filter_and_sort_glosses<'a>(
         sorted_glosses = unique.values().cloned().collect();
     }
     if options.sort_key {
-        let mut options = CollatorOptions::default();
-        options.strength = Some(Strength::Quaternary);
-        options.case_level = Some(CaseLevel::Off); //whether to distinguish case above the tertiary level
-        let blob_provider = BlobDataProvider::try_new_from_static_blob(include_bytes!(
-            "../greek_collation_blob.postcard"
-        ))
-        .unwrap();
-
-        let collator = Collator::try_new_with_buffer_provider(
-            &blob_provider,
-            locale!("el-u-kn-true").into(), //kn-true means to sort numbers numerically rather than as strings
-            options,
-        )
-        .expect("Greek collation data present");
+        let collator = greek_collator();
 
         sorted_glosses.sort_by(|a, b| {
             collator
@@ -956,26 +916,34 @@ fn filter_and_sort_glosses<'a>(
     sorted_glosses
 }
 
-fn make_page(
-    gloss_occurrances: &[GlossOccurrance],
-    appcrit_hash: &HashMap<WordUuid, String>,
-    export: &impl ExportDocument,
-    title: &str,
-    arrowed_words_index: &mut Vec<ArrowedWordsIndex>,
-    page_number: usize,
-    options: &GlossPageOptions,
-) -> String {
-    let mut page = export.page_start(title, page_number);
-    page.push_str(&export.make_text(gloss_occurrances, appcrit_hash));
-
-    page.push_str(&export.page_gloss_start());
-
-    let v = filter_and_sort_glosses(gloss_occurrances, arrowed_words_index, page_number, options);
+fn get_gloss_string(glosses: &[GlossEntry], export: &impl ExportDocument) -> String {
+    let mut res = String::from("");
+    for g in glosses {
+        res.push_str(
+            export
+                .gloss_entry(&g.occurrance, g.lemma.as_deref())
+                .as_str(),
+        );
+    }
+    res
+}
 
-    page.push_str(&get_gloss_string(&v, export));
+fn render_page(page: &TextPageModel, export: &impl ExportDocument) -> String {
+    let mut res = vec![];
+    write_page(page, export, &mut res).expect("writing to a Vec does not fail");
+    String::from_utf8(res).expect("exporters write UTF-8")
+}
 
-    page.push_str(&export.page_end());
-    page
+fn write_page<W: Write>(
+    page: &TextPageModel,
+    export: &impl ExportDocument,
+    out: &mut W,
+) -> std::io::Result<()> {
+    out.write_all(export.page_start(&page.title, page.page_number).as_bytes())?;
+    out.write_all(export.make_text(&page.text).as_bytes())?;
+    out.write_all(export.page_gloss_start().as_bytes())?;
+    out.write_all(get_gloss_string(&page.glosses, export).as_bytes())?;
+    out.write_all(export.page_end().as_bytes())
 }
 
 fn get_entity(e: Cow<'_, str>) -> &str {
@@ -1011,22 +979,6 @@ fn sanitize_greek(s: &str) -> String {
         .replace('\u{0344}', "\u{0308}\u{0301}")
 }
 
-fn get_gloss_string(glosses: &[GlossOccurrance], export: &impl ExportDocument) -> String {
-    let mut res = String::from("");
-    for g in glosses {
-        if let Some(some_gloss) = g.gloss {
-            res.push_str(
-                export
-                    .gloss_entry(g, Some(&sanitize_greek(&some_gloss.lemma)))
-                    .as_str(),
-            );
-        } else {
-            res.push_str(export.gloss_entry(g, None).as_str());
-        }
-    }
-    res
-}
-
 pub fn read_seq_desc_xml(xml: &str) -> Result<SequenceDescription, quick_xml::Error> {
     let mut reader = Reader::from_str(xml);
     reader.config_mut(); //.trim_text(true); // Trim whitespace from text nodes
@@ -1310,8 +1262,9 @@ pub fn create_sequence_zip(seq: &Sequence, seq_file: &str) -> Option<Vec<u8>> {
         filter_unique: true,
         filter_invisible: true,
         sort_key: true,
+        glossary: false,
     };
-    let doc = seq.make_document(&gloss_occurrances, &ExportLatex {}, &doc_options);
+    let doc = seq.make_document(&gloss_occurrances, &ExportLatex::default(), &doc_options);
     let name = "doc.tex";
     let _ = zip.start_file(name, options);
     let _ = zip.write_all(doc.as_bytes());
@@ -1553,10 +1506,12 @@ mod tests {
                 AppCrit {
                     word_uuid: Uuid::parse_str("cc402eca-165d-4af0-9514-4c57aee17bb7").unwrap(),
                     entry: String::from("1.4 ἀγανακτήσειε Η; οὐκ ἀγανακτείση P$^1$ -οίη P$^c$"),
+                    ..Default::default()
                 },
                 AppCrit {
                     word_uuid: Uuid::parse_str("8680e45e-f6e0-4c9d-aed4-d0deb9470b4f").unwrap(),
                     entry: String::from("2.1 ἡγοῖσθε (OCT, Carey); ἡγεῖσθαι P"),
+                    ..Default::default()
                 },
             ]),
             // words_per_page: String::from(
@@ -1615,10 +1570,12 @@ mod tests {
                 AppCrit {
                     word_uuid: Uuid::parse_str("cc402eca-165d-4af0-9514-4c57aee17bb7").unwrap(),
                     entry: String::from("1.4 ἀγανακτήσειε Η; οὐκ ἀγανακτείση P$^1$ -οίη P$^c$"),
+                    ..Default::default()
                 },
                 AppCrit {
                     word_uuid: Uuid::parse_str("8680e45e-f6e0-4c9d-aed4-d0deb9470b4f").unwrap(),
                     entry: String::from("2.1 ἡγοῖσθε (OCT, Carey); ἡγεῖσθαι P"),
+                    ..Default::default()
                 },
             ]),
         };
@@ -2071,7 +2028,7 @@ mod tests {
         let gloss_path = "../gkvocab_data/glosses.xml";
         let output_path = "../gkvocab_data/justhq.fodt";
         let page_number = 1;
-        let export = ExportFodt {};
+        let export = ExportFodt::default();
         let mut doc = export.document_start("", page_number);
 
         let mut hq_glosses_vec: Vec<Gloss> = Vec::new();
@@ -2115,7 +2072,9 @@ mod tests {
                     .as_str(),
                 );
                 doc.push_str(&export.page_gloss_start());
-                doc.push_str(get_gloss_string(&gloss_ocurrances, &export).as_str());
+                let entries: Vec<GlossEntry> =
+                    gloss_ocurrances.into_iter().map(GlossEntry::new).collect();
+                doc.push_str(get_gloss_string(&entries, &export).as_str());
                 doc.push_str(
                     r##"
         </table:table>
@@ -2145,11 +2104,12 @@ mod tests {
             filter_unique: false,
             filter_invisible: false,
             sort_key: false,
+            glossary: false,
         };
 
         let doc = seq.as_ref().unwrap().make_document(
             &gloss_occurrances.unwrap(),
-            &ExportHTML {},
+            &ExportHTML::default(),
             &options,
         );
         let output_path = "../gkvocab_data/ulgv3.html";
@@ -2168,11 +2128,12 @@ mod tests {
             filter_unique: true,
             filter_invisible: true,
             sort_key: true,
+            glossary: false,
         };
 
         let doc = seq.as_ref().unwrap().make_document(
             &gloss_occurrances.unwrap(),
-            &ExportLatex {},
+            &ExportLatex::default(),
             &options,
         );
         let output_path = "../gkvocab_data/ulgv3.tex";
@@ -2191,11 +2152,12 @@ mod tests {
             filter_unique: true,
             filter_invisible: true,
             sort_key: true,
+            glossary: false,
         };
 
         let doc = seq.as_ref().unwrap().make_document(
             &gloss_occurrances.unwrap(),
-            &ExportTypst {},
+            &ExportTypst::default(),
             &options,
         );
         let output_path = "../gkvocab_data/ulgv3.typ";
@@ -2214,11 +2176,12 @@ mod tests {
             filter_unique: true,
             filter_invisible: true,
             sort_key: true,
+            glossary: false,
         };
 
         let doc = seq.as_ref().unwrap().make_document(
             &gloss_occurrances.unwrap(),
-            &ExportFodt {},
+            &ExportFodt::default(),
             &options,
         );
         let output_path = "../gkvocab_data/ulgv3.fodt";
@@ -2237,15 +2200,16 @@ mod tests {
             filter_unique: false,
             filter_invisible: false,
             sort_key: false,
+            glossary: false,
         };
 
         //let doc = make_document(
-        let doc = seq.as_ref().unwrap().make_single_page(
-            &gloss_occurrances.unwrap(),
-            &ExportHTML {},
-            &options,
-            24,
-        );
+        let summary = seq.as_ref().unwrap().make_process_summary().unwrap();
+        let doc = seq
+            .as_ref()
+            .unwrap()
+            .make_single_page(&summary, &ExportHTML::default(), &options, 24)
+            .unwrap();
         let output_path = "../gkvocab_data/ulgv3_page.html";
         let _ = fs::write(output_path, &doc);
     }
@@ -2258,7 +2222,11 @@ mod tests {
         let gloss_occurrances = seq.as_ref().unwrap().process();
         assert!(gloss_occurrances.is_ok());
         //println!("{:?}", gloss_occurrances.as_ref().unwrap()[3]);
-        let words = lines::count_lines(&gloss_occurrances.unwrap()[3]);
+        let words = lines::count_lines(
+            &gloss_occurrances.unwrap()[3],
+            &lines::PageGeometry::default(),
+            &breaks::BreakRules::default(),
+        );
         println!("{:?}", words);
     }
 
@@ -2363,4 +2331,2191 @@ mod tests {
 
         assert_eq!(sequence, restored_sequence);
     }
+
+    #[test]
+    fn citest_test_unglossed_worklist() {
+        let g1 = Uuid::new_v4();
+        let g2 = Uuid::new_v4();
+        let word = |w: &str, gloss_uuid: Option<Uuid>| Word {
+            uuid: Uuid::new_v4(),
+            word: String::from(w),
+            word_type: WordType::Word,
+            gloss_uuid,
+        };
+        let texts = vec![
+            Text {
+                text_name: String::from("Text 1"),
+                words: vec![
+                    word("λόγος", Some(g1)),
+                    word("ἔχει", None),
+                    Word {
+                        uuid: Uuid::new_v4(),
+                        word: String::from("."),
+                        word_type: WordType::Punctuation,
+                        gloss_uuid: None,
+                    },
+                    word("Λόγος", None),
+                ],
+                appcrits: None,
+            },
+            Text {
+                text_name: String::from("Text 2"),
+                words: vec![
+                    word("ἔχει", Some(g2)),
+                    word("ἔχει", None),
+                    word("καί", None),
+                    word("ἔχει", None),
+                ],
+                appcrits: None,
+            },
+        ];
+        let seq = Sequence {
+            sequence_description: SequenceDescription::default(),
+            glosses: vec![Glosses {
+                gloss_name: String::from("glosses"),
+                gloss: vec![
+                    Gloss {
+                        uuid: g1,
+                        lemma: String::from("λόγος"),
+                        status: 1,
+                        ..Default::default()
+                    },
+                    Gloss {
+                        uuid: g2,
+                        lemma: String::from("ἔχω"),
+                        status: 1,
+                        ..Default::default()
+                    },
+                ],
+            }],
+            texts,
+        };
+
+        let worklist = seq.unglossed_worklist(worklist::WorklistSort::Frequency, 3);
+        let forms: Vec<&str> = worklist.forms.iter().map(|f| f.form.as_str()).collect();
+        assert_eq!(forms, vec!["ἔχει", "Λόγος", "καί"]);
+
+        assert_eq!(worklist.forms[0].count, 3);
+        assert_eq!(worklist.forms[0].positions[0].text_index, 0);
+        assert_eq!(worklist.forms[0].positions[0].word_index, 1);
+        assert_eq!(worklist.forms[0].positions[2].word_index, 3);
+        assert_eq!(worklist.forms[0].candidates[0].gloss_uuid, g2);
+        //only differs in case from a glossed form
+        assert_eq!(worklist.forms[1].candidates[0].gloss_uuid, g1);
+        assert!(worklist.forms[2].candidates.is_empty());
+
+        assert_eq!(worklist.texts[0].words, 3);
+        assert_eq!(worklist.texts[0].glossed, 1);
+        assert_eq!(worklist.texts[1].percent_glossed(), 25.0);
+    }
+
+    #[test]
+    fn citest_test_recommend_arrows() {
+        let frequent = Uuid::new_v4();
+        let rare = Uuid::new_v4();
+        let article = Uuid::new_v4();
+        let gloss = |uuid: Uuid, lemma: &str, pos: &str| Gloss {
+            uuid,
+            lemma: String::from(lemma),
+            sort_key: String::from(lemma),
+            pos: String::from(pos),
+            status: 1,
+            ..Default::default()
+        };
+        let word = |w: &str, gloss_uuid: Uuid| Word {
+            uuid: Uuid::new_v4(),
+            word: String::from(w),
+            word_type: WordType::Word,
+            gloss_uuid: Some(gloss_uuid),
+        };
+        let words = vec![
+            word("ὁ", article),
+            word("ἔπεμψε", frequent),
+            word("ὁ", article),
+            word("σπάνιον", rare),
+            word("ἔπεμψε", frequent),
+            word("ὁ", article),
+        ];
+        let first_frequent = words[1].uuid;
+        let rare_word = words[3].uuid;
+
+        let mut seq = Sequence {
+            sequence_description: SequenceDescription {
+                name: String::from("test"),
+                start_page: 1,
+                gloss_names: vec![String::from("glosses.xml")],
+                texts: vec![TextDescription {
+                    display: true,
+                    text: String::from("text.xml"),
+                    ..Default::default()
+                }],
+                arrowed_words: vec![GlossArrow {
+                    gloss_uuid: rare,
+                    word_uuid: rare_word,
+                }],
+            },
+            glosses: vec![Glosses {
+                gloss_name: String::from("glosses"),
+                gloss: vec![
+                    gloss(frequent, "πέμπω", "verb"),
+                    gloss(rare, "σπάνιος", "adj."),
+                    gloss(article, "ὁ, ἡ, τό", "article"),
+                ],
+            }],
+            texts: vec![Text {
+                text_name: String::from("Text 1"),
+                words,
+                appcrits: None,
+            }],
+        };
+
+        let rules = arrows::ArrowRules::default();
+        assert!(rules.is_function_word("prep. + gen."));
+        assert!(rules.is_function_word("Particle"));
+        assert!(!rules.is_function_word("participle"));
+        assert!(!rules.is_function_word("part. of εἰμί"));
+        let review = {
+            let gloss_occurrances = seq.process().unwrap();
+            seq.recommend_arrows(&gloss_occurrances, &rules)
+        };
+        assert_eq!(review.recommendations.len(), 1);
+        assert_eq!(review.recommendations[0].gloss_uuid, frequent);
+        assert_eq!(review.recommendations[0].word_uuid, first_frequent);
+        assert_eq!(review.recommendations[0].total_count, 2);
+
+        assert_eq!(review.violations.len(), 1);
+        assert_eq!(review.violations[0].gloss_uuid, rare);
+        assert_eq!(
+            review.violations[0].issues,
+            vec![arrows::ArrowIssue::Infrequent {
+                total_count: 1,
+                min_count: 2
+            }]
+        );
+
+        assert!(
+            seq.apply_arrow_recommendations(&review.recommendations)
+                .is_ok()
+        );
+        let gloss_occurrances = seq.process().unwrap();
+        assert_eq!(gloss_occurrances[0][1].arrowed_state, ArrowedState::Arrowed);
+        assert_eq!(
+            gloss_occurrances[0][4].arrowed_state,
+            ArrowedState::Invisible
+        );
+    }
+
+    #[test]
+    fn citest_test_process_range() {
+        let g1 = Uuid::new_v4();
+        let g2 = Uuid::new_v4();
+        let word = |w: &str, gloss_uuid: Option<Uuid>| Word {
+            uuid: Uuid::new_v4(),
+            word: String::from(w),
+            word_type: WordType::Word,
+            gloss_uuid,
+        };
+        let text1 = vec![
+            word("λόγος", Some(g1)),
+            word("ἔχει", Some(g2)),
+            word("λόγον", Some(g1)),
+        ];
+        let text2 = vec![
+            word("ἔχει", Some(g2)),
+            word("λόγου", Some(g1)),
+            word("καί", None),
+            word("ἔχει", Some(g2)),
+        ];
+        let arrowed_words = vec![
+            GlossArrow {
+                gloss_uuid: g1,
+                word_uuid: text1[2].uuid,
+            },
+            GlossArrow {
+                gloss_uuid: g2,
+                word_uuid: text2[0].uuid,
+            },
+        ];
+        let seq = Sequence {
+            sequence_description: SequenceDescription {
+                arrowed_words,
+                ..Default::default()
+            },
+            glosses: vec![Glosses {
+                gloss_name: String::from("glosses"),
+                gloss: vec![
+                    Gloss {
+                        uuid: g1,
+                        lemma: String::from("λόγος"),
+                        status: 1,
+                        ..Default::default()
+                    },
+                    Gloss {
+                        uuid: g2,
+                        lemma: String::from("ἔχω"),
+                        status: 1,
+                        ..Default::default()
+                    },
+                ],
+            }],
+            texts: vec![
+                Text {
+                    text_name: String::from("Text 1"),
+                    words: text1,
+                    appcrits: None,
+                },
+                Text {
+                    text_name: String::from("Text 2"),
+                    words: text2,
+                    appcrits: None,
+                },
+            ],
+        };
+
+        let all = seq.process().unwrap();
+        let summary = seq.make_process_summary().unwrap();
+        for (text_index, text) in all.iter().enumerate() {
+            for start in 0..=text.len() {
+                for end in start..=text.len() {
+                    let r = seq.process_range(&summary, text_index, start..end).unwrap();
+                    assert_eq!(r, text[start..end].to_vec());
+                }
+            }
+        }
+        assert_eq!(all[1][1].arrowed_state, ArrowedState::Invisible);
+        assert_eq!(all[1][0].arrowed_state, ArrowedState::Arrowed);
+
+        assert!(seq.process_range(&summary, 2, 0..0).is_err());
+        assert!(seq.process_range(&summary, 1, 2..5).is_err());
+    }
+
+    #[test]
+    fn citest_test_paginate() {
+        let mut glosses = vec![];
+        let mut words = vec![];
+        for (i, w) in ["ἄγω", "βλάπτω", "γαμέω", "δίδωμι", "ἔχω"]
+            .iter()
+            .enumerate()
+        {
+            let gloss_uuid = Uuid::new_v4();
+            glosses.push(Gloss {
+                uuid: gloss_uuid,
+                lemma: w.to_string(),
+                def: format!("def {}", i),
+                status: 1,
+                ..Default::default()
+            });
+            words.push(Word {
+                uuid: Uuid::new_v4(),
+                word: w.to_string(),
+                word_type: WordType::Word,
+                gloss_uuid: Some(gloss_uuid),
+            });
+        }
+        let mut seq = Sequence {
+            sequence_description: SequenceDescription {
+                texts: vec![TextDescription {
+                    display: true,
+                    text: String::from("text.xml"),
+                    ..Default::default()
+                }],
+                ..Default::default()
+            },
+            glosses: vec![Glosses {
+                gloss_name: String::from("glosses"),
+                gloss: glosses,
+            }],
+            texts: vec![Text {
+                text_name: String::from("Text 1"),
+                words,
+                appcrits: None,
+            }],
+        };
+
+        //the whole text fits on one line and each gloss takes one line:
+        //the body is three lines high
+        let geometry = lines::PageGeometry {
+            paper_height: 3.0,
+            margin_top: 0.0,
+            margin_bottom: 0.0,
+            text_width: 10000.0,
+            lemma_width: 10000.0,
+            def_width: 10000.0,
+            text_line_height: 1.0,
+            gloss_line_height: 1.0,
+            gloss_entry_gap: 0.0,
+            ..Default::default()
+        };
+        let report =
+            lines::paginate(&mut seq, geometry.clone(), &breaks::BreakRules::default()).unwrap();
+        assert_eq!(report.texts[0].words_per_page, vec![2, 2, 1]);
+        assert!(report.texts[0].overflows.is_empty());
+        assert_eq!(seq.sequence_description.texts[0].words_per_page, "2,2,1");
+
+        //the gap above each gloss entry counts too
+        let report = lines::paginate(
+            &mut seq,
+            lines::PageGeometry {
+                paper_height: 5.0,
+                gloss_entry_gap: 0.5,
+                ..geometry.clone()
+            },
+            &breaks::BreakRules::default(),
+        )
+        .unwrap();
+        assert_eq!(report.texts[0].words_per_page, vec![2, 2, 1]);
+
+        let geometry = lines::PageGeometry {
+            paper_height: 1.0,
+            ..geometry
+        };
+        let report = lines::paginate(&mut seq, geometry, &breaks::BreakRules::default()).unwrap();
+        assert_eq!(report.texts[0].words_per_page, vec![1, 1, 1, 1, 1]);
+        assert_eq!(report.texts[0].overflows.len(), 5);
+        assert_eq!(report.texts[0].overflows[0].height, 2.0);
+        assert_eq!(report.texts[0].overflows[0].capacity, 1.0);
+
+        //the presets follow each exporter's page layout
+        let latex = lines::PageGeometry::latex();
+        assert_eq!(latex.body_height(), 9.0 * 72.0);
+        assert_eq!(latex.body_width(), latex.text_width);
+        assert!(lines::PageGeometry::typst().text_width < lines::PageGeometry::fodt().text_width);
+        assert!(lines::PageGeometry::fodt().body_height() > latex.body_height());
+    }
+
+    #[test]
+    fn citest_test_text_measurer() {
+        let measurer = lines::TextMeasurer::new();
+        let s = "πλήττω, -πλήξω, -ἐπλήξα";
+        let regular = measurer.width(s, lines::FontStyle::Regular, 12.0);
+        assert!(regular > 0.0);
+        //cached widths are reused and scale with the font size
+        assert_eq!(measurer.width(s, lines::FontStyle::Regular, 12.0), regular);
+        assert!((measurer.width(s, lines::FontStyle::Regular, 24.0) - regular * 2.0).abs() < 0.001);
+        assert!((lines::get_width_at(s, 12.0) - regular).abs() < 0.001);
+        assert!(measurer.width(s, lines::FontStyle::Bold, 12.0) > 0.0);
+        assert_eq!(measurer.width("", lines::FontStyle::Regular, 12.0), 0.0);
+
+        //a caller-supplied font without a bold face measures bold text with the regular face
+        let bold_font = include_bytes!("../IFAOGrecBold.ttf") as &[u8];
+        let custom = lines::TextMeasurer::with_fonts(bold_font, None).unwrap();
+        assert_eq!(
+            custom.width(s, lines::FontStyle::Regular, 12.0),
+            measurer.width(s, lines::FontStyle::Bold, 12.0)
+        );
+        assert_eq!(
+            custom.width(s, lines::FontStyle::Bold, 12.0),
+            custom.width(s, lines::FontStyle::Regular, 12.0)
+        );
+        assert!(lines::TextMeasurer::with_fonts(&[0, 1, 2], None).is_err());
+    }
+
+    #[test]
+    fn citest_test_layout_lines() {
+        let make_seq = |types: &[WordType]| Sequence {
+            sequence_description: SequenceDescription {
+                texts: vec![TextDescription {
+                    display: true,
+                    text: String::from("text.xml"),
+                    ..Default::default()
+                }],
+                ..Default::default()
+            },
+            glosses: vec![Glosses {
+                gloss_name: String::from("glosses"),
+                gloss: vec![],
+            }],
+            texts: vec![Text {
+                text_name: String::from("Text 1"),
+                words: types
+                    .iter()
+                    .map(|t| Word {
+                        uuid: Uuid::new_v4(),
+                        word: String::from("λόγος"),
+                        word_type: *t,
+                        gloss_uuid: None,
+                    })
+                    .collect(),
+                appcrits: None,
+            }],
+        };
+        //two lines per page and lines wide enough for any prose
+        let geometry = lines::PageGeometry {
+            paper_height: 2.0,
+            margin_top: 0.0,
+            margin_bottom: 0.0,
+            text_width: 10000.0,
+            verse_width: 10000.0,
+            text_line_height: 1.0,
+            ..Default::default()
+        };
+        let pages = |types: &[WordType]| {
+            let mut seq = make_seq(types);
+            lines::paginate(&mut seq, geometry.clone(), &breaks::BreakRules::default())
+                .unwrap()
+                .texts[0]
+                .words_per_page
+                .clone()
+        };
+
+        //each verse line starts a new line
+        assert_eq!(
+            pages(&[
+                WordType::VerseLine,
+                WordType::Word,
+                WordType::Word,
+                WordType::VerseLine,
+                WordType::Word,
+                WordType::VerseLine,
+                WordType::Word,
+            ]),
+            vec![5, 2]
+        );
+        //titles take a line of their own and are not left at the bottom of a page
+        assert_eq!(
+            pages(&[
+                WordType::Word,
+                WordType::Word,
+                WordType::SectionTitle,
+                WordType::Word,
+            ]),
+            vec![2, 2]
+        );
+        //paragraphs break the line; section numbers take no room
+        assert_eq!(
+            pages(&[
+                WordType::Word,
+                WordType::Section,
+                WordType::Word,
+                WordType::ParaNoIndent,
+                WordType::Word,
+                WordType::ParaWithIndent,
+                WordType::Word,
+            ]),
+            vec![6, 1]
+        );
+
+        //a narrow column wraps prose greedily
+        let word = lines::get_width_at("λόγος", geometry.font_size);
+        let space = lines::get_width_at(" ", geometry.font_size);
+        let geometry = lines::PageGeometry {
+            text_width: word * 2.0 + space,
+            ..geometry
+        };
+        let mut seq = make_seq(&[WordType::Word; 5]);
+        let report = lines::paginate(&mut seq, geometry, &breaks::BreakRules::default()).unwrap();
+        assert_eq!(report.texts[0].words_per_page, vec![4, 1]);
+    }
+
+    #[test]
+    fn citest_test_page_breaks() {
+        let words: Vec<Word> = [
+            (WordType::Word, "ἦλθεν"),
+            (WordType::Punctuation, "."),
+            (WordType::SectionTitle, "Β"),
+            (WordType::ParaNoIndent, ""),
+            (WordType::Word, "καὶ"),
+            (WordType::Word, "εἶπεν"),
+            (WordType::Speaker, "ΧΟΡΟΣ"),
+            (WordType::VerseLine, "[line]1"),
+            (WordType::Word, "ὦ"),
+            (WordType::Word, "φίλοι"),
+            (WordType::VerseLine, "[line]2"),
+            (WordType::Word, "ἴτε"),
+        ]
+        .iter()
+        .map(|(t, w)| Word {
+            uuid: Uuid::new_v4(),
+            word: w.to_string(),
+            word_type: *t,
+            gloss_uuid: None,
+        })
+        .collect();
+        let rules = breaks::BreakRules::default();
+
+        assert!(breaks::is_legal_break(&words, 2, &rules));
+        //a section title followed only by a paragraph marker is orphaned
+        assert_eq!(
+            breaks::break_issues(&words, 4, &rules),
+            vec![breaks::BreakIssue::OrphanedHeading {
+                word_type: WordType::SectionTitle
+            }]
+        );
+        assert!(breaks::is_legal_break(&words, 5, &rules));
+        assert!(!breaks::is_legal_break(&words, 7, &rules));
+        assert!(breaks::is_legal_break(&words, 10, &rules));
+        assert_eq!(
+            breaks::break_issues(&words, 9, &rules),
+            vec![breaks::BreakIssue::SplitsVerseLine]
+        );
+
+        //breaking prose only at the end of a sentence
+        let sentences = breaks::BreakRules {
+            sentence_breaks_in_prose: true,
+        };
+        assert!(breaks::is_legal_break(&words, 2, &sentences));
+        assert_eq!(
+            breaks::break_issues(&words, 5, &sentences),
+            vec![breaks::BreakIssue::MidSentence]
+        );
+        assert!(breaks::is_legal_break(&words, 10, &sentences));
+
+        //the last count is ignored and the suggestion keeps both pages non-empty
+        let violations = breaks::check_page_breaks(&words, &[4, 5, 100], &rules);
+        assert_eq!(violations.len(), 2);
+        assert_eq!(violations[0].page_index, 0);
+        assert_eq!(violations[0].break_index, 4);
+        assert_eq!(violations[0].suggested_break, Some(5));
+        assert_eq!(violations[1].break_index, 9);
+        assert_eq!(violations[1].suggested_break, Some(10));
+        assert!(breaks::check_page_breaks(&words, &[2, 8, 2], &rules).is_empty());
+        assert_eq!(breaks::nearest_legal_break(&words, 9, 8, 10, &rules), None);
+
+        //pagination ends a page at the last legal break rather than after the speaker
+        let mut seq = Sequence {
+            sequence_description: SequenceDescription {
+                texts: vec![TextDescription {
+                    display: true,
+                    text: String::from("text.xml"),
+                    words_per_page: String::from("9,3"),
+                    ..Default::default()
+                }],
+                ..Default::default()
+            },
+            glosses: vec![Glosses {
+                gloss_name: String::from("glosses"),
+                gloss: vec![],
+            }],
+            texts: vec![Text {
+                text_name: String::from("Text 1"),
+                words,
+                appcrits: None,
+            }],
+        };
+        let reports = seq.check_page_breaks(&rules).unwrap();
+        assert_eq!(reports[0].violations[0].suggested_break, Some(10));
+
+        let geometry = lines::PageGeometry {
+            paper_height: 4.0,
+            margin_top: 0.0,
+            margin_bottom: 0.0,
+            text_width: 10000.0,
+            verse_width: 10000.0,
+            text_line_height: 1.0,
+            ..Default::default()
+        };
+        let report = lines::paginate(&mut seq, geometry, &rules).unwrap();
+        assert_eq!(report.texts[0].words_per_page, vec![6, 6]);
+        assert!(
+            seq.check_page_breaks(&rules).unwrap()[0]
+                .violations
+                .is_empty()
+        );
+    }
+
+    #[test]
+    fn citest_test_page_map() {
+        let make_words = |words: &[&str]| -> Vec<Word> {
+            words
+                .iter()
+                .map(|w| Word {
+                    uuid: Uuid::new_v4(),
+                    word: w.to_string(),
+                    word_type: WordType::Word,
+                    gloss_uuid: None,
+                })
+                .collect()
+        };
+        let make_text = |name: &str, words: Vec<Word>| Text {
+            text_name: String::from(name),
+            words,
+            appcrits: None,
+        };
+        let make_desc = |words_per_page: &str, display: bool| TextDescription {
+            display,
+            text: String::from("text.xml"),
+            words_per_page: String::from(words_per_page),
+            ..Default::default()
+        };
+        let seq = Sequence {
+            sequence_description: SequenceDescription {
+                start_page: 2,
+                texts: vec![
+                    make_desc("2,10,1", true),
+                    make_desc("1", false),
+                    make_desc("1,1", true),
+                ],
+                ..Default::default()
+            },
+            glosses: vec![Glosses {
+                gloss_name: String::from("glosses"),
+                gloss: vec![],
+            }],
+            texts: vec![
+                make_text("Text 1", make_words(&["α", "β", "γ", "δ", "ε"])),
+                make_text("Text 2", make_words(&["ζ"])),
+                make_text("Text 3", make_words(&["η", "θ"])),
+            ],
+        };
+
+        let map = seq.make_page_map();
+        let numbers: Vec<usize> = map.pages.iter().map(|p| p.page_number).collect();
+        assert_eq!(numbers, (2..=10).collect::<Vec<usize>>());
+        let blank: Vec<bool> = map
+            .pages
+            .iter()
+            .map(|p| p.content == pagemap::PageContent::Blank)
+            .collect();
+        //a blank page before the first odd page, then the first text's two pages, both cut to
+        //the text's length. the next text starts on page 7 after two blank pages
+        assert_eq!(
+            blank,
+            vec![true, false, false, true, true, false, false, true, true]
+        );
+        let pagemap::PageContent::Text(second) = &map.page(4).unwrap().content else {
+            panic!("page 4 should have text");
+        };
+        assert_eq!(second.text_index, 0);
+        assert_eq!(second.page_in_text, 1);
+        assert_eq!(second.words, 2..5);
+        assert_eq!(second.first_word, seq.texts[0].words[2].uuid);
+        assert_eq!(second.last_word, seq.texts[0].words[4].uuid);
+        assert_eq!(map.page_of_word(seq.texts[0].words[3].uuid), Some(4));
+        assert_eq!(map.page_of_word(seq.texts[1].words[0].uuid), None);
+        assert_eq!(map.page_of_word(seq.texts[2].words[1].uuid), Some(8));
+        assert_eq!(
+            map.text_pages(2).map(|(n, _)| n).collect::<Vec<_>>(),
+            vec![7, 8]
+        );
+
+        let gloss_occurrances = seq.process().unwrap();
+        let options = GlossPageOptions {
+            filter_unique: true,
+            filter_invisible: true,
+            sort_key: true,
+            glossary: false,
+        };
+        let summary = seq.make_process_summary().unwrap();
+        let page = seq
+            .make_single_page(&summary, &ExportLatex::default(), &options, 4)
+            .unwrap();
+        assert!(page.contains("γ δ ε"));
+        assert!(page.contains("Text 1"));
+        assert_eq!(
+            seq.make_single_page(&summary, &ExportLatex::default(), &options, 2),
+            Ok(ExportLatex::default().blank_page())
+        );
+        assert_eq!(
+            seq.make_single_page(&summary, &ExportLatex::default(), &options, 11),
+            Err(GlosserError::NotFound(String::from(
+                "page 11 is not in the sequence"
+            )))
+        );
+        let doc = seq.make_document(&gloss_occurrances, &ExportLatex::default(), &options);
+        assert!(doc.contains("γ δ ε"));
+        assert!(!doc.contains("ζ"));
+        //a single page matches the same page of the whole document, and the page of a later text
+        //too, though only that text is processed for it
+        let document = seq.make_document_model(&gloss_occurrances, &options);
+        for page_number in [4, 8] {
+            let Some(model::PageModel::Text(page)) = document
+                .pages
+                .iter()
+                .find(|p| matches!(p, model::PageModel::Text(t) if t.page_number == page_number))
+            else {
+                panic!("page {} should have text", page_number);
+            };
+            assert_eq!(
+                seq.make_single_page(&summary, &ExportLatex::default(), &options, page_number),
+                Ok(render_page(page, &ExportLatex::default()))
+            );
+        }
+        //a summary which doesn't cover the page's text is an error, not an empty page
+        let mut first_text = seq.clone();
+        first_text.texts.truncate(1);
+        first_text.sequence_description.texts.truncate(1);
+        let first_summary = first_text.make_process_summary().unwrap();
+        assert!(
+            seq.make_single_page(&first_summary, &ExportLatex::default(), &options, 8)
+                .is_err()
+        );
+    }
+
+    #[test]
+    fn citest_test_validate_words_per_page() {
+        use pagecounts::PageCountIssue;
+        let desc = |words_per_page: &str| TextDescription {
+            display: true,
+            text: String::from("text.xml"),
+            words_per_page: String::from(words_per_page),
+            ..Default::default()
+        };
+
+        assert!(desc("3, 4,3").validate_page_counts(10).is_empty());
+        assert_eq!(
+            desc("").validate_page_counts(10),
+            vec![PageCountIssue::NoCounts]
+        );
+        assert_eq!(
+            desc("3,4").validate_page_counts(10),
+            vec![PageCountIssue::TooFewWords {
+                word_index: 7,
+                missing: 3
+            }]
+        );
+        //though the page map gives the last page the rest of the text
+        assert_eq!(pagecounts::page_ranges(&[3, 4], 10), vec![0..3, 3..10]);
+        assert_eq!(pagecounts::page_ranges(&[3, 9, 4], 10), vec![0..3, 3..10]);
+        assert_eq!(
+            desc("3,4,5,1").validate_page_counts(10),
+            vec![PageCountIssue::TooManyWords {
+                page_index: 2,
+                word_index: 10,
+                excess: 2
+            }]
+        );
+        //the last page is checked too
+        assert_eq!(
+            desc("3,8").validate_page_counts(10),
+            vec![PageCountIssue::TooManyWords {
+                page_index: 1,
+                word_index: 10,
+                excess: 1
+            }]
+        );
+        assert_eq!(
+            desc("3,x,0,7").validate_page_counts(10),
+            vec![
+                PageCountIssue::Unparsable {
+                    entry_index: 1,
+                    entry: String::from("x")
+                },
+                PageCountIssue::EmptyPage {
+                    page_index: 1,
+                    word_index: 3
+                },
+            ]
+        );
+        assert_eq!(desc("3,x,0,7").page_counts(), vec![3, 0, 7]);
+
+        let seq = Sequence {
+            sequence_description: SequenceDescription {
+                texts: vec![
+                    desc("1,1"),
+                    desc("2"),
+                    TextDescription {
+                        display: false,
+                        ..desc("9")
+                    },
+                ],
+                ..Default::default()
+            },
+            glosses: vec![],
+            texts: ["Text 1", "Text 2", "Text 3"]
+                .iter()
+                .map(|name| Text {
+                    text_name: name.to_string(),
+                    words: vec![Word::default(), Word::default()],
+                    appcrits: None,
+                })
+                .collect(),
+        };
+        let reports = seq.validate_words_per_page();
+        assert_eq!(reports.len(), 0);
+        let mut seq = seq;
+        seq.sequence_description.texts[1].words_per_page = String::from("2,1");
+        let reports = seq.validate_words_per_page();
+        assert_eq!(reports.len(), 1);
+        assert_eq!(reports[0].text_index, 1);
+        assert_eq!(reports[0].text_name, "Text 2");
+        assert_eq!(
+            reports[0].issues[0].to_string(),
+            "page 1 runs 1 word(s) past the end of the text at word 2"
+        );
+    }
+
+    #[test]
+    fn citest_test_document_model() {
+        let words: Vec<Word> = [
+            (WordType::WorkTitle, "Title"),
+            (WordType::ParaWithIndent, ""),
+            (WordType::Section, "[section]1.1"),
+            (WordType::Word, "λόγος"),
+            (WordType::Punctuation, ","),
+            (WordType::Section, "[section]1.2"),
+            (WordType::InlineSpeaker, "ΣΩ."),
+            (WordType::Word, "ἔφη"),
+            (WordType::Speaker, "ΧΟΡΟΣ"),
+            (WordType::VerseLine, "[line]4"),
+            (WordType::Word, "μῆνιν"),
+            (WordType::InlineVerseSpeaker, "ΑΓ."),
+            (WordType::VerseLine, "[line]5"),
+            (WordType::Word, "ἄειδε"),
+            (WordType::ParaNoIndent, ""),
+            (WordType::Word, "θεά"),
+        ]
+        .iter()
+        .map(|(t, w)| Word {
+            uuid: Uuid::new_v4(),
+            word: w.to_string(),
+            word_type: *t,
+            gloss_uuid: None,
+        })
+        .collect();
+        let seq = Sequence {
+            sequence_description: SequenceDescription {
+                name: String::from("Sequence"),
+                start_page: 1,
+                texts: vec![TextDescription {
+                    display: true,
+                    text: String::from("text.xml"),
+                    words_per_page: String::from("16"),
+                    ..Default::default()
+                }],
+                ..Default::default()
+            },
+            glosses: vec![Glosses {
+                gloss_name: String::from("glosses"),
+                gloss: vec![],
+            }],
+            texts: vec![Text {
+                text_name: String::from("Text 1"),
+                appcrits: Some(vec![AppCrit {
+                    word_uuid: words[3].uuid,
+                    entry: String::from("λόγος] λόγοι"),
+                    ..Default::default()
+                }]),
+                words,
+            }],
+        };
+
+        let gloss_occurrances = seq.process().unwrap();
+        let page_text = model::make_page_text(&gloss_occurrances[0], &seq.make_appcrit_hash());
+        assert_eq!(
+            page_text
+                .appcrits
+                .iter()
+                .map(|ap| ap.text.as_str())
+                .collect::<Vec<_>>(),
+            vec!["λόγος] λόγοι"]
+        );
+        assert_eq!(page_text.blocks.len(), 4);
+        assert_eq!(page_text.blocks[0], model::TextBlock::WorkTitle("Title"));
+        let model::TextBlock::Paragraph { start, inlines } = &page_text.blocks[1] else {
+            panic!("expected a paragraph");
+        };
+        assert_eq!(*start, model::ParagraphStart::Indented);
+        assert_eq!(
+            inlines[0],
+            model::Inline::Section {
+                number: String::from("1"),
+                major: true
+            }
+        );
+        //the word with the note is followed by its mark
+        assert_eq!(inlines[2], model::Inline::AppCritMark(1));
+        assert_eq!(
+            inlines[3],
+            model::Inline::Word {
+                word: &seq.texts[0].words[4],
+                space_before: false
+            }
+        );
+        assert_eq!(
+            inlines[4],
+            model::Inline::Section {
+                number: String::from("2"),
+                major: false
+            }
+        );
+        assert_eq!(inlines[5], model::Inline::Speaker("ΣΩ."));
+        assert_eq!(page_text.blocks[2], model::TextBlock::Speaker("ΧΟΡΟΣ"));
+        //once the page is in verse, paragraph markers don't end it
+        let model::TextBlock::Verse(lines) = &page_text.blocks[3] else {
+            panic!("expected verse");
+        };
+        assert_eq!(lines.len(), 2);
+        assert_eq!(lines[0].speaker, Some("ΑΓ."));
+        assert_eq!(lines[0].display_number(), "");
+        assert_eq!(lines[1].display_number(), "5");
+        assert_eq!(lines[1].inlines.len(), 2);
+
+        let options = GlossPageOptions {
+            filter_unique: true,
+            filter_invisible: true,
+            sort_key: true,
+            glossary: false,
+        };
+        let document = seq.make_document_model(&gloss_occurrances, &options);
+        assert_eq!(document.title, "Sequence");
+        assert_eq!(document.pages.len(), 2);
+        assert!(matches!(document.pages[0], model::PageModel::Text(_)));
+        for output in [
+            seq.make_document(&gloss_occurrances, &ExportLatex::default(), &options),
+            seq.make_document(&gloss_occurrances, &ExportTypst::default(), &options),
+            seq.make_document(&gloss_occurrances, &ExportHTML::default(), &options),
+            seq.make_document(&gloss_occurrances, &ExportFodt::default(), &options),
+        ] {
+            assert!(output.contains("μῆνιν"));
+            assert!(output.contains("λόγοι"));
+        }
+
+        //streaming writes the same document and passes on write errors
+        let mut streamed = vec![];
+        seq.write_document(
+            &gloss_occurrances,
+            &ExportFodt::default(),
+            &options,
+            &mut streamed,
+        )
+        .unwrap();
+        assert_eq!(
+            String::from_utf8(streamed).unwrap(),
+            seq.make_document(&gloss_occurrances, &ExportFodt::default(), &options)
+        );
+        let mut buf = [0u8; 16];
+        let result = seq.write_document(
+            &gloss_occurrances,
+            &ExportLatex::default(),
+            &options,
+            &mut &mut buf[..],
+        );
+        assert_eq!(result.unwrap_err().kind(), std::io::ErrorKind::WriteZero);
+        let mut streamed = vec![];
+        write_rendered_document(&document, &ExportTypst::default(), &mut streamed).unwrap();
+        assert_eq!(
+            String::from_utf8(streamed).unwrap(),
+            render_document(&document, &ExportTypst::default())
+        );
+    }
+
+    #[test]
+    fn citest_test_markup() {
+        use markup::{MarkupError, MarkupNode, MarkupTag};
+
+        assert_eq!(
+            markup::parse_markup("a <i>b <b>c</b></i> x<y").unwrap(),
+            vec![
+                MarkupNode::Text(String::from("a ")),
+                MarkupNode::Tagged {
+                    tag: MarkupTag::Italic,
+                    children: vec![
+                        MarkupNode::Text(String::from("b ")),
+                        MarkupNode::Tagged {
+                            tag: MarkupTag::Bold,
+                            children: vec![MarkupNode::Text(String::from("c"))],
+                        },
+                    ],
+                },
+                MarkupNode::Text(String::from(" x<y")),
+            ]
+        );
+        assert_eq!(
+            markup::lint_markup("<i>a <u>b</u> c</b>"),
+            vec![
+                MarkupError::UnknownTag {
+                    tag: String::from("u"),
+                    position: 5
+                },
+                MarkupError::UnknownTag {
+                    tag: String::from("u"),
+                    position: 9
+                },
+                MarkupError::UnexpectedClosingTag {
+                    tag: String::from("b"),
+                    position: 15
+                },
+                MarkupError::UnclosedTag {
+                    tag: MarkupTag::Italic,
+                    position: 0
+                },
+            ]
+        );
+        //an inner tag left open is closed with the outer one
+        assert_eq!(
+            markup::parse_markup("<b><i>a</b>"),
+            Err(MarkupError::UnclosedTag {
+                tag: MarkupTag::Italic,
+                position: 3
+            })
+        );
+        assert_eq!(
+            markup::parse_markup_lenient("<b><i>a</b>"),
+            vec![MarkupNode::Tagged {
+                tag: MarkupTag::Bold,
+                children: vec![MarkupNode::Tagged {
+                    tag: MarkupTag::Italic,
+                    children: vec![MarkupNode::Text(String::from("a"))],
+                }],
+            }]
+        );
+
+        let mut gloss = Gloss {
+            uuid: Uuid::new_v4(),
+            lemma: String::from("λόγος, -ου, ὁ"),
+            sort_key: String::from("λογος"),
+            def: String::from("<i>word</i>, a\\b <b>1<sup>st</sup></b> x<y"),
+            pos: String::from("noun"),
+            ..Default::default()
+        };
+        let word = Word {
+            uuid: Uuid::new_v4(),
+            word: String::from("λόγος"),
+            word_type: WordType::Word,
+            gloss_uuid: Some(gloss.uuid),
+        };
+        let occurrance = GlossOccurrance {
+            word: &word,
+            gloss: Some(&gloss),
+            running_count: Some(1),
+            total_count: Some(1),
+            arrowed_state: ArrowedState::Visible,
+        };
+        let lemma = Some("λόγος");
+        let latex = ExportLatex::default().gloss_entry(&occurrance, lemma);
+        assert!(
+            latex.contains(
+                "\\textit{word}, a\\textbackslash{}b \\textbf{1\\textsuperscript{st}} x<y"
+            )
+        );
+        let typst = ExportTypst::default().gloss_entry(&occurrance, lemma);
+        assert!(typst.contains("#fakeitalic[word], a\\\\b #strong[1#super[st]] x\\<y"));
+        let fodt = ExportFodt::default().gloss_entry(&occurrance, lemma);
+        assert!(fodt.contains(
+            r#"<text:span text:style-name="T2">word</text:span>, a\b <text:span text:style-name="T1">1<text:span text:style-name="T3">st</text:span></text:span> x&lt;y"#
+        ));
+        let html = ExportHTML::default().gloss_entry(&occurrance, lemma);
+        assert!(html.contains("<i>word</i>, a\\b <b>1<sup>st</sup></b> x&lt;y"));
+
+        gloss.def = String::from("<i>word");
+        let glosses = Glosses {
+            gloss_name: String::from("glosses"),
+            gloss: vec![gloss.clone()],
+        };
+        assert_eq!(
+            glosses.lint_markup(),
+            vec![markup::MarkupIssue {
+                gloss_uuid: gloss.uuid,
+                field: markup::MarkupField::Def,
+                error: MarkupError::UnclosedTag {
+                    tag: MarkupTag::Italic,
+                    position: 0
+                },
+            }]
+        );
+    }
+
+    //a short text with a section, a verse line and one arrowed gloss
+    fn make_glossed_sequence() -> Sequence {
+        let gloss = Gloss {
+            uuid: Uuid::new_v4(),
+            lemma: String::from("λόγος, -ου, ὁ"),
+            sort_key: String::from("λογος"),
+            def: String::from("<i>word</i>, speech"),
+            pos: String::from("noun"),
+            status: 1,
+            ..Default::default()
+        };
+        let words: Vec<Word> = [
+            (WordType::Section, "[section]1.1"),
+            (WordType::Word, "ὁ"),
+            (WordType::Word, "λόγος"),
+            (WordType::Punctuation, "."),
+            (WordType::VerseLine, "[line]5"),
+            (WordType::Word, "μῆνιν"),
+        ]
+        .iter()
+        .map(|(t, w)| Word {
+            uuid: Uuid::new_v4(),
+            word: w.to_string(),
+            word_type: *t,
+            gloss_uuid: if *w == "λόγος" {
+                Some(gloss.uuid)
+            } else {
+                None
+            },
+        })
+        .collect();
+        Sequence {
+            sequence_description: SequenceDescription {
+                name: String::from("Sequence"),
+                start_page: 1,
+                texts: vec![TextDescription {
+                    display: true,
+                    text: String::from("text.xml"),
+                    words_per_page: String::from("6"),
+                    ..Default::default()
+                }],
+                arrowed_words: vec![GlossArrow {
+                    gloss_uuid: gloss.uuid,
+                    word_uuid: words[2].uuid,
+                }],
+                ..Default::default()
+            },
+            glosses: vec![Glosses {
+                gloss_name: String::from("glosses"),
+                gloss: vec![gloss],
+            }],
+            texts: vec![Text {
+                text_name: String::from("Text 1"),
+                words,
+                appcrits: None,
+            }],
+        }
+    }
+
+    #[test]
+    fn citest_test_markdown_and_plain_text() {
+        let seq = make_glossed_sequence();
+        let gloss_occurrances = seq.process().unwrap();
+        let options = GlossPageOptions {
+            filter_unique: true,
+            filter_invisible: true,
+            sort_key: true,
+            glossary: false,
+        };
+
+        let markdown = seq.make_document(&gloss_occurrances, &ExportMarkdown {}, &options);
+        assert!(markdown.starts_with("# Sequence\n"));
+        assert!(markdown.contains("## Page 1\n"));
+        assert!(markdown.contains("**1** ὁ λόγος.\n"));
+        assert!(markdown.contains("|  | μῆνιν | 5 |\n"));
+        assert!(markdown.contains("- → **λόγος, -ου, ὁ** (noun) *word*, speech (1 of 1)\n"));
+        assert!(markdown.contains("## Index\n\n- λόγος, 1\n"));
+
+        let summary = seq.make_process_summary().unwrap();
+        let text = seq
+            .make_single_page(&summary, &ExportPlainText {}, &options, 1)
+            .unwrap();
+        assert_eq!(
+            text,
+            "\n=== Page 1 ===\n\n[1] ὁ λόγος.\n\nμῆνιν [5]\n\n--\n→ λόγος, -ου, ὁ (noun) word, speech (1 of 1)\n"
+        );
+    }
+
+    #[test]
+    fn citest_test_epub() {
+        let seq = make_glossed_sequence();
+        let gloss_occurrances = seq.process().unwrap();
+        let options = GlossPageOptions {
+            filter_unique: true,
+            filter_invisible: true,
+            sort_key: true,
+            glossary: false,
+        };
+        let epub = seq.make_epub(&gloss_occurrances, &options).unwrap();
+        exportepub::validate_epub(&epub).unwrap();
+
+        let mut archive = ZipArchive::new(Cursor::new(epub.clone())).unwrap();
+        assert_eq!(archive.by_index(0).unwrap().name(), "mimetype");
+        for name in [
+            "OEBPS/nav.xhtml",
+            "OEBPS/text-0.xhtml",
+            "OEBPS/page-1.xhtml",
+            "OEBPS/index.xhtml",
+            "OEBPS/fonts/IFAOGrec.ttf",
+            "OEBPS/fonts/IFAOGrecBold.ttf",
+        ] {
+            assert!(archive.by_name(name).is_ok(), "{} is missing", name);
+        }
+        let mut page = String::new();
+        archive
+            .by_name("OEBPS/page-1.xhtml")
+            .unwrap()
+            .read_to_string(&mut page)
+            .unwrap();
+        assert!(page.contains("μῆνιν"));
+        assert!(page.contains("<i>word</i>, speech"));
+        let mut index = String::new();
+        archive
+            .by_name("OEBPS/index.xhtml")
+            .unwrap()
+            .read_to_string(&mut index)
+            .unwrap();
+        assert!(index.contains("<li>λόγος, <a href=\"page-1.xhtml\">1</a></li>"));
+
+        //the identifier comes from the sequence, so each export is the same book
+        let identifier = |epub: Vec<u8>| {
+            let mut opf = String::new();
+            ZipArchive::new(Cursor::new(epub))
+                .unwrap()
+                .by_name("OEBPS/content.opf")
+                .unwrap()
+                .read_to_string(&mut opf)
+                .unwrap();
+            let start = opf.find("urn:uuid:").unwrap();
+            opf[start..start + 45].to_string()
+        };
+        assert_eq!(
+            identifier(epub.clone()),
+            identifier(seq.make_epub(&gloss_occurrances, &options).unwrap())
+        );
+        assert_eq!(
+            identifier(epub.clone()),
+            format!(
+                "urn:uuid:{}",
+                Uuid::new_v5(&Uuid::NAMESPACE_OID, "Sequence".as_bytes())
+            )
+        );
+
+        //a package whose mimetype isn't first fails
+        let mut zip = ZipWriter::new(Cursor::new(Vec::new()));
+        let file_options = SimpleFileOptions::default();
+        for i in 1..archive.len() {
+            let mut file = archive.by_index(i).unwrap();
+            let mut content = vec![];
+            file.read_to_end(&mut content).unwrap();
+            zip.start_file(file.name(), file_options).unwrap();
+            zip.write_all(&content).unwrap();
+        }
+        let broken = zip.finish().unwrap().into_inner();
+        assert!(exportepub::validate_epub(&broken).is_err());
+    }
+
+    #[test]
+    fn citest_test_odt() {
+        let seq = make_glossed_sequence();
+        let gloss_occurrances = seq.process().unwrap();
+        let options = GlossPageOptions {
+            filter_unique: true,
+            filter_invisible: true,
+            sort_key: true,
+            glossary: false,
+        };
+        let odt = seq
+            .make_odt(&gloss_occurrances, &ExportFodt::default(), &options)
+            .unwrap();
+        let mut archive = ZipArchive::new(Cursor::new(odt)).unwrap();
+        {
+            let mut mimetype = archive.by_index(0).unwrap();
+            assert_eq!(mimetype.name(), "mimetype");
+            assert_eq!(mimetype.compression(), zip::CompressionMethod::Stored);
+            let mut content = String::new();
+            mimetype.read_to_string(&mut content).unwrap();
+            assert_eq!(content, "application/vnd.oasis.opendocument.text");
+        }
+
+        let mut read = |name: &str| {
+            let mut content = String::new();
+            archive
+                .by_name(name)
+                .unwrap()
+                .read_to_string(&mut content)
+                .unwrap();
+            //every part is well formed
+            let mut reader = Reader::from_str(&content);
+            reader.config_mut().check_end_names = true;
+            loop {
+                match reader.read_event() {
+                    Ok(Event::Eof) => break,
+                    Ok(_) => (),
+                    Err(e) => panic!("{} is not well formed: {}", name, e),
+                }
+            }
+            content
+        };
+        let content = read("content.xml");
+        assert!(content.contains("<office:document-content "));
+        assert!(!content.contains("office:mimetype"));
+        assert!(content.contains("μῆνιν"));
+        assert!(content.contains("<office:automatic-styles>"));
+        assert!(content.contains("xlink:href=\"Fonts/IFAOGrec.ttf\""));
+        let styles = read("styles.xml");
+        assert!(styles.contains("<office:master-styles>"));
+        assert!(styles.contains("style:name=\"GlossTableLemma\""));
+        assert!(!styles.contains("<office:body>"));
+        //each automatic style is in one file: the page layout and header and footer styles with
+        //the master pages, the rest with the body
+        assert!(styles.contains("<style:page-layout style:name=\"pm1\">"));
+        assert!(styles.contains("style:name=\"HeaderLeft\""));
+        assert!(!styles.contains("style:name=\"GlossTable\""));
+        assert!(content.contains("style:name=\"GlossTable\""));
+        assert!(!content.contains("style:name=\"pm1\""));
+        assert!(!content.contains("style:name=\"HeaderLeft\""));
+        read("meta.xml");
+        assert!(read("settings.xml").contains(
+            "<config:config-item config:name=\"EmbedFonts\" config:type=\"boolean\">true</config:config-item>"
+        ));
+        let manifest = read("META-INF/manifest.xml");
+        for path in [
+            "content.xml",
+            "styles.xml",
+            "Fonts/IFAOGrec.ttf",
+            "Fonts/IFAOGrecBold.ttf",
+        ] {
+            assert!(manifest.contains(&format!("manifest:full-path=\"{}\"", path)));
+            assert!(archive.by_name(path).is_ok());
+        }
+
+        //the configured main font's face gets its font file, read from disk
+        let export = ExportFodt {
+            config: ExportConfig {
+                font_families: vec![String::from("Gentium Plus")],
+                font_file: Some(String::from("IFAOGrecBold.ttf")),
+                ..Default::default()
+            },
+        };
+        let odt = seq.make_odt(&gloss_occurrances, &export, &options).unwrap();
+        let mut archive = ZipArchive::new(Cursor::new(odt)).unwrap();
+        let mut content = String::new();
+        archive
+            .by_name("content.xml")
+            .unwrap()
+            .read_to_string(&mut content)
+            .unwrap();
+        assert!(content.contains(
+            "<style:font-face style:name=\"Gentium Plus\" svg:font-family=\"&apos;Gentium Plus&apos;\"><svg:font-face-src><svg:font-face-uri xlink:href=\"Fonts/IFAOGrecBold.ttf\""
+        ));
+        assert!(archive.by_name("Fonts/IFAOGrecBold.ttf").is_ok());
+        assert!(archive.by_name("Fonts/IFAOGrec.ttf").is_err());
+
+        //without a font file nothing is embedded
+        let export = ExportFodt {
+            config: ExportConfig {
+                font_file: None,
+                ..Default::default()
+            },
+        };
+        let odt = seq.make_odt(&gloss_occurrances, &export, &options).unwrap();
+        let mut archive = ZipArchive::new(Cursor::new(odt)).unwrap();
+        assert!(archive.by_name("Fonts/IFAOGrec.ttf").is_err());
+        let mut content = String::new();
+        archive
+            .by_name("content.xml")
+            .unwrap()
+            .read_to_string(&mut content)
+            .unwrap();
+        assert!(!content.contains("svg:font-face-src"));
+    }
+
+    #[test]
+    fn citest_test_tei() {
+        let mut seq = make_glossed_sequence();
+        let words = &mut seq.texts[0].words;
+        words.insert(
+            0,
+            Word {
+                uuid: Uuid::new_v4(),
+                word: String::from("ΣΩΚΡΑΤΗΣ"),
+                word_type: WordType::Speaker,
+                gloss_uuid: None,
+            },
+        );
+        seq.texts[0].appcrits = Some(vec![AppCrit {
+            word_uuid: seq.texts[0].words[6].uuid,
+            entry: String::from("μῆνιν] μῆνις"),
+            ..Default::default()
+        }]);
+        seq.sequence_description.texts[0].words_per_page = String::from("7");
+        let gloss_uuid = seq.glosses[0].gloss[0].uuid;
+        let arrowed = seq.texts[0].words[3].uuid;
+
+        let tei = seq.to_tei().unwrap();
+        let mut reader = Reader::from_str(&tei);
+        reader.config_mut().check_end_names = true;
+        loop {
+            match reader.read_event() {
+                Ok(Event::Eof) => break,
+                Ok(_) => (),
+                Err(e) => panic!("the TEI is not well formed: {}", e),
+            }
+        }
+        assert!(tei.contains("<TEI xmlns=\"http://www.tei-c.org/ns/1.0\">"));
+        assert!(tei.contains("<text xml:id=\"text-0\" n=\"Text 1\">"));
+        assert!(tei.contains("<pb n=\"1\"/>"));
+        assert!(tei.contains("<speaker>ΣΩΚΡΑΤΗΣ</speaker>"));
+        assert!(tei.contains("<milestone unit=\"section\" n=\"1.1\"/>"));
+        assert!(tei.contains(&format!(
+            "<w xml:id=\"w-{}\" lemmaRef=\"#gloss-{}\">λόγος</w>",
+            arrowed, gloss_uuid
+        )));
+        assert!(tei.contains(&format!(
+            "<pc xml:id=\"w-{}\">.</pc>",
+            seq.texts[0].words[4].uuid
+        )));
+        assert!(tei.contains("<l n=\"5\">"));
+        assert!(tei.contains("<note type=\"appcrit\">μῆνιν] μῆνις</note>"));
+        assert!(tei.contains(&format!("<entry xml:id=\"gloss-{}\"", gloss_uuid)));
+        assert!(tei.contains("<orth>λόγος, -ου, ὁ</orth>"));
+        assert!(tei.contains("<note type=\"count\">1</note>"));
+        assert!(tei.contains("<xr type=\"arrow\" n=\"1\">"));
+        assert!(tei.contains(&format!("<ptr target=\"#w-{}\"/>", arrowed)));
+
+        //a note with a reading and witnesses, over a span of words
+        seq.texts[0].appcrits = Some(vec![AppCrit {
+            word_uuid: seq.texts[0].words[2].uuid,
+            end_word_uuid: Some(seq.texts[0].words[3].uuid),
+            lemma: String::from("ὁ λόγος"),
+            reading: String::from("λόγος"),
+            witnesses: vec![String::from("A"), String::from("Vat. gr. 1")],
+            entry: String::from(""),
+        }]);
+        //a hidden text: its glosses and arrows stay out of the glossary
+        let new_gloss = |lemma: &str| Gloss {
+            uuid: Uuid::new_v4(),
+            lemma: lemma.to_string(),
+            sort_key: lemma.to_string(),
+            def: String::from("def"),
+            pos: String::from("noun"),
+            status: 1,
+            ..Default::default()
+        };
+        let shown = new_gloss("ἀνήρ");
+        let hidden = new_gloss("θεά");
+        let new_word = |word: &str, gloss: &Gloss| Word {
+            uuid: Uuid::new_v4(),
+            word: word.to_string(),
+            word_type: WordType::Word,
+            gloss_uuid: Some(gloss.uuid),
+        };
+        seq.texts[0].words.push(new_word("ἄνδρα", &shown));
+        let hidden_words = vec![new_word("ἄνδρα", &shown), new_word("θεά", &hidden)];
+        seq.sequence_description.arrowed_words.push(GlossArrow {
+            gloss_uuid: shown.uuid,
+            word_uuid: hidden_words[0].uuid,
+        });
+        seq.sequence_description.texts.push(TextDescription {
+            display: false,
+            text: String::from("hidden.xml"),
+            words_per_page: String::from(""),
+            ..Default::default()
+        });
+        seq.texts.push(Text {
+            text_name: String::from("Hidden"),
+            words: hidden_words,
+            appcrits: None,
+        });
+        let (shown_uuid, hidden_uuid) = (shown.uuid, hidden.uuid);
+        seq.glosses[0].gloss.extend([shown, hidden]);
+
+        let tei = seq.to_tei().unwrap();
+        let mut reader = Reader::from_str(&tei);
+        reader.config_mut().check_end_names = true;
+        loop {
+            match reader.read_event() {
+                Ok(Event::Eof) => break,
+                Ok(_) => (),
+                Err(e) => panic!("the TEI is not well formed: {}", e),
+            }
+        }
+        let app = &tei[tei.find("<app>").unwrap()..tei.find("</app>").unwrap()];
+        assert!(app.contains(">ὁ</w>"));
+        assert!(app.contains(">λόγος</w>"));
+        assert!(app.contains("<rdg wit=\"#wit-A #wit-Vat__gr__1\">λόγος</rdg>"));
+        //the witnesses the readings point to are declared
+        assert!(tei.contains("<witness xml:id=\"wit-A\">A</witness>"));
+        assert!(tei.contains("<witness xml:id=\"wit-Vat__gr__1\">Vat. gr. 1</witness>"));
+        assert!(tei.find("<listWit>") < tei.find("</sourceDesc>"));
+        assert!(!app.contains("<note"));
+        assert!(!tei.contains("n=\"Hidden\""));
+        assert!(tei.contains(&format!("<entry xml:id=\"gloss-{}\"", shown_uuid)));
+        assert!(!tei.contains(&format!("gloss-{}", hidden_uuid)));
+        assert_eq!(tei.matches("<xr ").count(), 1);
+
+        //a text on its own refers to glosses by uuid
+        let text_tei = seq.texts[0].to_tei().unwrap();
+        assert!(text_tei.contains(&format!("lemmaRef=\"urn:uuid:{}\"", gloss_uuid)));
+        assert!(!text_tei.contains("<back>"));
+    }
+
+    #[test]
+    fn citest_test_json() {
+        let mut seq = make_glossed_sequence();
+        seq.sequence_description.gloss_names = vec![String::from("glosses.xml")];
+        seq.texts[0].appcrits = Some(vec![AppCrit {
+            word_uuid: seq.texts[0].words[2].uuid,
+            entry: String::from("2 λόγος] <i>om.</i> A"),
+            ..Default::default()
+        }]);
+
+        let json = seq.to_json().unwrap();
+        assert!(json.contains(r#""word_type":"Word""#));
+        assert_eq!(Sequence::from_json(&json).unwrap(), seq);
+
+        let binary = seq.to_binary().unwrap();
+        assert!(binary.len() < json.len());
+        assert_eq!(Sequence::from_binary(&binary).unwrap(), seq);
+
+        //the same sequence read back from its xml files
+        let zip = create_sequence_zip(&seq, "sequence.xml").unwrap();
+        let from_xml = from_sequence_zip(zip, "sequence.xml").unwrap();
+        assert_eq!(from_xml, seq);
+        assert_eq!(from_xml.to_json().unwrap(), json);
+
+        assert!(Sequence::from_json("{}").is_err());
+        assert!(Sequence::from_binary(&binary[..binary.len() / 2]).is_err());
+    }
+
+    #[test]
+    fn citest_test_export_config() {
+        use exportconfig::{Margins, Paper};
+
+        let seq = make_glossed_sequence();
+        let gloss_occurrances = seq.process().unwrap();
+        let options = GlossPageOptions {
+            filter_unique: true,
+            filter_invisible: true,
+            sort_key: true,
+            glossary: false,
+        };
+
+        //the defaults keep the output as it was
+        let latex = seq.make_document(&gloss_occurrances, &ExportLatex::default(), &options);
+        assert!(latex.starts_with("\\documentclass[twoside,openright,12pt,letterpaper]{book}"));
+        assert!(latex.contains("\\usepackage[twoside, margin=1.0in]{geometry}"));
+        assert!(latex.contains("\\newcommand{\\GlossLineSpacing}{1.5}"));
+        assert!(latex.contains("ItalicFont={IFAOGrec.ttf}"));
+        assert!(latex.contains("\\fancyhead[EL]{Sequence}"));
+        assert!(latex.contains("\\fancyhead[OR]{}\n\\begin{spacing}"));
+        let typst = seq.make_document(&gloss_occurrances, &ExportTypst::default(), &options);
+        assert!(typst.contains("#set page(width: 8.5in, height: 11in)"));
+        assert!(typst.contains("leading: 0.9em,"));
+        assert!(typst.contains("font: \"IFAO-Grec Unicode\",\n          size: 12pt,"));
+        let fodt = seq.make_document(&gloss_occurrances, &ExportFodt::default(), &options);
+        assert!(fodt.contains(r#"fo:page-width="8.5in" fo:page-height="11in""#));
+        assert!(fodt.contains(r#"fo:margin-left="0.7874in""#));
+        assert!(fodt.contains(r#"style:font-name="IFAO-Grec Unicode" fo:font-size="12pt""#));
+        assert!(fodt.contains(r#"fo:line-height="150%""#));
+        assert!(latex.contains(
+            r"\begin{tabular}{ m{0.2cm} L{\dimexpr0.5\textwidth\relax} D{\dimexpr0.5\textwidth-0.15in\relax} }"
+        ));
+        assert!(typst.contains("columns: (0.6cm, 8.0cm * 1, 9.0cm * 1),"));
+        assert!(fodt.contains(
+            r#"<style:table-properties style:width="7.1799in" fo:margin-left="-0.2597in""#
+        ));
+        assert!(fodt.contains(r#"<style:table-column-properties style:column-width="3.4236in"/>"#));
+        assert!(fodt.contains(r#"<style:table-properties style:width="6.925in""#));
+        //page titles are escaped in HTML like the rest of the text
+        let mut escaped = seq.clone();
+        escaped.texts[0].text_name = String::from("Text <1> & 2");
+        escaped.sequence_description.texts[0].words_per_page = String::from("3,3");
+        let html = escaped.make_document(
+            &escaped.process().unwrap(),
+            &ExportHTML::default(),
+            &options,
+        );
+        assert!(html.contains("<div class='PageTitle'>Text &lt;1&gt; &amp; 2 - Page 2</div>"));
+        //the course header, not the title, on even pages in Typst and FODT
+        assert!(typst.contains("#let evenheader = \"LGI - UPPER LEVEL GREEK\""));
+        assert!(
+            fodt.contains(
+                r#"<text:p text:style-name="HeaderLeft">LGI - UPPER LEVEL GREEK</text:p>"#
+            )
+        );
+
+        //the tables shrink with the text block
+        let config = ExportConfig {
+            paper: Paper::A5,
+            ..Default::default()
+        };
+        let a5_typst = seq.make_document(
+            &gloss_occurrances,
+            &ExportTypst {
+                config: config.clone(),
+            },
+            &options,
+        );
+        assert!(a5_typst.contains("columns: (0.6cm, 8.0cm * 0.6855, 9.0cm * 0.6855),"));
+        assert!(a5_typst.contains("#let placegloss = place.with(bottom, dx: -0.8cm * 0.6855)"));
+        let a5_fodt = seq.make_document(&gloss_occurrances, &ExportFodt { config }, &options);
+        assert!(
+            a5_fodt.contains(r#"<style:table-column-properties style:column-width="2.102in"/>"#)
+        );
+
+        let config = ExportConfig {
+            title: Some(String::from("Greek & Latin")),
+            subtitle: Some(String::from("Reader")),
+            even_header: Some(String::from("LGI - UPPER LEVEL GREEK")),
+            odd_header: Some(String::from("Odd \"Header\"")),
+            paper: Paper::A4,
+            margins: Some(Margins::all(54.0)),
+            font_size: 11.0,
+            line_spacing: 2.0,
+            font_families: vec![String::from("New Athena Unicode"), String::from("Gentium")],
+            font_file: None,
+            ..Default::default()
+        };
+
+        let latex = seq.make_document(
+            &gloss_occurrances,
+            &ExportLatex {
+                config: config.clone(),
+            },
+            &options,
+        );
+        assert!(latex.starts_with("\\documentclass[twoside,openright,11pt,a4paper]{book}"));
+        assert!(latex.contains(
+            "\\usepackage[twoside, top=0.75in, bottom=0.75in, inner=0.75in, outer=0.75in]{geometry}"
+        ));
+        assert!(latex.contains("\\newcommand{\\GlossLineSpacing}{2}"));
+        assert!(latex.contains("ItalicFont={New Athena Unicode}"));
+        assert!(latex.contains("\\fancyhead[EL]{LGI - UPPER LEVEL GREEK}"));
+        assert!(latex.contains("\\fancyhead[OR]{Odd \"Header\"}"));
+
+        let latex = seq.make_document(
+            &gloss_occurrances,
+            &ExportLatex {
+                config: ExportConfig {
+                    font_size: 14.0,
+                    paper: Paper::Custom {
+                        width: 432.0,
+                        height: 648.0,
+                    },
+                    ..Default::default()
+                },
+            },
+            &options,
+        );
+        assert!(latex.starts_with("\\documentclass[twoside,openright,12pt]{book}"));
+        assert!(latex.contains("margin=1.0in, paperwidth=6in, paperheight=9in]{geometry}"));
+        assert!(latex.contains("\\begin{document}\n\\fontsize{14pt}{16.8pt}\\selectfont\n"));
+
+        let typst = seq.make_document(
+            &gloss_occurrances,
+            &ExportTypst {
+                config: config.clone(),
+            },
+            &options,
+        );
+        assert!(typst.contains("#set document(title: \"Greek & Latin: Reader\")"));
+        assert!(typst.contains(
+            "#set page(width: 8.2677in, height: 11.6929in, margin: (top: 0.75in, bottom: 0.75in, inside: 0.75in, outside: 0.75in))"
+        ));
+        assert!(typst.contains("#let evenheader = \"LGI - UPPER LEVEL GREEK\""));
+        assert!(typst.contains("align(right, \"Odd \\\"Header\\\"\")"));
+        assert!(typst.contains("leading: 1.2em,"));
+        assert!(
+            typst.contains("font: (\"New Athena Unicode\", \"Gentium\"),\n          size: 11pt,")
+        );
+
+        let html = seq.make_document(
+            &gloss_occurrances,
+            &ExportHTML {
+                config: config.clone(),
+            },
+            &options,
+        );
+        assert!(html.contains("<title>Greek &amp; Latin: Reader</title>"));
+        assert!(
+            html.contains(
+                "@page { size: 8.2677in 11.6929in; margin: 0.75in 0.75in 0.75in 0.75in; }"
+            )
+        );
+        assert!(html.contains("font-family: \"New Athena Unicode\", \"Gentium\", WebIFAO,"));
+        assert!(html.contains("font-size: 11pt;"));
+        assert!(html.contains("line-height: 2;"));
+        assert!(!html.contains("Greek Vocab DB2"));
+
+        let fodt = seq.make_document(&gloss_occurrances, &ExportFodt { config }, &options);
+        assert!(fodt.contains("<dc:title>Greek &amp; Latin: Reader</dc:title>"));
+        assert!(fodt.contains(r#"fo:page-width="8.2677in" fo:page-height="11.6929in""#));
+        assert!(fodt.contains(r#"fo:margin-left="0.75in""#));
+        assert!(fodt.contains("LGI - UPPER LEVEL GREEK</text:p>"));
+        assert!(fodt.contains("Odd &quot;Header&quot;</text:p>"));
+        assert!(fodt.contains(r#"style:font-name="New Athena Unicode" fo:font-size="11pt""#));
+        assert!(fodt.contains(r#"fo:line-height="200%""#));
+        assert!(!fodt.contains("IFAO-Grec Unicode"));
+    }
+
+    #[test]
+    fn citest_test_templates() {
+        use exportfodt::FODT_TEMPLATE;
+        use exportlatex::LATEX_TEMPLATE;
+        use exporttypst::TYPST_TEMPLATE;
+
+        assert_eq!(ExportLatex::default().validate_template(), Ok(()));
+        assert_eq!(ExportTypst::default().validate_template(), Ok(()));
+        assert_eq!(ExportHTML::default().validate_template(), Ok(()));
+        assert_eq!(ExportFodt::default().validate_template(), Ok(()));
+
+        let seq = make_glossed_sequence();
+        let gloss_occurrances = seq.process().unwrap();
+        let options = GlossPageOptions {
+            filter_unique: true,
+            filter_invisible: true,
+            sort_key: true,
+            glossary: false,
+        };
+
+        let preamble = r"\documentclass{book}
+\usepackage{array,setspace}
+\usepackage[margin=2cm]{geometry}
+\usepackage{fontspec}
+\usepackage{fancyhdr}
+\newcommand{\GlossLineSpacing}{1.2}
+\newcommand{\marginsec}[1]{#1}
+\newcommand\marginseclight[1]{#1}
+\newcolumntype{L}{p}
+\newcolumntype{D}{p}
+\title{%MAIN_TITLE%: %SUBTITLE%}
+\date{%DATE%}
+%% %SEQUENCE_NAME%
+\begin{document}
+\setcounter{page}{%PAGE_NUM%}
+";
+        let export = ExportLatex {
+            config: ExportConfig {
+                title: Some(String::from("Herodotus & Thucydides")),
+                subtitle: Some(String::from("Book 1")),
+                template: Some(String::from(preamble)),
+                date: Some(String::from("2026-10-18")),
+                ..Default::default()
+            },
+        };
+        assert_eq!(export.validate_template(), Ok(()));
+        let latex = seq.make_document(&gloss_occurrances, &export, &options);
+        assert!(latex.starts_with(
+            "\\documentclass{book}\n\\usepackage{array,setspace}\n\\usepackage[margin=2cm]{geometry}\n"
+        ));
+        assert!(latex.contains(
+            "\\title{Herodotus \\& Thucydides: Book 1}\n\\date{2026-10-18}\n%% Sequence\n"
+        ));
+        assert!(latex.contains("\\setcounter{page}{1}\n"));
+        assert!(latex.contains("\\marginsec{1}"));
+
+        let export = ExportLatex {
+            config: ExportConfig {
+                template: Some(String::from("\\documentclass{book}\n\\begin{document}\n")),
+                ..Default::default()
+            },
+        };
+        assert_eq!(
+            export.validate_template(),
+            Err(GlosserError::InvalidInput(String::from(
+                r"template does not define: \marginsec, \marginseclight, \GlossLineSpacing, the L column type, the D column type, array, setspace, fancyhdr, fontspec"
+            )))
+        );
+        let export = ExportLatex {
+            config: ExportConfig {
+                template: Some(LATEX_TEMPLATE.replace("\\newcommand{\\marginseclight}", "")),
+                ..Default::default()
+            },
+        };
+        assert_eq!(
+            export.validate_template(),
+            Err(GlosserError::InvalidInput(String::from(
+                r"template does not define: \marginseclight"
+            )))
+        );
+
+        let export = ExportTypst {
+            config: ExportConfig {
+                template: Some(TYPST_TEMPLATE.replace("#let placegloss", "#let placeglosses")),
+                ..Default::default()
+            },
+        };
+        assert_eq!(
+            export.validate_template(),
+            Err(GlosserError::InvalidInput(String::from(
+                "template does not define: placegloss"
+            )))
+        );
+
+        let export = ExportHTML {
+            config: ExportConfig {
+                template: Some(String::from(
+                    "<html><head><title>%MAIN_TITLE% %DATE%</title></head>",
+                )),
+                ..Default::default()
+            },
+        };
+        assert!(export.validate_template().is_err());
+        //today's date when none is given
+        let html = seq.make_document(&gloss_occurrances, &export, &options);
+        let date = &html["<html><head><title>Sequence ".len()..][..10];
+        assert!(date.starts_with("20") && date.as_bytes()[4] == b'-' && date.as_bytes()[7] == b'-');
+
+        let export = ExportFodt {
+            config: ExportConfig {
+                template: Some(FODT_TEMPLATE.replace("style:name=\"PIndented\"", "")),
+                ..Default::default()
+            },
+        };
+        assert_eq!(
+            export.validate_template(),
+            Err(GlosserError::InvalidInput(String::from(
+                "template does not define: PIndented"
+            )))
+        );
+
+        //a template's own fonts, sizes and spacing are left alone; only placeholders are filled in
+        let export = ExportFodt {
+            config: ExportConfig {
+                font_families: vec![String::from("Gentium Plus")],
+                font_size: 11.0,
+                line_spacing: 1.0,
+                template: Some(
+                    FODT_TEMPLATE
+                        .replace(
+                            r#"<style:font-face style:name="%MAIN_FONT%" svg:font-family="&apos;%MAIN_FONT%&apos;"/>"#,
+                            r#"<style:font-face style:name="IFAO-Grec Unicode" svg:font-family="&apos;IFAO-Grec Unicode&apos;"/>"#,
+                        )
+                        .replace(r#"fo:line-height="%LINE_HEIGHT%""#, r#"fo:line-height="150%""#)
+                        .replacen("%FONT_SIZE%", "12pt", 1),
+                ),
+                ..Default::default()
+            },
+        };
+        let fodt = seq.make_document(&gloss_occurrances, &export, &options);
+        assert!(fodt.contains(r#"<style:font-face style:name="IFAO-Grec Unicode" svg:font-family="&apos;IFAO-Grec Unicode&apos;"/>"#));
+        assert!(fodt.contains(r#"style:font-name="Gentium Plus""#));
+        assert!(fodt.contains(r#"fo:font-size="12pt""#));
+        assert!(fodt.contains(r#"fo:font-size="11pt""#));
+        assert!(fodt.contains(r#"fo:line-height="150%""#));
+    }
+
+    #[test]
+    fn citest_test_html_index() {
+        let seq = make_glossed_sequence();
+        let gloss_occurrances = seq.process().unwrap();
+        let options = GlossPageOptions {
+            filter_unique: true,
+            filter_invisible: true,
+            sort_key: true,
+            glossary: false,
+        };
+        let arrowed_word = seq.texts[0].words[2].uuid;
+
+        let html = seq.make_document(&gloss_occurrances, &ExportHTML::default(), &options);
+        assert!(html.contains("<div class='Page' id='page-1'>"));
+        assert!(html.contains(&format!("<div id=\"gloss-word-{}\"", arrowed_word)));
+        assert!(html.contains("<input type='search' id='index-filter'"));
+        assert!(html.contains(&format!(
+            "<div class='IndexLetter'>\n<h3>Λ</h3>\n<ul>\n<li class='IndexEntry'><a href='#gloss-word-{}'>λόγος</a> <a class='IndexPage' href='#page-1'>1</a></li>\n</ul>\n</div>\n",
+            arrowed_word
+        )));
+
+        let entry = |lemma: &str, sort: &str, page_number| ArrowedWordsIndex {
+            gloss_lemma: String::from(lemma),
+            gloss_sort: String::from(sort),
+            page_number,
+            word_uuid: Uuid::new_v4(),
+        };
+        let mut index = vec![
+            entry("βίος", "βιος", 3),
+            entry("ἀνήρ", "ανηρ", 7),
+            entry("ἄγω", "αγω", 12),
+        ];
+        sort_index(&mut index);
+        let html = ExportHTML::default().make_index(&index);
+        assert_eq!(html.matches("<h3>").count(), 2);
+        let alpha = html.find("<h3>Α</h3>").unwrap();
+        let ago = html.find(">ἄγω</a>").unwrap();
+        let aner = html.find(">ἀνήρ</a>").unwrap();
+        let beta = html.find("<h3>Β</h3>").unwrap();
+        let bios = html.find(">βίος</a>").unwrap();
+        assert!(alpha < ago && ago < aner && aner < beta && beta < bios);
+        assert!(html.contains("href='#page-12'>12</a>"));
+
+        let html = ExportHTML::default().make_index(&[]);
+        assert!(!html.contains("IndexLetter'>"));
+        assert!(html.contains("id='index-filter'"));
+    }
+
+    #[test]
+    fn citest_test_index_layout() {
+        use exportconfig::{IndexBreaks, IndexLayout, Leader};
+
+        let entry = |lemma: &str, sort: &str, page_number| ArrowedWordsIndex {
+            gloss_lemma: String::from(lemma),
+            gloss_sort: String::from(sort),
+            page_number,
+            word_uuid: Uuid::new_v4(),
+        };
+        let index = vec![
+            entry("ἄγω", "αγω", 12),
+            entry("ἀνήρ", "ανηρ", 7),
+            entry("βίος", "βιος", 3),
+            entry("βοῦς", "βους", 4),
+            entry("γῆ", "γη", 5),
+        ];
+
+        //by default one column which the typesetter breaks into pages
+        let latex = ExportLatex::default().make_index(&index);
+        assert!(latex.contains("ἄγω \\dotfill 12 \\\\ \nἀνήρ \\dotfill 7 \\\\ \n"));
+        assert!(!latex.contains("multicols"));
+        assert_eq!(latex.matches("\\newpage").count(), 1);
+        let typst = ExportTypst::default().make_index(&index);
+        assert!(typst.contains(
+            "#indextable(\n[ἄγω #box(width: 1fr, repeat[.])],[#box(width: 1fr, repeat[.]) 12],"
+        ));
+        assert!(!typst.contains("#colbreak()") && !typst.contains("#columns("));
+        let fodt = ExportFodt::default();
+        let start = fodt.document_start("", 1);
+        assert!(start.contains(r#"<style:columns fo:column-count="1" fo:column-gap="0.3in"/>"#));
+        assert!(start.contains(
+            r#"<style:tab-stop style:position="6.9252in" style:type="right" style:leader-style="dotted" style:leader-text="."/>"#
+        ));
+        assert!(start.contains(r#"fo:break-before="page""#));
+        let body = fodt.make_index(&index);
+        assert!(
+            body.contains(r#"<text:section text:style-name="IndexSection" text:name="Index">"#)
+        );
+        assert!(
+            body.contains(
+                r#"<text:p text:style-name="IndexOfArrowedWords">γῆ<text:tab/>5</text:p>"#
+            )
+        );
+        assert!(!body.contains("Break\""));
+
+        let config = ExportConfig {
+            index: IndexLayout {
+                columns: 2,
+                letter_headings: true,
+                leader: Leader::Line,
+                breaks: IndexBreaks::EntriesPerColumn(3),
+            },
+            ..Default::default()
+        };
+
+        let export = ExportLatex {
+            config: config.clone(),
+        };
+        assert_eq!(export.validate_template(), Ok(()));
+        let latex = export.make_index(&index);
+        assert!(latex.contains("\\begin{multicols}{2}\n\\par\\medskip\\noindent\\textbf{Α}\\par\\nopagebreak\\noindent\nἄγω \\hrulefill 12 \\\\ \n"));
+        assert_eq!(latex.matches("\\textbf{").count(), 3);
+        assert!(latex.contains(
+            "βίος \\hrulefill 3 \\\\ \n\\columnbreak\n\\noindent\nβοῦς \\hrulefill 4 \\\\ \n"
+        ));
+        assert!(latex.ends_with("γῆ \\hrulefill 5 \\\\ \n\\end{multicols}\n"));
+        let export = ExportLatex {
+            config: ExportConfig {
+                template: Some(exportlatex::LATEX_TEMPLATE.replace("\\usepackage{multicol}", "")),
+                ..config.clone()
+            },
+        };
+        assert_eq!(
+            export.validate_template(),
+            Err(GlosserError::InvalidInput(String::from(
+                "template does not define: multicol"
+            )))
+        );
+
+        let typst = ExportTypst {
+            config: config.clone(),
+        }
+        .make_index(&index);
+        assert!(typst.contains("#columns(2)[\n#indextable(\ntable.cell(colspan: 2)[#strong[Α]],[ἄγω #box(width: 1fr, line(length: 100%, stroke: 0.5pt))],"));
+        assert_eq!(typst.matches("#colbreak()").count(), 1);
+        assert!(typst.contains("3],\n)\n#colbreak()\n#indextable(\n[βοῦς "));
+        assert!(typst.ends_with("\n)\n]"));
+
+        let fodt = ExportFodt { config };
+        let start = fodt.document_start("", 1);
+        assert!(start.contains(r#"<style:columns fo:column-count="2" fo:column-gap="0.3in"/>"#));
+        assert!(start.contains(
+            r#"<style:tab-stop style:position="3.3126in" style:type="right" style:leader-style="solid"/>"#
+        ));
+        assert!(start.contains(r#"fo:break-before="column""#));
+        let body = fodt.make_index(&index);
+        assert_eq!(body.matches("\"IndexLetter\"").count(), 3);
+        assert!(body.contains(
+            r#"<text:p text:style-name="IndexOfArrowedWordsBreak">βοῦς<text:tab/>4</text:p>"#
+        ));
+        assert_eq!(body.matches("Break\"").count(), 1);
+    }
+
+    #[test]
+    fn citest_test_appcrits() {
+        use exportconfig::AppCritStyle;
+        let mut seq = make_glossed_sequence();
+        let words = &seq.texts[0].words;
+        seq.texts[0].appcrits = Some(vec![
+            AppCrit {
+                word_uuid: words[2].uuid,
+                end_word_uuid: Some(words[5].uuid),
+                lemma: String::from("λόγος"),
+                reading: String::from("<i>om.</i>"),
+                witnesses: vec![String::from("A"), String::from("B")],
+                ..Default::default()
+            },
+            AppCrit {
+                word_uuid: words[1].uuid,
+                lemma: String::from("ὁ"),
+                reading: String::from("τὸ"),
+                entry: String::from("(Carey)"),
+                ..Default::default()
+            },
+        ]);
+
+        //the structured fields round-trip through the xml
+        let xml = seq.texts[0].to_xml().unwrap();
+        assert!(xml.contains(r#"witnesses="A,B""#));
+        assert_eq!(Text::from_xml(&xml, None, None).unwrap(), seq.texts[0]);
+
+        //notes are numbered in the order of their words, and a span crossing lines is referenced
+        //by its first and last
+        let gloss_occurrances = seq.process().unwrap();
+        let page_text = model::make_page_text(&gloss_occurrances[0], &seq.make_appcrit_hash());
+        assert_eq!(
+            page_text.appcrits,
+            vec![
+                model::AppCritNote {
+                    number: 1,
+                    reference: String::from("1.1"),
+                    text: String::from("ὁ] τὸ (Carey)"),
+                },
+                model::AppCritNote {
+                    number: 2,
+                    reference: String::from("1.1–5"),
+                    text: String::from("λόγος] <i>om.</i> A B"),
+                },
+            ]
+        );
+
+        let options = GlossPageOptions {
+            filter_unique: true,
+            filter_invisible: true,
+            sort_key: true,
+            glossary: false,
+        };
+        //single pages show the notes too
+        let summary = seq.make_process_summary().unwrap();
+        let text = seq
+            .make_single_page(&summary, &ExportPlainText {}, &options, 1)
+            .unwrap();
+        assert!(text.contains("ὁ] τὸ (Carey)\nλόγος] om. A B\n"));
+
+        let latex = ExportLatex {
+            config: ExportConfig {
+                appcrit_notes: AppCritStyle::Numbered,
+                ..Default::default()
+            },
+        };
+        let page = seq.make_single_page(&summary, &latex, &options, 1).unwrap();
+        assert!(page.contains("ὁ\\textsuperscript{1} λόγος"));
+        assert!(page.contains("μῆνιν\\textsuperscript{2}"));
+        assert!(page.contains("\\textbf{2} λόγος] \\textit{om.} A B\\\\\n"));
+
+        let typst = ExportTypst {
+            config: ExportConfig {
+                appcrit_notes: AppCritStyle::LineReferenced,
+                ..Default::default()
+            },
+        };
+        let page = seq.make_single_page(&summary, &typst, &options, 1).unwrap();
+        assert!(page.contains("#strong[1.1] ὁ\\u{005D} τὸ (Carey) \\\n"));
+        assert!(page.contains("#strong[1.1–5] λόγος"));
+        assert!(!page.contains("#super[1]"));
+    }
+
+    #[cfg(feature = "pdf")]
+    #[test]
+    fn citest_test_pdf() {
+        let pdf =
+            pdf::compile_pdf("#set text(font: \"IFAO-Grec Unicode\")\nὁ λόγος", None).unwrap();
+        assert!(pdf.starts_with(b"%PDF"));
+
+        //dated from the given date, not the clock, so the same input gives the same pdf
+        let source = "#datetime.today().display()";
+        let first = pdf::compile_pdf(source, Some("2024-01-31")).unwrap();
+        assert_eq!(first, pdf::compile_pdf(source, Some("2024-01-31")).unwrap());
+        assert!(pdf::compile_pdf(source, None).is_err());
+
+        //the whole document, with the template's packages read from the embedded copies
+        let seq = make_glossed_sequence();
+        let gloss_occurrances = seq.process().unwrap();
+        let options = GlossPageOptions {
+            filter_unique: true,
+            filter_invisible: true,
+            sort_key: true,
+            glossary: true,
+        };
+        let mut export = ExportTypst::default();
+        export.config.date = Some(String::from("2024-01-31"));
+        let pdf = seq.make_pdf(&gloss_occurrances, &export, &options).unwrap();
+        assert!(pdf.starts_with(b"%PDF"));
+        assert_eq!(
+            pdf,
+            seq.make_pdf(&gloss_occurrances, &export, &options).unwrap()
+        );
+
+        //other packages are never downloaded
+        let err = pdf::compile_pdf("#import \"@preview/other:1.0.0\": thing", None).unwrap_err();
+        let GlosserError::Other(message) = err else {
+            panic!("expected a Typst error");
+        };
+        assert!(message.contains("@preview/other:1.0.0"), "{}", message);
+    }
+
+    #[test]
+    fn citest_test_glossary() {
+        let mut seq = make_glossed_sequence();
+        let gloss = Gloss {
+            uuid: Uuid::new_v4(),
+            lemma: String::from("ἀνήρ, ἀνδρός, ὁ"),
+            sort_key: String::from("ανηρ"),
+            def: String::from("<i>man</i>"),
+            pos: String::from("noun"),
+            status: 1,
+            ..Default::default()
+        };
+        seq.texts[0].words[5].gloss_uuid = Some(gloss.uuid);
+        seq.glosses[0].gloss.push(gloss);
+        let gloss_occurrances = seq.process().unwrap();
+        let mut options = GlossPageOptions {
+            filter_unique: true,
+            filter_invisible: true,
+            sort_key: true,
+            glossary: false,
+        };
+
+        let markdown = seq.make_document(&gloss_occurrances, &ExportMarkdown {}, &options);
+        assert!(!markdown.contains("## Glossary"));
+        assert!(
+            seq.make_document_model(&gloss_occurrances, &options)
+                .glossary
+                .is_empty()
+        );
+
+        options.glossary = true;
+        let document = seq.make_document_model(&gloss_occurrances, &options);
+        assert_eq!(
+            document.glossary,
+            vec![
+                model::GlossaryEntry {
+                    lemma: String::from("ἀνήρ, ἀνδρός, ὁ"),
+                    sort_key: String::from("ανηρ"),
+                    pos: String::from("noun"),
+                    def: String::from("<i>man</i>"),
+                    total_count: 1,
+                    arrowed_page: None,
+                },
+                model::GlossaryEntry {
+                    lemma: String::from("λόγος, -ου, ὁ"),
+                    sort_key: String::from("λογος"),
+                    pos: String::from("noun"),
+                    def: String::from("<i>word</i>, speech"),
+                    total_count: 1,
+                    arrowed_page: Some(1),
+                },
+            ]
+        );
+
+        //the glossary follows the index
+        let markdown = seq.make_document(&gloss_occurrances, &ExportMarkdown {}, &options);
+        assert!(markdown.ends_with(
+            "## Index\n\n- λόγος, 1\n\n## Glossary\n\n- **ἀνήρ, ἀνδρός, ὁ** (noun) *man* (1×)\n- **λόγος, -ου, ὁ** (noun) *word*, speech (1×, → p. 1)\n"
+        ));
+        let text = seq.make_document(&gloss_occurrances, &ExportPlainText {}, &options);
+        assert!(text.contains("=== Glossary ===\n\nἀνήρ, ἀνδρός, ὁ (noun) man (1×)\n"));
+        let latex = seq.make_document(&gloss_occurrances, &ExportLatex::default(), &options);
+        assert!(latex.contains("\\fancyhead[OR]{GLOSSARY}"));
+        assert!(
+            latex.contains(
+                "\\textbf{λόγος, -ου, ὁ} (noun) \\textit{word}, speech (1×, → p. 1)\\par\n"
+            )
+        );
+        let typst = seq.make_document(&gloss_occurrances, &ExportTypst::default(), &options);
+        assert!(
+            typst.contains("#glosshang[#strong[ἀνήρ, ἀνδρός, ὁ] (noun) #fakeitalic[man] (1×)]")
+        );
+        let html = seq.make_document(&gloss_occurrances, &ExportHTML::default(), &options);
+        assert!(html.contains("speech (1×, <a href='#page-1'>→ p. 1</a>)</li>"));
+        let fodt = seq.make_document(&gloss_occurrances, &ExportFodt::default(), &options);
+        assert!(fodt.contains(r#"<text:p text:style-name="Glossary"><text:span text:style-name="T1">ἀνήρ, ἀνδρός, ὁ</text:span>"#));
+        let epub = seq.make_epub(&gloss_occurrances, &options).unwrap();
+        assert!(epub.windows(14).any(|w| w == b"glossary.xhtml"));
+    }
 }
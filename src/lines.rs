@@ -1,74 +1,608 @@
-use crate::{ArrowedState, GlossOccurrance};
+use crate::breaks::{BreakRules, is_legal_break};
+use crate::model::{NO_SPACE_BEFORE, is_no_space_after};
+use crate::{
+    ArrowedState, GlossOccurrance, GlossUuid, GlosserError, Sequence, Word, WordType, WordUuid,
+};
+use rustybuzz::{Face, UnicodeBuffer, shape};
+use std::cell::RefCell;
+use std::collections::{HashMap, HashSet};
 
-pub fn get_width(text: &str) -> f32 {
-    use rustybuzz::{Face, UnicodeBuffer, shape};
-    let font_bytes = include_bytes!("../IFAOGrec.ttf") as &[u8];
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum FontStyle {
+    Regular,
+    Bold,
+}
+
+//shapes text with rustybuzz, parsing each font once and caching the width of every string it has seen.
+//widths are cached in ems so one measurer serves any font size.
+pub struct TextMeasurer<'a> {
+    regular: Face<'a>,
+    bold: Face<'a>,
+    regular_widths: RefCell<HashMap<String, f32>>,
+    bold_widths: RefCell<HashMap<String, f32>>,
+}
+
+impl Default for TextMeasurer<'static> {
+    fn default() -> Self {
+        TextMeasurer::new()
+    }
+}
 
-    // Create a rustybuzz Face
-    let face = Face::from_slice(font_bytes, 0).expect("Failed to load font");
+impl TextMeasurer<'static> {
+    //the bundled IFAOGrec.ttf and IFAOGrecBold.ttf
+    pub fn new() -> TextMeasurer<'static> {
+        let regular = include_bytes!("../IFAOGrec.ttf") as &[u8];
+        let bold = include_bytes!("../IFAOGrecBold.ttf") as &[u8];
+        TextMeasurer::with_fonts(regular, Some(bold)).expect("Failed to load font")
+    }
+}
+
+impl<'a> TextMeasurer<'a> {
+    //a caller-supplied font: when no bold face is given, bold text is measured with the regular face
+    pub fn with_fonts(regular: &'a [u8], bold: Option<&'a [u8]>) -> Result<Self, GlosserError> {
+        let load = |data: &'a [u8]| {
+            Face::from_slice(data, 0)
+                .ok_or_else(|| GlosserError::InvalidInput(String::from("could not parse font")))
+        };
+        let regular_face = load(regular)?;
+        let bold_face = match bold {
+            Some(data) => load(data)?,
+            None => regular_face.clone(),
+        };
+        Ok(TextMeasurer {
+            regular: regular_face,
+            bold: bold_face,
+            regular_widths: RefCell::new(HashMap::default()),
+            bold_widths: RefCell::new(HashMap::default()),
+        })
+    }
+
+    //the advance width of text at font_size, in the same unit as font_size
+    pub fn width(&self, text: &str, style: FontStyle, font_size: f32) -> f32 {
+        let (face, widths) = match style {
+            FontStyle::Regular => (&self.regular, &self.regular_widths),
+            FontStyle::Bold => (&self.bold, &self.bold_widths),
+        };
+        if let Some(em) = widths.borrow().get(text) {
+            return em * font_size;
+        }
+        let em = shape_width(face, text);
+        widths.borrow_mut().insert(text.to_string(), em);
+        em * font_size
+    }
+}
 
+//the width of text in ems
+fn shape_width(face: &Face, text: &str) -> f32 {
     // Build a UnicodeBuffer for shaping
     let mut buffer = UnicodeBuffer::new();
     buffer.push_str(text);
 
     // Shape the text with no features (empty slice)
-    let glyph_buffer = shape(&face, &[], buffer);
-
-    // Get shaped positions
-    let positions = glyph_buffer.glyph_positions();
+    let glyph_buffer = shape(face, &[], buffer);
 
     // Get the font’s units per EM for scaling
     let upem = face.units_per_em() as f32;
 
-    // Desired pixel font size
-    let px_size: f32 = 32.0;
+    // Sum horizontal advances
+    // Rustybuzz uses 26.6 fixed-point (divide by 64) to get float font units
+    let width: f32 = glyph_buffer
+        .glyph_positions()
+        .iter()
+        .map(|pos| pos.x_advance as f32 / 64.0)
+        .sum();
+    width / upem
+}
 
-    let scale = px_size / upem;
+thread_local! {
+    static MEASURER: TextMeasurer<'static> = TextMeasurer::new();
+}
 
-    // Sum horizontal advances
-    let mut width_px: f32 = 0.0;
-
-    for pos in positions {
-        // Rustybuzz uses 26.6 fixed-point (divide by 64) to get float font units
-        let adv_font_units = pos.x_advance as f32 / 64.0;
-        width_px += adv_font_units * scale;
-    }
-    width_px
-}
-
-pub fn count_lines(gloss_occurances: &[GlossOccurrance]) -> Vec<usize> {
-    use std::cmp::max;
-
-    let text_lines_per_page = 23;
-    let width_of_line: f32 = 7.0;
-    let width_of_lemma: f32 = 9.5;
-    let width_of_def: f32 = 8.3;
-
-    let mut word_counts = vec![];
-    let mut current_text_width = 0.0;
-    let mut lines_in_page = 0;
-    let mut words_per_page = 0;
-    for go in gloss_occurances {
-        words_per_page += 1;
-        current_text_width += get_width(&go.word.word);
-        if current_text_width > width_of_line {
-            lines_in_page += 1;
-            current_text_width = 0.0;
+pub fn get_width(text: &str) -> f32 {
+    get_width_at(text, 32.0)
+}
+
+//the advance width of text set in IFAOGrec at font_size, in the same unit as font_size
+pub fn get_width_at(text: &str, font_size: f32) -> f32 {
+    MEASURER.with(|m| m.width(text, FontStyle::Regular, font_size))
+}
+
+//the number of words on each page when the text is fitted into pages of this geometry
+pub fn count_lines(
+    gloss_occurances: &[GlossOccurrance],
+    geometry: &PageGeometry,
+    rules: &BreakRules,
+) -> Vec<usize> {
+    let words: Vec<Word> = gloss_occurances.iter().map(|go| go.word.clone()).collect();
+    let measurer = TextMeasurer::new();
+    let layout = Layout {
+        geometry,
+        measurer: &measurer,
+    };
+    paginate_text(
+        gloss_occurances,
+        &words,
+        &HashMap::default(),
+        rules,
+        &layout,
+    )
+    .0
+}
+
+const POINTS_PER_INCH: f32 = 72.0;
+const POINTS_PER_CM: f32 = 72.0 / 2.54;
+
+//the layout of a page, all lengths in points
+#[derive(Clone, Debug, PartialEq)]
+pub struct PageGeometry {
+    pub paper_width: f32,
+    pub paper_height: f32,
+    pub margin_top: f32,
+    pub margin_bottom: f32,
+    pub margin_left: f32,
+    pub margin_right: f32,
+    pub font_size: f32,
+    pub lemma_style: FontStyle,
+    pub text_width: f32,  //width of a line of running text
+    pub lemma_width: f32, //width of the lemma column of the gloss table
+    pub def_width: f32,   //width of the definition column of the gloss table
+    pub verse_width: f32, //width of the column verse lines are set in
+    pub paragraph_indent: f32,
+    pub text_line_height: f32,
+    pub gloss_line_height: f32,
+    pub gloss_entry_gap: f32, //extra space above each row of the gloss table
+}
+
+impl Default for PageGeometry {
+    fn default() -> Self {
+        PageGeometry::latex()
+    }
+}
+
+impl PageGeometry {
+    //the space left for text and glosses once the margins are taken away
+    pub fn body_height(&self) -> f32 {
+        self.paper_height - self.margin_top - self.margin_bottom
+    }
+
+    pub fn body_width(&self) -> f32 {
+        self.paper_width - self.margin_left - self.margin_right
+    }
+
+    //letterpaper, 1in margins and 12pt as in ExportLatex's preamble:
+    //the text is set in \begin{spacing}{1.5} and the gloss table uses L{3.25in} D{3.1in} columns
+    //with \extrarowheight of 8pt; verse is set in a p{9.5cm} column
+    pub fn latex() -> PageGeometry {
+        let font_size = 12.0;
+        let baselineskip = 14.5; //for the 12pt class option
+        let margin = POINTS_PER_INCH;
+        PageGeometry {
+            paper_width: 8.5 * POINTS_PER_INCH,
+            paper_height: 11.0 * POINTS_PER_INCH,
+            margin_top: margin,
+            margin_bottom: margin,
+            margin_left: margin,
+            margin_right: margin,
+            font_size,
+            lemma_style: FontStyle::Regular,
+            text_width: 6.5 * POINTS_PER_INCH,
+            lemma_width: 3.25 * POINTS_PER_INCH,
+            def_width: 3.1 * POINTS_PER_INCH,
+            verse_width: 9.5 * POINTS_PER_CM,
+            paragraph_indent: font_size * 1.5,
+            text_line_height: baselineskip * 1.5,
+            gloss_line_height: baselineskip,
+            gloss_entry_gap: 8.0,
         }
-        if let Some(gloss) = go.gloss
-            && go.arrowed_state != ArrowedState::Invisible
-        {
-            let lemma_width = get_width(&gloss.lemma);
-            let def_width = get_width(&gloss.def);
-            let lemma_lines = (width_of_lemma / lemma_width).ceil() as usize;
-            let def_lines = (width_of_def / def_width).ceil() as usize;
-            lines_in_page += max(lemma_lines, def_lines);
+    }
+
+    //8.5x11in with typst's default margins (2.5/21 of the shorter side) and 12pt as in ExportTypst:
+    //par leading is 0.9em for the text and 0.7em for glosses, glosstable has 8.0cm and 9.0cm columns,
+    //the default 5pt cell inset and a 0.07cm row-gutter; versetable's text column is 9.0cm
+    pub fn typst() -> PageGeometry {
+        let font_size = 12.0;
+        let paper_width = 8.5 * POINTS_PER_INCH;
+        let margin = paper_width * 2.5 / 21.0;
+        let inset = 5.0;
+        PageGeometry {
+            paper_width,
+            paper_height: 11.0 * POINTS_PER_INCH,
+            margin_top: margin,
+            margin_bottom: margin,
+            margin_left: margin,
+            margin_right: margin,
+            font_size,
+            lemma_style: FontStyle::Regular,
+            text_width: paper_width - 2.0 * margin,
+            lemma_width: 8.0 * POINTS_PER_CM - 2.0 * inset,
+            def_width: 9.0 * POINTS_PER_CM - 2.0 * inset,
+            verse_width: 9.0 * POINTS_PER_CM - 2.0 * inset,
+            paragraph_indent: font_size * 2.0,
+            text_line_height: font_size * 1.9,
+            gloss_line_height: font_size * 1.7,
+            gloss_entry_gap: 2.0 * inset + 0.07 * POINTS_PER_CM,
         }
-        if lines_in_page >= text_lines_per_page {
-            word_counts.push(words_per_page);
-            words_per_page = 0;
-            lines_in_page = 0;
+    }
+
+    //8.5x11in, 0.7874in margins and 12pt as in ExportFodt's page layout:
+    //Standard paragraphs have a proportional line height of 130% and the gloss paragraphs 100%,
+    //taking a single line as 1.2em. GlossTable.B and .C are 3.4236in and 3.5in less 0.1201in padding
+    //and each gloss table cell has 0.1097in top padding. VerseTable.B is 4.0625in and PIndented
+    //paragraphs are indented 0.5in
+    pub fn fodt() -> PageGeometry {
+        let font_size = 12.0;
+        let single_line = font_size * 1.2;
+        let margin = 0.7874 * POINTS_PER_INCH;
+        let padding = 0.1201 * POINTS_PER_INCH;
+        PageGeometry {
+            paper_width: 8.5 * POINTS_PER_INCH,
+            paper_height: 11.0 * POINTS_PER_INCH,
+            margin_top: margin,
+            margin_bottom: margin,
+            margin_left: margin,
+            margin_right: margin,
+            font_size,
+            lemma_style: FontStyle::Regular,
+            text_width: 8.5 * POINTS_PER_INCH - 2.0 * margin,
+            lemma_width: 3.4236 * POINTS_PER_INCH - padding,
+            def_width: 3.5 * POINTS_PER_INCH - padding,
+            verse_width: (4.0625 - 2.0 * 0.0201) * POINTS_PER_INCH,
+            paragraph_indent: 0.5 * POINTS_PER_INCH,
+            text_line_height: single_line * 1.3,
+            gloss_line_height: single_line,
+            gloss_entry_gap: 0.1097 * POINTS_PER_INCH,
         }
     }
-    word_counts
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct PageOverflow {
+    pub page_index: usize,
+    pub height: f32,   //points used by the page
+    pub capacity: f32, //the geometry's body height
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct TextPagination {
+    pub text_index: usize,
+    pub words_per_page: Vec<usize>,
+    pub overflows: Vec<PageOverflow>, //pages which do not fit even though they cannot be split further
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct PaginationReport {
+    pub texts: Vec<TextPagination>,
+}
+
+//what the page estimate needs: the page's dimensions and how to measure text
+struct Layout<'a> {
+    geometry: &'a PageGeometry,
+    measurer: &'a TextMeasurer<'a>,
+}
+
+impl Layout<'_> {
+    fn width(&self, text: &str, style: FontStyle) -> f32 {
+        self.measurer.width(text, style, self.geometry.font_size)
+    }
+}
+
+fn gloss_lines(go: &GlossOccurrance, layout: &Layout) -> usize {
+    if let Some(gloss) = go.gloss
+        && go.arrowed_state != ArrowedState::Invisible
+    {
+        let lemma_width = layout.width(&gloss.lemma, layout.geometry.lemma_style);
+        let def_width = layout.width(&gloss.def, FontStyle::Regular);
+        let lemma_lines = (lemma_width / layout.geometry.lemma_width).ceil() as usize;
+        let def_lines = (def_width / layout.geometry.def_width).ceil() as usize;
+        lemma_lines.max(def_lines).max(1)
+    } else {
+        0
+    }
+}
+
+//the running text of a page laid out line by line following the structure make_text gives it:
+//prose fills each line greedily, each verse line starts a new line in the verse column,
+//paragraphs and inline speakers break the line and titles and speakers are lines of their own
+#[derive(Clone, Copy, Default)]
+struct TextFlow {
+    lines: usize,            //finished lines
+    line_width: Option<f32>, //the width used by the open line, if any
+    line_start: f32,         //the indent of the next line to be opened
+    verse_line: bool,        //a verse line has been started but has no words yet
+    verse: bool,             //once a page turns to verse, the rest of it is set in the verse table
+    prev_non_space: bool,
+}
+
+impl TextFlow {
+    fn column(&self, layout: &Layout) -> f32 {
+        if self.verse {
+            layout.geometry.verse_width
+        } else {
+            layout.geometry.text_width
+        }
+    }
+
+    fn break_line(&mut self) {
+        if self.line_width.is_some() || self.verse_line {
+            self.lines += 1;
+        }
+        self.line_width = None;
+        self.line_start = 0.0;
+        self.verse_line = false;
+    }
+
+    //spaces at the start of a line are dropped
+    fn add_inline(&mut self, width: f32, space: f32, layout: &Layout) {
+        let column = self.column(layout);
+        self.line_width = match self.line_width {
+            None => Some(self.line_start + width),
+            Some(w) if w + space + width > column => {
+                self.lines += 1;
+                Some(width)
+            }
+            Some(w) => Some(w + space + width),
+        };
+        self.verse_line = false;
+    }
+
+    //centred lines such as titles, wrapped at the text width
+    fn add_block(&mut self, width: f32, layout: &Layout) {
+        self.break_line();
+        self.lines += ((width / layout.geometry.text_width).ceil() as usize).max(1);
+    }
+
+    fn push(&mut self, go: &GlossOccurrance, layout: &Layout) {
+        let word = go.word.word.as_str();
+        match go.word.word_type {
+            WordType::VerseLine => {
+                self.break_line();
+                self.verse = true;
+                self.verse_line = true;
+            }
+            WordType::WorkTitle => self.add_block(layout.width(word, FontStyle::Bold), layout),
+            WordType::Word | WordType::Punctuation => {
+                let space = if NO_SPACE_BEFORE.contains(&word) || self.prev_non_space {
+                    0.0
+                } else {
+                    layout.width(" ", FontStyle::Regular)
+                };
+                self.add_inline(layout.width(word, FontStyle::Regular), space, layout);
+                self.prev_non_space = is_no_space_after(word);
+            }
+            WordType::ParaWithIndent => {
+                self.break_line();
+                if !self.verse {
+                    self.line_start = layout.geometry.paragraph_indent;
+                }
+            }
+            WordType::ParaNoIndent => self.break_line(),
+            WordType::SectionTitle | WordType::Speaker => {
+                self.add_block(layout.width(word, FontStyle::Regular), layout)
+            }
+            //section numbers are set in the margin
+            WordType::Section => self.prev_non_space = true,
+            WordType::InlineSpeaker if !self.verse => {
+                self.break_line();
+                let width = layout.width(word, FontStyle::Bold);
+                self.line_width = Some(width + layout.width(" ", FontStyle::Regular));
+            }
+            //verse speakers are set in their own column
+            _ => (),
+        }
+    }
+
+    fn lines(&self) -> usize {
+        self.lines + usize::from(self.line_width.is_some() || self.verse_line)
+    }
+}
+
+//the lines used by the words of one page so far
+#[derive(Default)]
+struct PageLines {
+    words: usize,
+    text: TextFlow,
+    appcrit_lines: usize,
+    gloss_lines: usize,
+    gloss_entries: usize,
+    glosses: HashSet<GlossUuid>,
+}
+
+fn page_height(
+    text_lines: usize,
+    gloss_lines: usize,
+    gloss_entries: usize,
+    layout: &Layout,
+) -> f32 {
+    text_lines as f32 * layout.geometry.text_line_height
+        + gloss_lines as f32 * layout.geometry.gloss_line_height
+        + gloss_entries as f32 * layout.geometry.gloss_entry_gap
+}
+
+impl PageLines {
+    //the apparatus follows the text after an empty line
+    fn added_appcrit_lines(&self, appcrit: Option<&str>, layout: &Layout) -> usize {
+        match appcrit {
+            Some(entry) => {
+                let width = layout.width(entry, FontStyle::Regular);
+                let lines = ((width / layout.geometry.text_width).ceil() as usize).max(1);
+                lines + usize::from(self.appcrit_lines == 0)
+            }
+            None => 0,
+        }
+    }
+
+    //each gloss is listed once per page
+    fn added_gloss_lines(&self, go: &GlossOccurrance, layout: &Layout) -> usize {
+        match go.gloss {
+            Some(gloss) if !self.glosses.contains(&gloss.uuid) => gloss_lines(go, layout),
+            _ => 0,
+        }
+    }
+
+    fn height_with(&self, go: &GlossOccurrance, appcrit: Option<&str>, layout: &Layout) -> f32 {
+        let mut text = self.text;
+        text.push(go, layout);
+        let added = self.added_gloss_lines(go, layout);
+        page_height(
+            text.lines() + self.appcrit_lines + self.added_appcrit_lines(appcrit, layout),
+            self.gloss_lines + added,
+            self.gloss_entries + usize::from(added > 0),
+            layout,
+        )
+    }
+
+    fn push(&mut self, go: &GlossOccurrance, appcrit: Option<&str>, layout: &Layout) {
+        self.words += 1;
+        self.text.push(go, layout);
+        self.appcrit_lines += self.added_appcrit_lines(appcrit, layout);
+        let added = self.added_gloss_lines(go, layout);
+        if added > 0 {
+            self.gloss_lines += added;
+            self.gloss_entries += 1;
+        }
+        if let Some(gloss) = go.gloss {
+            self.glosses.insert(gloss.uuid);
+        }
+    }
+
+    fn height(&self, layout: &Layout) -> f32 {
+        page_height(
+            self.text.lines() + self.appcrit_lines,
+            self.gloss_lines,
+            self.gloss_entries,
+            layout,
+        )
+    }
+}
+
+fn layout_page(
+    gloss_occurrances: &[GlossOccurrance],
+    appcrits: &HashMap<WordUuid, String>,
+    layout: &Layout,
+) -> PageLines {
+    let mut page = PageLines::default();
+    for go in gloss_occurrances {
+        let appcrit = appcrits.get(&go.word.uuid).map(|a| a.as_str());
+        page.push(go, appcrit, layout);
+    }
+    page
+}
+
+//words are the text's words, in the same order as gloss_occurrances
+fn paginate_text(
+    gloss_occurrances: &[GlossOccurrance],
+    words: &[Word],
+    appcrits: &HashMap<WordUuid, String>,
+    rules: &BreakRules,
+    layout: &Layout,
+) -> (Vec<usize>, Vec<PageOverflow>) {
+    let capacity = layout.geometry.body_height();
+    let mut words_per_page = vec![];
+    let mut overflows = vec![];
+    let mut close_page = |page: &PageLines| {
+        //a page can only be too long when its first word alone doesn't fit
+        let height = page.height(layout);
+        if height > capacity {
+            overflows.push(PageOverflow {
+                page_index: words_per_page.len(),
+                height,
+                capacity,
+            });
+        }
+        words_per_page.push(page.words);
+    };
+    let mut page = PageLines::default();
+    let mut start = 0;
+    let mut i = 0;
+    while i < gloss_occurrances.len() {
+        let go = &gloss_occurrances[i];
+        let appcrit = appcrits.get(&go.word.uuid).map(|a| a.as_str());
+        if page.words > 0 && page.height_with(go, appcrit, layout) > capacity {
+            //the word doesn't fit: end the page at the last legal break on it
+            //and start the next page there. when there is none, break before the word anyway
+            let b = (start + 1..=i)
+                .rev()
+                .find(|b| is_legal_break(words, *b, rules))
+                .unwrap_or(i);
+            if b < i {
+                page = layout_page(&gloss_occurrances[start..b], appcrits, layout);
+            }
+            close_page(&page);
+            page = PageLines::default();
+            start = b;
+            i = b;
+            continue;
+        }
+        page.push(go, appcrit, layout);
+        i += 1;
+    }
+    if page.words > 0 {
+        close_page(&page);
+    }
+    (words_per_page, overflows)
+}
+
+//fits the text and the visible gloss entries of each page into the page, breaking pages where rules allow,
+//and writes the resulting counts into each displayed text's words_per_page
+pub fn paginate(
+    seq: &mut Sequence,
+    geometry: PageGeometry,
+    rules: &BreakRules,
+) -> Result<PaginationReport, GlosserError> {
+    paginate_with_measurer(seq, geometry, rules, &TextMeasurer::new())
+}
+
+//as paginate, measuring with the caller's fonts
+pub fn paginate_with_measurer(
+    seq: &mut Sequence,
+    geometry: PageGeometry,
+    rules: &BreakRules,
+    measurer: &TextMeasurer,
+) -> Result<PaginationReport, GlosserError> {
+    let layout = Layout {
+        geometry: &geometry,
+        measurer,
+    };
+    let mut texts = vec![];
+    {
+        let gloss_occurrances = seq.process()?;
+        for (text_index, text) in gloss_occurrances.iter().enumerate() {
+            if !seq
+                .sequence_description
+                .texts
+                .get(text_index)
+                .is_some_and(|t| t.display)
+            {
+                continue;
+            }
+            let mut appcrits = HashMap::default();
+            if let Some(entries) = seq.texts.get(text_index).and_then(|t| t.appcrits.as_ref()) {
+                for ap in entries {
+                    appcrits.insert(ap.word_uuid, ap.text());
+                }
+            }
+            let (words_per_page, overflows) = paginate_text(
+                text,
+                &seq.texts[text_index].words,
+                &appcrits,
+                rules,
+                &layout,
+            );
+            texts.push(TextPagination {
+                text_index,
+                words_per_page,
+                overflows,
+            });
+        }
+    }
+
+    for t in &texts {
+        seq.sequence_description.texts[t.text_index].words_per_page = t
+            .words_per_page
+            .iter()
+            .map(|c| c.to_string())
+            .collect::<Vec<String>>()
+            .join(",");
+    }
+    Ok(PaginationReport { texts })
 }
@@ -0,0 +1,258 @@
+use crate::GlossUuid;
+use crate::glosses::Glosses;
+use std::fmt;
+
+//the small html-like markup allowed in gloss lemmas and definitions
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum MarkupTag {
+    Italic,
+    Bold,
+    Superscript,
+}
+
+impl MarkupTag {
+    fn from_name(name: &str) -> Option<MarkupTag> {
+        match name {
+            "i" => Some(MarkupTag::Italic),
+            "b" => Some(MarkupTag::Bold),
+            "sup" => Some(MarkupTag::Superscript),
+            _ => None,
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            MarkupTag::Italic => "i",
+            MarkupTag::Bold => "b",
+            MarkupTag::Superscript => "sup",
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum MarkupNode {
+    Text(String),
+    Tagged {
+        tag: MarkupTag,
+        children: Vec<MarkupNode>,
+    },
+}
+
+//positions are byte offsets of the tag's '<'
+#[derive(Clone, Debug, PartialEq)]
+pub enum MarkupError {
+    UnknownTag { tag: String, position: usize },
+    UnclosedTag { tag: MarkupTag, position: usize },
+    UnexpectedClosingTag { tag: String, position: usize },
+}
+
+impl fmt::Display for MarkupError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            MarkupError::UnknownTag { tag, position } => {
+                write!(f, "unknown tag <{}> at {}", tag, position)
+            }
+            MarkupError::UnclosedTag { tag, position } => {
+                write!(f, "<{}> at {} is never closed", tag.name(), position)
+            }
+            MarkupError::UnexpectedClosingTag { tag, position } => {
+                write!(f, "</{}> at {} closes no open tag", tag, position)
+            }
+        }
+    }
+}
+
+struct OpenTag {
+    tag: MarkupTag,
+    position: usize,
+    children: Vec<MarkupNode>,
+}
+
+fn push_text(nodes: &mut Vec<MarkupNode>, s: &str) {
+    if let Some(MarkupNode::Text(text)) = nodes.last_mut() {
+        text.push_str(s);
+    } else {
+        nodes.push(MarkupNode::Text(s.to_string()));
+    }
+}
+
+//the name of the tag at the start of s and the tag's length, or None if s doesn't start with a tag
+fn read_tag(s: &str) -> Option<(bool, &str, usize)> {
+    let rest = s.strip_prefix('<')?;
+    let (closing, rest) = match rest.strip_prefix('/') {
+        Some(r) => (true, r),
+        None => (false, rest),
+    };
+    let name_len = rest
+        .find(|c: char| !c.is_ascii_alphanumeric())
+        .unwrap_or(rest.len());
+    if name_len == 0 || !rest[name_len..].starts_with('>') {
+        return None;
+    }
+    let len = 1 + usize::from(closing) + name_len + 1;
+    Some((closing, &rest[..name_len], len))
+}
+
+//parses as much as it can: anything which isn't a known tag is text and open tags are closed at
+//the end, so every string has a rendering. the errors say where the markup was repaired
+fn parse(s: &str) -> (Vec<MarkupNode>, Vec<MarkupError>) {
+    let mut errors = vec![];
+    let mut stack: Vec<OpenTag> = vec![];
+    let mut nodes = vec![];
+
+    fn close(stack: &mut Vec<OpenTag>, nodes: &mut Vec<MarkupNode>) {
+        if let Some(open) = stack.pop() {
+            let node = MarkupNode::Tagged {
+                tag: open.tag,
+                children: open.children,
+            };
+            match stack.last_mut() {
+                Some(parent) => parent.children.push(node),
+                None => nodes.push(node),
+            }
+        }
+    }
+
+    let mut position = 0;
+    while position < s.len() {
+        let rest = &s[position..];
+        let next = rest.find('<').unwrap_or(rest.len());
+        if next > 0 {
+            let target = match stack.last_mut() {
+                Some(open) => &mut open.children,
+                None => &mut nodes,
+            };
+            push_text(target, &rest[..next]);
+            position += next;
+            continue;
+        }
+
+        //a '<' which doesn't start a tag is text, as are unknown and stray tags
+        let mut literal_len = 1;
+        if let Some((closing, name, len)) = read_tag(rest) {
+            literal_len = len;
+            match MarkupTag::from_name(name) {
+                None => errors.push(MarkupError::UnknownTag {
+                    tag: name.to_string(),
+                    position,
+                }),
+                Some(tag) if !closing => {
+                    stack.push(OpenTag {
+                        tag,
+                        position,
+                        children: vec![],
+                    });
+                    position += len;
+                    continue;
+                }
+                Some(tag) if stack.iter().any(|open| open.tag == tag) => {
+                    //tags left open inside this one are closed with it
+                    while let Some(open) = stack.last() {
+                        let found = open.tag == tag;
+                        if !found {
+                            errors.push(MarkupError::UnclosedTag {
+                                tag: open.tag,
+                                position: open.position,
+                            });
+                        }
+                        close(&mut stack, &mut nodes);
+                        if found {
+                            break;
+                        }
+                    }
+                    position += len;
+                    continue;
+                }
+                Some(_) => errors.push(MarkupError::UnexpectedClosingTag {
+                    tag: name.to_string(),
+                    position,
+                }),
+            }
+        }
+        let target = match stack.last_mut() {
+            Some(open) => &mut open.children,
+            None => &mut nodes,
+        };
+        push_text(target, &rest[..literal_len]);
+        position += literal_len;
+    }
+
+    while let Some(open) = stack.last() {
+        errors.push(MarkupError::UnclosedTag {
+            tag: open.tag,
+            position: open.position,
+        });
+        close(&mut stack, &mut nodes);
+    }
+    (nodes, errors)
+}
+
+//fails on the first problem in the markup
+pub fn parse_markup(s: &str) -> Result<Vec<MarkupNode>, MarkupError> {
+    let (nodes, mut errors) = parse(s);
+    if errors.is_empty() {
+        Ok(nodes)
+    } else {
+        Err(errors.remove(0))
+    }
+}
+
+//never fails: unknown or stray tags are kept as text and unclosed tags end with the string
+pub fn parse_markup_lenient(s: &str) -> Vec<MarkupNode> {
+    parse(s).0
+}
+
+pub fn lint_markup(s: &str) -> Vec<MarkupError> {
+    parse(s).1
+}
+
+//renders the nodes for an output format: escape makes text safe and wrap surrounds the
+//rendering of a tag's children
+pub fn render_markup(
+    nodes: &[MarkupNode],
+    escape: &impl Fn(&str) -> String,
+    wrap: &impl Fn(MarkupTag, &str) -> String,
+) -> String {
+    let mut res = String::from("");
+    for node in nodes {
+        match node {
+            MarkupNode::Text(text) => res.push_str(&escape(text)),
+            MarkupNode::Tagged { tag, children } => {
+                res.push_str(&wrap(*tag, &render_markup(children, escape, wrap)))
+            }
+        }
+    }
+    res
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum MarkupField {
+    Lemma,
+    Def,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct MarkupIssue {
+    pub gloss_uuid: GlossUuid,
+    pub field: MarkupField,
+    pub error: MarkupError,
+}
+
+impl Glosses {
+    //checks the markup of every gloss's lemma and definition
+    pub fn lint_markup(&self) -> Vec<MarkupIssue> {
+        let mut issues = vec![];
+        for g in &self.gloss {
+            for (field, s) in [(MarkupField::Lemma, &g.lemma), (MarkupField::Def, &g.def)] {
+                for error in lint_markup(s) {
+                    issues.push(MarkupIssue {
+                        gloss_uuid: g.uuid,
+                        field,
+                        error,
+                    });
+                }
+            }
+        }
+        issues
+    }
+}
@@ -0,0 +1,528 @@
+use crate::exportconfig::AppCritStyle;
+use crate::pagemap::{PageContent, PageMap, TextPage};
+use crate::texts::AppCrit;
+use crate::{
+    ArrowedState, ArrowedWordsIndex, GlossOccurrance, GlossPageOptions, GlossUuid, Sequence, Word,
+    WordType, WordUuid, filter_and_sort_glosses, greek_collator, sanitize_greek,
+};
+use once_cell::sync::Lazy;
+use regex::Regex;
+use std::collections::HashMap;
+use unicode_normalization::UnicodeNormalization;
+
+//punctuation which follows the previous word without a space
+pub(crate) const NO_SPACE_BEFORE: [&str; 15] = [
+    ".", ",", "·", "·", ";", ";", ">", "]", ")", ",\"", ".”", ".\"", "·\"", "·\"", ".’",
+];
+
+//punctuation which the next word follows without a space
+pub(crate) fn is_no_space_after(word: &str) -> bool {
+    word == "<" || word == "[" || word == "("
+}
+
+static SECTION_RE: Lazy<Regex> = Lazy::new(|| Regex::new("([0-9]+)[.]([0-9]+)").unwrap());
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum Inline<'a> {
+    Word { word: &'a Word, space_before: bool },
+    //a section number: major for the first subsection of a section (or a number with no subsection),
+    //otherwise number is the subsection
+    Section { number: String, major: bool },
+    Speaker(&'a str),
+    AppCritMark(usize), //the number of the note on the word before
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum ParagraphStart {
+    Continued, //the page starts in the middle of a paragraph
+    Indented,
+    NotIndented,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct VerseLine<'a> {
+    pub number: String,
+    pub speaker: Option<&'a str>,
+    pub inlines: Vec<Inline<'a>>,
+}
+
+impl VerseLine<'_> {
+    //numeric line numbers are only shown every fifth line
+    pub fn display_number(&self) -> &str {
+        match self.number.parse::<i32>() {
+            Ok(i) if i % 5 != 0 => "",
+            _ => &self.number,
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum TextBlock<'a> {
+    WorkTitle(&'a str),
+    SectionTitle(&'a str),
+    Speaker(&'a str),
+    Paragraph {
+        start: ParagraphStart,
+        inlines: Vec<Inline<'a>>,
+    },
+    Verse(Vec<VerseLine<'a>>),
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct AppCritNote {
+    pub number: usize, //counting from 1 on each page
+    //the verse line or section the note is on, e.g. 5 or 1.2, or a range such as 5–7 when its
+    //span crosses lines
+    pub reference: String,
+    pub text: String,
+}
+
+impl AppCritNote {
+    pub fn label(&self, style: AppCritStyle) -> Option<String> {
+        match style {
+            AppCritStyle::AsWritten => None,
+            AppCritStyle::LineReferenced if !self.reference.is_empty() => {
+                Some(self.reference.clone())
+            }
+            AppCritStyle::LineReferenced | AppCritStyle::Numbered => Some(self.number.to_string()),
+        }
+    }
+}
+
+//the text of one page, independent of the output format
+#[derive(Clone, Debug, PartialEq)]
+pub struct PageText<'a> {
+    pub blocks: Vec<TextBlock<'a>>,
+    pub appcrits: Vec<AppCritNote>,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct GlossEntry<'a> {
+    pub occurrance: GlossOccurrance<'a>,
+    pub lemma: Option<String>,
+}
+
+impl<'a> GlossEntry<'a> {
+    pub fn new(occurrance: GlossOccurrance<'a>) -> GlossEntry<'a> {
+        GlossEntry {
+            lemma: occurrance.gloss.map(|g| sanitize_greek(&g.lemma)),
+            occurrance,
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct TextPageModel<'a> {
+    pub page_number: usize,
+    pub title: String,
+    pub text: PageText<'a>,
+    pub glosses: Vec<GlossEntry<'a>>,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum PageModel<'a> {
+    Blank { page_number: usize },
+    Text(TextPageModel<'a>),
+}
+
+//a gloss in the glossary at the back of the document
+#[derive(Clone, Debug, PartialEq)]
+pub struct GlossaryEntry {
+    pub lemma: String,
+    pub sort_key: String,
+    pub pos: String,
+    pub def: String,
+    pub total_count: usize,
+    pub arrowed_page: Option<usize>,
+}
+
+impl GlossaryEntry {
+    //how often the gloss occurs and the page it is arrowed on, e.g. 3×, → p. 12
+    pub fn frequency(&self) -> String {
+        match self.arrowed_page {
+            Some(page) => format!("{}×, → p. {}", self.total_count, page),
+            None => format!("{}×", self.total_count),
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct DocumentModel<'a> {
+    pub title: String,
+    pub start_page: usize,
+    pub pages: Vec<PageModel<'a>>,
+    pub index: Vec<ArrowedWordsIndex>, //sorted
+    pub glossary: Vec<GlossaryEntry>,  //sorted; empty unless GlossPageOptions::glossary
+}
+
+#[derive(Default)]
+struct PageTextBuilder<'a> {
+    blocks: Vec<TextBlock<'a>>,
+    paragraph: Option<(ParagraphStart, Vec<Inline<'a>>)>,
+    verse: Option<Vec<VerseLine<'a>>>,
+    line: Option<VerseLine<'a>>,
+    //once a page turns to verse, words are verse lines until the end of the page
+    in_verse: bool,
+    pending_speaker: Option<&'a str>,
+}
+
+impl<'a> PageTextBuilder<'a> {
+    fn close_paragraph(&mut self) {
+        if let Some((start, inlines)) = self.paragraph.take() {
+            self.blocks.push(TextBlock::Paragraph { start, inlines });
+        }
+    }
+
+    fn close_line(&mut self) {
+        if let Some(line) = self.line.take()
+            && !line.inlines.is_empty()
+        {
+            self.verse.get_or_insert_with(Vec::new).push(line);
+        }
+    }
+
+    fn close_verse(&mut self) {
+        self.close_line();
+        if let Some(lines) = self.verse.take()
+            && !lines.is_empty()
+        {
+            self.blocks.push(TextBlock::Verse(lines));
+        }
+    }
+
+    fn push_block(&mut self, block: TextBlock<'a>) {
+        self.close_paragraph();
+        self.close_verse();
+        self.blocks.push(block);
+    }
+
+    fn push_inline(&mut self, inline: Inline<'a>) {
+        if self.in_verse {
+            let speaker = &mut self.pending_speaker;
+            self.line
+                .get_or_insert_with(|| VerseLine {
+                    number: String::from(""),
+                    speaker: speaker.take(),
+                    inlines: vec![],
+                })
+                .inlines
+                .push(inline);
+        } else {
+            self.paragraph
+                .get_or_insert_with(|| (ParagraphStart::Continued, vec![]))
+                .1
+                .push(inline);
+        }
+    }
+
+    fn set_verse_speaker(&mut self, speaker: &'a str) {
+        match &mut self.line {
+            Some(line) => line.speaker = Some(speaker),
+            None => self.pending_speaker = Some(speaker),
+        }
+    }
+
+    fn finish(mut self) -> Vec<TextBlock<'a>> {
+        self.close_paragraph();
+        self.close_verse();
+        self.blocks
+    }
+}
+
+//the verse line or section number a word starts
+fn reference_of(w: &Word) -> Option<String> {
+    match w.word_type {
+        WordType::VerseLine => Some(w.word.replace("[line]", "")),
+        WordType::Section => Some(w.word.replace("[section]", "")),
+        _ => None,
+    }
+}
+
+//lays out the words of a page as blocks, following the structure the exporters all share
+pub fn make_page_text<'a>(
+    gloss_occurrances: &[GlossOccurrance<'a>],
+    appcrit_hash: &HashMap<WordUuid, AppCrit>,
+) -> PageText<'a> {
+    make_page_text_at(gloss_occurrances, appcrit_hash, String::from(""))
+}
+
+//as make_page_text, for a page which starts in the verse line or section reference
+fn make_page_text_at<'a>(
+    gloss_occurrances: &[GlossOccurrance<'a>],
+    appcrit_hash: &HashMap<WordUuid, AppCrit>,
+    mut reference: String,
+) -> PageText<'a> {
+    let mut builder = PageTextBuilder::default();
+    let mut appcrits: Vec<AppCritNote> = vec![];
+    //the notes whose spans haven't ended yet, by their last word
+    let mut open_spans: HashMap<WordUuid, usize> = HashMap::default();
+    let mut prev_non_space = true;
+
+    for go in gloss_occurrances {
+        let w: &'a Word = go.word;
+        if let Some(r) = reference_of(w) {
+            reference = r;
+        }
+        //the numbers of the notes which end on this word
+        let mut marks = vec![];
+        if let Some(ap) = appcrit_hash.get(&w.uuid) {
+            appcrits.push(AppCritNote {
+                number: appcrits.len() + 1,
+                reference: reference.clone(),
+                text: ap.text(),
+            });
+            match ap.end_word_uuid {
+                Some(end) if end != w.uuid => {
+                    open_spans.insert(end, appcrits.len() - 1);
+                }
+                _ => marks.push(appcrits.len()),
+            }
+        }
+        if let Some(i) = open_spans.remove(&w.uuid) {
+            if appcrits[i].reference != reference {
+                appcrits[i].reference = format!("{}–{}", appcrits[i].reference, reference);
+            }
+            marks.push(i + 1);
+        }
+        match w.word_type {
+            WordType::VerseLine => {
+                builder.close_paragraph();
+                builder.close_line();
+                builder.in_verse = true;
+                builder.line = Some(VerseLine {
+                    number: w.word.replace("[line]", ""),
+                    speaker: builder.pending_speaker.take(),
+                    inlines: vec![],
+                });
+            }
+            WordType::WorkTitle => builder.push_block(TextBlock::WorkTitle(&w.word)),
+            WordType::SectionTitle => builder.push_block(TextBlock::SectionTitle(&w.word)),
+            WordType::Speaker => builder.push_block(TextBlock::Speaker(&w.word)),
+            WordType::Word | WordType::Punctuation => {
+                let space_before = !(NO_SPACE_BEFORE.contains(&w.word.as_str()) || prev_non_space);
+                builder.push_inline(Inline::Word {
+                    word: w,
+                    space_before,
+                });
+                for number in marks {
+                    builder.push_inline(Inline::AppCritMark(number));
+                }
+                prev_non_space = is_no_space_after(&w.word);
+            }
+            WordType::ParaWithIndent | WordType::ParaNoIndent if !builder.in_verse => {
+                builder.close_paragraph();
+                let start = if w.word_type == WordType::ParaWithIndent {
+                    ParagraphStart::Indented
+                } else {
+                    ParagraphStart::NotIndented
+                };
+                builder.paragraph = Some((start, vec![]));
+            }
+            WordType::Section => {
+                let section_input = w.word.replace("[section]", "");
+                let (number, major) = match SECTION_RE.captures(&section_input) {
+                    Some(matches) if &matches[2] == "1" => (matches[1].to_string(), true),
+                    Some(matches) => (matches[2].to_string(), false),
+                    None => (section_input.clone(), true),
+                };
+                builder.push_inline(Inline::Section { number, major });
+                prev_non_space = true;
+            }
+            WordType::InlineSpeaker if !builder.in_verse => {
+                builder.push_inline(Inline::Speaker(&w.word))
+            }
+            WordType::InlineSpeaker | WordType::InlineVerseSpeaker => {
+                builder.set_verse_speaker(&w.word)
+            }
+            _ => (),
+        }
+    }
+
+    PageText {
+        blocks: builder.finish(),
+        appcrits,
+    }
+}
+
+fn make_page_model<'a>(
+    gloss_occurrances: &[GlossOccurrance<'a>],
+    appcrit_hash: &HashMap<WordUuid, AppCrit>,
+    reference: String,
+    title: &str,
+    arrowed_words_index: &mut Vec<ArrowedWordsIndex>,
+    page_number: usize,
+    options: &GlossPageOptions,
+) -> TextPageModel<'a> {
+    let glosses =
+        filter_and_sort_glosses(gloss_occurrances, arrowed_words_index, page_number, options)
+            .into_iter()
+            .map(GlossEntry::new)
+            .collect();
+    TextPageModel {
+        page_number,
+        title: title.to_string(),
+        text: make_page_text_at(gloss_occurrances, appcrit_hash, reference),
+        glosses,
+    }
+}
+
+//sorts the index of arrowed words by lemma
+pub(crate) fn sort_index(index: &mut [ArrowedWordsIndex]) {
+    let collator = greek_collator();
+    index.sort_by(|a, b| collator.as_borrowed().compare(&a.gloss_sort, &b.gloss_sort));
+}
+
+//the letter a lemma is listed under in the index: its first letter without accents or breathings
+pub(crate) fn index_letter(lemma: &str) -> char {
+    lemma
+        .nfd()
+        .find(|c| c.is_alphabetic())
+        .and_then(|c| c.to_uppercase().next())
+        .unwrap_or('#')
+}
+
+impl Sequence {
+    //every gloss used on the pages of the displayed texts, sorted with the Greek collator
+    pub(crate) fn make_glossary(
+        &self,
+        gloss_occurrances: &[Vec<GlossOccurrance>],
+        map: &PageMap,
+    ) -> Vec<GlossaryEntry> {
+        let mut entries: HashMap<GlossUuid, GlossaryEntry> = HashMap::default();
+        for page in &map.pages {
+            let PageContent::Text(text_page) = &page.content else {
+                continue;
+            };
+            for go in &gloss_occurrances[text_page.text_index][text_page.words.clone()] {
+                let Some(gloss) = go.gloss else {
+                    continue;
+                };
+                let entry = entries.entry(gloss.uuid).or_insert_with(|| GlossaryEntry {
+                    lemma: sanitize_greek(&gloss.lemma),
+                    sort_key: gloss.sort_key.clone(),
+                    pos: gloss.pos.clone(),
+                    def: gloss.def.clone(),
+                    total_count: go.total_count.unwrap_or(0),
+                    arrowed_page: None,
+                });
+                if go.arrowed_state == ArrowedState::Arrowed {
+                    entry.arrowed_page = Some(page.page_number);
+                }
+            }
+        }
+
+        let collator = greek_collator();
+        let mut glossary: Vec<GlossaryEntry> = entries.into_values().collect();
+        glossary.sort_by(|a, b| {
+            collator
+                .as_borrowed()
+                .compare(&a.sort_key, &b.sort_key)
+                .then_with(|| a.lemma.cmp(&b.lemma))
+        });
+        glossary
+    }
+
+    pub(crate) fn make_appcrit_hash(&self) -> HashMap<WordUuid, AppCrit> {
+        let mut appcrit_hash = HashMap::default();
+        for t in &self.texts {
+            if let Some(appcrits) = &t.appcrits {
+                for ap in appcrits {
+                    appcrit_hash.insert(ap.word_uuid, ap.clone());
+                }
+            }
+        }
+        appcrit_hash
+    }
+
+    //the model of a page of text in the page map, adding its arrowed words to index
+    pub(crate) fn make_text_page_model<'a>(
+        &self,
+        gloss_occurrances: &[Vec<GlossOccurrance<'a>>],
+        text_page: &TextPage,
+        page_number: usize,
+        appcrit_hash: &HashMap<WordUuid, AppCrit>,
+        index: &mut Vec<ArrowedWordsIndex>,
+        options: &GlossPageOptions,
+    ) -> TextPageModel<'a> {
+        self.make_text_page_model_from(
+            &gloss_occurrances[text_page.text_index][text_page.words.clone()],
+            text_page,
+            page_number,
+            appcrit_hash,
+            index,
+            options,
+        )
+    }
+
+    //as make_text_page_model, from the occurrances of the page's words alone, e.g. from process_range
+    pub(crate) fn make_text_page_model_from<'a>(
+        &self,
+        page_occurrances: &[GlossOccurrance<'a>],
+        text_page: &TextPage,
+        page_number: usize,
+        appcrit_hash: &HashMap<WordUuid, AppCrit>,
+        index: &mut Vec<ArrowedWordsIndex>,
+        options: &GlossPageOptions,
+    ) -> TextPageModel<'a> {
+        //the line or section the page starts in
+        let reference = self.texts[text_page.text_index].words[..text_page.words.start]
+            .iter()
+            .rev()
+            .find_map(reference_of)
+            .unwrap_or_default();
+        make_page_model(
+            page_occurrances,
+            appcrit_hash,
+            reference,
+            if text_page.page_in_text == 0 {
+                ""
+            } else {
+                &self.texts[text_page.text_index].text_name
+            },
+            index,
+            page_number,
+            options,
+        )
+    }
+
+    //the whole document as make_document renders it
+    pub fn make_document_model<'a>(
+        &self,
+        gloss_occurrances: &[Vec<GlossOccurrance<'a>>],
+        options: &GlossPageOptions,
+    ) -> DocumentModel<'a> {
+        let appcrit_hash = self.make_appcrit_hash();
+        let map = self.make_page_map();
+        let mut index = vec![];
+        let mut pages = vec![];
+        for page in &map.pages {
+            pages.push(match &page.content {
+                PageContent::Blank => PageModel::Blank {
+                    page_number: page.page_number,
+                },
+                PageContent::Text(text_page) => PageModel::Text(self.make_text_page_model(
+                    gloss_occurrances,
+                    text_page,
+                    page.page_number,
+                    &appcrit_hash,
+                    &mut index,
+                    options,
+                )),
+            });
+        }
+        sort_index(&mut index);
+        let glossary = if options.glossary {
+            self.make_glossary(gloss_occurrances, &map)
+        } else {
+            vec![]
+        };
+
+        DocumentModel {
+            title: self.sequence_description.name.clone(),
+            start_page: self.sequence_description.start_page,
+            pages,
+            index,
+            glossary,
+        }
+    }
+}
@@ -0,0 +1,172 @@
+use crate::{Sequence, TextDescription};
+use std::fmt;
+use std::ops::Range;
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum PageCountIssue {
+    //words_per_page is empty so the text has no pages
+    NoCounts,
+    Unparsable {
+        entry_index: usize,
+        entry: String,
+    },
+    EmptyPage {
+        page_index: usize,
+        word_index: usize,
+    },
+    //the counts end before the text does: word_index is the first word not on any page
+    TooFewWords {
+        word_index: usize,
+        missing: usize,
+    },
+    //page_index runs past the end of the text, which has word_index words
+    TooManyWords {
+        page_index: usize,
+        word_index: usize,
+        excess: usize,
+    },
+}
+
+impl fmt::Display for PageCountIssue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PageCountIssue::NoCounts => write!(f, "no words per page given"),
+            PageCountIssue::Unparsable { entry_index, entry } => {
+                write!(f, "entry {} ({:?}) is not a number", entry_index, entry)
+            }
+            PageCountIssue::EmptyPage {
+                page_index,
+                word_index,
+            } => write!(f, "page {} at word {} has no words", page_index, word_index),
+            PageCountIssue::TooFewWords {
+                word_index,
+                missing,
+            } => write!(
+                f,
+                "counts end at word {}, {} word(s) before the end of the text",
+                word_index, missing
+            ),
+            PageCountIssue::TooManyWords {
+                page_index,
+                word_index,
+                excess,
+            } => write!(
+                f,
+                "page {} runs {} word(s) past the end of the text at word {}",
+                page_index, excess, word_index
+            ),
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct PageCountReport {
+    pub text_index: usize,
+    pub text_name: String,
+    pub word_count: usize,
+    pub issues: Vec<PageCountIssue>,
+}
+
+//the one rule for dividing a text into pages, which make_page_map and check_page_breaks both
+//follow: each count but the last gives the next page, cut short at the end of the text, and the
+//last page takes the rest of the text, whatever its count. pages left without words are dropped.
+//validate_page_counts reports the counts this has to make up for
+pub fn page_ranges(counts: &[usize], word_count: usize) -> Vec<Range<usize>> {
+    let mut ranges = vec![];
+    let mut start = 0;
+    for (i, count) in counts.iter().enumerate() {
+        let end = if i == counts.len() - 1 {
+            word_count
+        } else {
+            (start + count).min(word_count)
+        };
+        if end > start {
+            ranges.push(start..end);
+        }
+        start = end;
+    }
+    ranges
+}
+
+impl TextDescription {
+    //the counts in words_per_page, leaving out entries which are not numbers
+    pub fn page_counts(&self) -> Vec<usize> {
+        self.words_per_page
+            .split(',')
+            .filter_map(|s| s.trim().parse::<usize>().ok())
+            .collect()
+    }
+
+    //checks that words_per_page divides the text's word_count words exactly into non-empty pages
+    pub fn validate_page_counts(&self, word_count: usize) -> Vec<PageCountIssue> {
+        let mut issues = vec![];
+        if self.words_per_page.trim().is_empty() {
+            issues.push(PageCountIssue::NoCounts);
+            return issues;
+        }
+
+        let mut counts = vec![];
+        for (entry_index, entry) in self.words_per_page.split(',').enumerate() {
+            match entry.trim().parse::<usize>() {
+                Ok(count) => counts.push(count),
+                Err(_) => issues.push(PageCountIssue::Unparsable {
+                    entry_index,
+                    entry: entry.to_string(),
+                }),
+            }
+        }
+
+        let mut word_index = 0;
+        for (page_index, count) in counts.iter().enumerate() {
+            if *count == 0 {
+                issues.push(PageCountIssue::EmptyPage {
+                    page_index,
+                    word_index,
+                });
+            }
+            if word_index + count > word_count {
+                issues.push(PageCountIssue::TooManyWords {
+                    page_index,
+                    word_index: word_count,
+                    excess: word_index + count - word_count,
+                });
+                return issues;
+            }
+            word_index += count;
+        }
+        if word_index < word_count {
+            issues.push(PageCountIssue::TooFewWords {
+                word_index,
+                missing: word_count - word_index,
+            });
+        }
+        issues
+    }
+}
+
+impl Sequence {
+    //reports each displayed text whose words_per_page has problems
+    pub fn validate_words_per_page(&self) -> Vec<PageCountReport> {
+        let mut reports = vec![];
+        for (text_index, (t, desc)) in self
+            .texts
+            .iter()
+            .zip(&self.sequence_description.texts)
+            .enumerate()
+        {
+            if !desc.display {
+                continue;
+            }
+            let issues = desc.validate_page_counts(t.words.len());
+            if !issues.is_empty() {
+                reports.push(PageCountReport {
+                    text_index,
+                    text_name: t.text_name.clone(),
+                    word_count: t.words.len(),
+                    issues,
+                });
+            }
+        }
+        reports
+    }
+}
@@ -0,0 +1,108 @@
+use crate::pagecounts::page_ranges;
+use crate::{Sequence, WordUuid};
+use std::collections::HashMap;
+use std::ops::Range;
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct TextPage {
+    pub text_index: usize,
+    pub page_in_text: usize, //0 for the first page of the text
+    pub words: Range<usize>, //indices into the text's words
+    pub first_word: WordUuid,
+    pub last_word: WordUuid,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum PageContent {
+    Blank,
+    Text(TextPage),
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct MappedPage {
+    pub page_number: usize,
+    pub content: PageContent,
+}
+
+//every page of the document in order, including the blank pages inserted so texts start on odd pages
+#[derive(Clone, Debug, PartialEq)]
+pub struct PageMap {
+    pub pages: Vec<MappedPage>,
+    word_pages: HashMap<WordUuid, usize>, //word to page number
+}
+
+impl PageMap {
+    pub fn page(&self, page_number: usize) -> Option<&MappedPage> {
+        self.pages.iter().find(|p| p.page_number == page_number)
+    }
+
+    //the number of the page the word is printed on
+    pub fn page_of_word(&self, word_uuid: WordUuid) -> Option<usize> {
+        self.word_pages.get(&word_uuid).copied()
+    }
+
+    pub fn text_pages(&self, text_index: usize) -> impl Iterator<Item = (usize, &TextPage)> {
+        self.pages.iter().filter_map(move |p| match &p.content {
+            PageContent::Text(t) if t.text_index == text_index => Some((p.page_number, t)),
+            _ => None,
+        })
+    }
+
+    fn push(&mut self, page_number: &mut usize, content: PageContent) {
+        self.pages.push(MappedPage {
+            page_number: *page_number,
+            content,
+        });
+        *page_number += 1;
+    }
+}
+
+impl Sequence {
+    //lays out the pages as make_document prints them: a blank page first if the sequence starts
+    //on an even page, then each displayed text followed by a blank page to make the next text
+    //start on an odd page and one more blank page.
+    //each text's pages come from its words_per_page, divided as page_ranges describes
+    pub fn make_page_map(&self) -> PageMap {
+        let mut map = PageMap {
+            pages: vec![],
+            word_pages: HashMap::default(),
+        };
+        let mut page_number = self.sequence_description.start_page;
+
+        if page_number.is_multiple_of(2) {
+            map.push(&mut page_number, PageContent::Blank);
+        }
+        for (text_index, t) in self.texts.iter().enumerate() {
+            let Some(desc) = self.sequence_description.texts.get(text_index) else {
+                continue;
+            };
+            if !desc.display {
+                continue;
+            }
+            for (page_in_text, words) in page_ranges(&desc.page_counts(), t.words.len())
+                .into_iter()
+                .enumerate()
+            {
+                for w in &t.words[words.clone()] {
+                    map.word_pages.insert(w.uuid, page_number);
+                }
+                map.push(
+                    &mut page_number,
+                    PageContent::Text(TextPage {
+                        text_index,
+                        page_in_text,
+                        first_word: t.words[words.start].uuid,
+                        last_word: t.words[words.end - 1].uuid,
+                        words,
+                    }),
+                );
+            }
+
+            if !page_number.is_multiple_of(2) {
+                map.push(&mut page_number, PageContent::Blank);
+            }
+            map.push(&mut page_number, PageContent::Blank);
+        }
+        map
+    }
+}
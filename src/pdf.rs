@@ -0,0 +1,163 @@
+use crate::{ExportTypst, GlossOccurrance, GlossPageOptions, GlosserError, Sequence};
+use std::path::Path;
+use typst::diag::{FileError, FileResult, PackageError};
+use typst::foundations::{Bytes, Datetime, Smart};
+use typst::layout::PagedDocument;
+use typst::syntax::{FileId, Source, VirtualPath};
+use typst::text::{Font, FontBook};
+use typst::utils::LazyHash;
+use typst::{Library, World};
+use typst_pdf::{PdfOptions, Timestamp};
+
+//the fonts the exported Typst uses, bundled so nothing needs to be installed; typst's own fonts
+//are the fallbacks, as with `typst compile`
+const FONTS: [&[u8]; 2] = [
+    include_bytes!("../IFAOGrec.ttf"),
+    include_bytes!("../IFAOGrecBold.ttf"),
+];
+
+//the files of the packages vendored in typst-packages/, embedded by build.rs so nothing is
+//downloaded: (namespace, name, version, path in the package, contents)
+const PACKAGES: &[(&str, &str, &str, &str, &[u8])] =
+    include!(concat!(env!("OUT_DIR"), "/typst_packages.rs"));
+
+//a yyyy-mm-dd date, as in ExportConfig's date
+fn parse_date(date: &str) -> Option<Datetime> {
+    let mut parts = date.trim().splitn(3, '-');
+    Datetime::from_ymd(
+        parts.next()?.parse().ok()?,
+        parts.next()?.parse().ok()?,
+        parts.next()?.parse().ok()?,
+    )
+}
+
+//the compiler's view of one document: the generated source as main.typ, the bundled fonts and
+//the embedded packages
+struct PdfWorld {
+    library: LazyHash<Library>,
+    book: LazyHash<FontBook>,
+    fonts: Vec<Font>,
+    main: Source,
+    date: Option<Datetime>,
+}
+
+impl PdfWorld {
+    fn new(source: &str, date: Option<Datetime>) -> PdfWorld {
+        let fonts: Vec<Font> = FONTS
+            .into_iter()
+            .chain(typst_assets::fonts())
+            .flat_map(|data| Font::iter(Bytes::new(data)))
+            .collect();
+        PdfWorld {
+            library: LazyHash::new(Library::builder().build()),
+            book: LazyHash::new(FontBook::from_fonts(&fonts)),
+            fonts,
+            main: Source::new(
+                FileId::new(None, VirtualPath::new("main.typ")),
+                source.to_string(),
+            ),
+            date,
+        }
+    }
+
+    //a file of an embedded package; the document itself has no other files
+    fn read(&self, id: FileId) -> FileResult<&'static [u8]> {
+        let Some(package) = id.package() else {
+            return Err(FileError::NotFound(id.vpath().as_rootless_path().into()));
+        };
+        let version = package.version.to_string();
+        let mut files = PACKAGES
+            .iter()
+            .filter(|(namespace, name, v, _, _)| {
+                package.namespace == *namespace && package.name == *name && *v == version
+            })
+            .peekable();
+        if files.peek().is_none() {
+            return Err(FileError::Package(PackageError::NotFound(package.clone())));
+        }
+        let path = id.vpath().as_rootless_path();
+        files
+            .find(|(_, _, _, file, _)| path == Path::new(file))
+            .map(|(_, _, _, _, contents)| *contents)
+            .ok_or_else(|| FileError::NotFound(path.into()))
+    }
+}
+
+impl World for PdfWorld {
+    fn library(&self) -> &LazyHash<Library> {
+        &self.library
+    }
+
+    fn book(&self) -> &LazyHash<FontBook> {
+        &self.book
+    }
+
+    fn main(&self) -> FileId {
+        self.main.id()
+    }
+
+    fn source(&self, id: FileId) -> FileResult<Source> {
+        if id == self.main.id() {
+            return Ok(self.main.clone());
+        }
+        let contents = std::str::from_utf8(self.read(id)?).map_err(|_| FileError::InvalidUtf8)?;
+        Ok(Source::new(id, contents.to_string()))
+    }
+
+    fn file(&self, id: FileId) -> FileResult<Bytes> {
+        self.read(id).map(Bytes::new)
+    }
+
+    fn font(&self, index: usize) -> Option<Font> {
+        self.fonts.get(index).cloned()
+    }
+
+    //the given date rather than the clock, so the same input always gives the same pdf
+    fn today(&self, _offset: Option<i64>) -> Option<Datetime> {
+        self.date
+    }
+}
+
+fn typst_error(errors: &[typst::diag::SourceDiagnostic]) -> GlosserError {
+    GlosserError::Other(format!(
+        "Typst error: {}",
+        errors
+            .iter()
+            .map(|e| e.message.as_str())
+            .collect::<Vec<_>>()
+            .join("; ")
+    ))
+}
+
+//compiles Typst source, e.g. a document from ExportTypst, to the bytes of a pdf. date
+//(yyyy-mm-dd) is both Typst's today and the pdf's creation date; without one the pdf has none
+pub fn compile_pdf(source: &str, date: Option<&str>) -> Result<Vec<u8>, GlosserError> {
+    let date = date.and_then(parse_date);
+    let world = PdfWorld::new(source, date);
+    let document = typst::compile::<PagedDocument>(&world)
+        .output
+        .map_err(|errors| typst_error(&errors))?;
+    let options = PdfOptions {
+        ident: Smart::Auto,
+        timestamp: date.map(Timestamp::new_utc),
+        ..Default::default()
+    };
+    typst_pdf::pdf(&document, &options).map_err(|errors| typst_error(&errors))
+}
+
+impl Sequence {
+    //the whole document as a pdf, without running the typst command line; dated with the
+    //export's configured date
+    pub fn make_pdf(
+        &self,
+        gloss_occurrances: &[Vec<GlossOccurrance>],
+        export: &ExportTypst,
+        options: &GlossPageOptions,
+    ) -> Result<Vec<u8>, GlosserError> {
+        export.validate_template()?;
+        compile_pdf(
+            &self.make_document(gloss_occurrances, export, options),
+            export.config.date.as_deref(),
+        )
+    }
+}
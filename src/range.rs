@@ -0,0 +1,114 @@
+use crate::{
+    Gloss, GlossOccurrance, GlossSeqCount, GlossUuid, GlosserError, Sequence, WordUuid,
+    next_gloss_occurrance,
+};
+use std::collections::HashMap;
+use std::ops::Range;
+
+//the running gloss counts and arrow positions at the start of each text,
+//so that a slice of one text can be processed without processing everything before it.
+//it must be rebuilt whenever the sequence's glosses, texts or arrows change.
+pub struct ProcessSummary<'a> {
+    text_starts: Vec<usize>, //sequence index of the first word of each text
+    before_text: Vec<HashMap<GlossUuid, GlossSeqCount>>,
+    total_counts: HashMap<GlossUuid, usize>,
+    //kept so each range doesn't have to rebuild them
+    glosses_hash: HashMap<GlossUuid, &'a Gloss>,
+    arrowed_words_hash: HashMap<WordUuid, GlossUuid>,
+}
+
+impl Sequence {
+    pub fn make_process_summary(&self) -> Result<ProcessSummary<'_>, GlosserError> {
+        if self.texts.is_empty() || self.glosses.is_empty() {
+            return Err(GlosserError::NotFound(String::from(
+                "Gloss or texts not found",
+            )));
+        }
+        let glosses_hash = self.make_glosses_hash();
+        let arrowed_words_hash = self.make_arrowed_words_hash();
+
+        if self.verify(&arrowed_words_hash, &glosses_hash).is_err() {
+            return Err(GlosserError::InvalidInput(String::from(
+                "Invalid input: Has errors",
+            )));
+        }
+
+        let mut gloss_seq_count: HashMap<GlossUuid, GlossSeqCount> = HashMap::default();
+        let mut text_starts = vec![];
+        let mut before_text = vec![];
+        let mut i = 0;
+        for t in &self.texts {
+            text_starts.push(i);
+            before_text.push(gloss_seq_count.clone());
+            for w in &t.words {
+                next_gloss_occurrance(
+                    w,
+                    i,
+                    &glosses_hash,
+                    &arrowed_words_hash,
+                    &mut gloss_seq_count,
+                );
+                i += 1;
+            }
+        }
+
+        let total_counts = gloss_seq_count
+            .iter()
+            .map(|(gloss_uuid, gsc)| (*gloss_uuid, gsc.count))
+            .collect();
+
+        Ok(ProcessSummary {
+            text_starts,
+            before_text,
+            total_counts,
+            glosses_hash,
+            arrowed_words_hash,
+        })
+    }
+
+    //the same occurrances process() returns for self.texts[text_index][word_range]
+    pub fn process_range<'a>(
+        &'a self,
+        summary: &ProcessSummary<'a>,
+        text_index: usize,
+        word_range: Range<usize>,
+    ) -> Result<Vec<GlossOccurrance<'a>>, GlosserError> {
+        let (Some(text), Some(before)) = (
+            self.texts.get(text_index),
+            summary.before_text.get(text_index),
+        ) else {
+            return Err(GlosserError::InvalidInput(format!(
+                "text index {} out of range",
+                text_index
+            )));
+        };
+        if word_range.start > word_range.end || word_range.end > text.words.len() {
+            return Err(GlosserError::InvalidInput(format!(
+                "word range {:?} out of range for text {} with {} words",
+                word_range,
+                text_index,
+                text.words.len()
+            )));
+        }
+
+        //only the words of this text before the range still need to be counted
+        let mut gloss_seq_count = before.clone();
+        let mut res = vec![];
+        for (idx, w) in text.words[..word_range.end].iter().enumerate() {
+            let mut go = next_gloss_occurrance(
+                w,
+                summary.text_starts[text_index] + idx,
+                &summary.glosses_hash,
+                &summary.arrowed_words_hash,
+                &mut gloss_seq_count,
+            );
+            if idx >= word_range.start {
+                if let Some(g) = go.gloss {
+                    go.total_count = summary.total_counts.get(&g.uuid).copied();
+                }
+                res.push(go);
+            }
+        }
+        Ok(res)
+    }
+}
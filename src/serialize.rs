@@ -0,0 +1,26 @@
+use crate::{GlosserError, Sequence};
+
+//the whole sequence (description, glosses and texts) in one document, for clients like the
+//js editor which would otherwise have to fetch and parse each xml file separately.
+//both forms hold exactly what the xml files hold, so they round-trip through either
+impl Sequence {
+    pub fn to_json(&self) -> Result<String, GlosserError> {
+        serde_json::to_string(self).map_err(|e| GlosserError::Other(format!("JSON error: {}", e)))
+    }
+
+    pub fn from_json(s: &str) -> Result<Sequence, GlosserError> {
+        serde_json::from_str(s)
+            .map_err(|e| GlosserError::InvalidInput(format!("JSON error: {}", e)))
+    }
+
+    //a compact binary form (postcard) for caching or sending a sequence over the wire
+    pub fn to_binary(&self) -> Result<Vec<u8>, GlosserError> {
+        postcard::to_allocvec(self)
+            .map_err(|e| GlosserError::Other(format!("Binary encoding error: {}", e)))
+    }
+
+    pub fn from_binary(data: &[u8]) -> Result<Sequence, GlosserError> {
+        postcard::from_bytes(data)
+            .map_err(|e| GlosserError::InvalidInput(format!("Binary decoding error: {}", e)))
+    }
+}
@@ -0,0 +1,475 @@
+use crate::texts::{AppCrit, Text, WordType};
+use crate::{GlossUuid, GlosserError, Sequence, WordUuid, greek_collator};
+use quick_xml::events::{BytesEnd, BytesStart, BytesText, Event};
+use quick_xml::writer::Writer;
+use std::collections::{HashMap, HashSet};
+use std::io::Cursor;
+
+const TEI_NS: &str = "http://www.tei-c.org/ns/1.0";
+
+//xml:ids can't start with a digit so uuids get a prefix
+fn word_id(uuid: &WordUuid) -> String {
+    format!("w-{}", uuid)
+}
+
+fn gloss_id(uuid: &GlossUuid) -> String {
+    format!("gloss-{}", uuid)
+}
+
+//sigla can have spaces or dots, e.g. "Vat. gr. 1", which an xml:id can't
+fn witness_id(sigil: &str) -> String {
+    let id: String = sigil
+        .chars()
+        .map(|c| {
+            if c.is_alphanumeric() || c == '-' || c == '_' {
+                c
+            } else {
+                '_'
+            }
+        })
+        .collect();
+    format!("wit-{}", id)
+}
+
+//the sigla the texts' appcrits cite, in the order they first appear
+fn witnesses<'a>(texts: impl Iterator<Item = &'a Text>) -> Vec<&'a str> {
+    let mut sigla: Vec<&str> = vec![];
+    for a in texts.flat_map(|t| t.appcrits.iter().flatten()) {
+        for w in &a.witnesses {
+            if !sigla.contains(&w.as_str()) {
+                sigla.push(w);
+            }
+        }
+    }
+    sigla
+}
+
+//writes the body of a text, keeping track of which elements are open:
+//a div for each title, an sp for each speaker and a p or l holding the words
+struct TeiBody<'w> {
+    writer: &'w mut Writer<Cursor<Vec<u8>>>,
+    div_open: bool,
+    sp_open: bool,
+    block: Option<&'static str>,
+    appcrits: HashMap<WordUuid, &'w AppCrit>,
+    app_open: Option<&'w AppCrit>, //the note whose lemma is being written
+    page_breaks: HashMap<WordUuid, usize>, //page numbers of the first words of pages
+    glossary_in_document: bool,
+}
+
+impl TeiBody<'_> {
+    fn start(&mut self, name: &str, attributes: &[(&str, &str)]) -> std::io::Result<()> {
+        let mut e = BytesStart::new(name);
+        for a in attributes {
+            e.push_attribute(*a);
+        }
+        self.writer.write_event(Event::Start(e))
+    }
+
+    fn end(&mut self, name: &str) -> std::io::Result<()> {
+        self.writer.write_event(Event::End(BytesEnd::new(name)))
+    }
+
+    fn element(
+        &mut self,
+        name: &str,
+        attributes: &[(&str, &str)],
+        text: &str,
+    ) -> std::io::Result<()> {
+        let mut e = self.writer.create_element(name);
+        for a in attributes {
+            e = e.with_attribute(*a);
+        }
+        e.write_text_content(BytesText::new(text))?;
+        Ok(())
+    }
+
+    fn empty(&mut self, name: &str, attributes: &[(&str, &str)]) -> std::io::Result<()> {
+        let mut e = self.writer.create_element(name);
+        for a in attributes {
+            e = e.with_attribute(*a);
+        }
+        e.write_empty()?;
+        Ok(())
+    }
+
+    //a lemma ends at the end of its span or, since an app can't cross one, of the block
+    fn close_app(&mut self) -> std::io::Result<()> {
+        let Some(appcrit) = self.app_open.take() else {
+            return Ok(());
+        };
+        self.end("lem")?;
+        if !appcrit.reading.is_empty() || !appcrit.witnesses.is_empty() {
+            let wit = appcrit
+                .witnesses
+                .iter()
+                .map(|w| format!("#{}", witness_id(w)))
+                .collect::<Vec<_>>()
+                .join(" ");
+            if wit.is_empty() {
+                self.element("rdg", &[], &appcrit.reading)?;
+            } else {
+                self.element("rdg", &[("wit", wit.as_str())], &appcrit.reading)?;
+            }
+        }
+        if !appcrit.entry.is_empty() {
+            self.element("note", &[("type", "appcrit")], &appcrit.entry)?;
+        }
+        self.end("app")
+    }
+
+    fn close_block(&mut self) -> std::io::Result<()> {
+        self.close_app()?;
+        if let Some(block) = self.block.take() {
+            self.end(block)?;
+        }
+        Ok(())
+    }
+
+    fn close_sp(&mut self) -> std::io::Result<()> {
+        self.close_block()?;
+        if self.sp_open {
+            self.end("sp")?;
+            self.sp_open = false;
+        }
+        Ok(())
+    }
+
+    fn close_div(&mut self) -> std::io::Result<()> {
+        self.close_sp()?;
+        if self.div_open {
+            self.end("div")?;
+            self.div_open = false;
+        }
+        Ok(())
+    }
+
+    fn ensure_div(&mut self) -> std::io::Result<()> {
+        if !self.div_open {
+            self.start("div", &[("type", "textpart")])?;
+            self.div_open = true;
+        }
+        Ok(())
+    }
+
+    fn open_block(
+        &mut self,
+        name: &'static str,
+        attributes: &[(&str, &str)],
+    ) -> std::io::Result<()> {
+        self.close_block()?;
+        self.ensure_div()?;
+        self.start(name, attributes)?;
+        self.block = Some(name);
+        Ok(())
+    }
+
+    //words outside a paragraph or verse line go in a paragraph of their own
+    fn ensure_block(&mut self) -> std::io::Result<()> {
+        if self.block.is_none() {
+            self.open_block("p", &[])?;
+        }
+        Ok(())
+    }
+
+    fn write_text(&mut self, text: &Text) -> std::io::Result<()> {
+        for w in &text.words {
+            if let Some(page_number) = self.page_breaks.get(&w.uuid) {
+                let n = page_number.to_string();
+                self.ensure_div()?;
+                self.empty("pb", &[("n", n.as_str())])?;
+            }
+            match w.word_type {
+                WordType::Word | WordType::Punctuation => {
+                    self.ensure_block()?;
+                    if let Some(appcrit) = self.appcrits.get(&w.uuid).copied() {
+                        self.close_app()?;
+                        self.start("app", &[])?;
+                        self.start("lem", &[])?;
+                        self.app_open = Some(appcrit);
+                    }
+                    let id = word_id(&w.uuid);
+                    if w.word_type == WordType::Punctuation {
+                        self.element("pc", &[("xml:id", id.as_str())], &w.word)?;
+                    } else if let Some(gloss_uuid) = w.gloss_uuid {
+                        let lemma_ref = if self.glossary_in_document {
+                            format!("#{}", gloss_id(&gloss_uuid))
+                        } else {
+                            format!("urn:uuid:{}", gloss_uuid)
+                        };
+                        self.element(
+                            "w",
+                            &[("xml:id", id.as_str()), ("lemmaRef", lemma_ref.as_str())],
+                            &w.word,
+                        )?;
+                    } else {
+                        self.element("w", &[("xml:id", id.as_str())], &w.word)?;
+                    }
+                    if self
+                        .app_open
+                        .is_some_and(|a| a.end_word_uuid.unwrap_or(a.word_uuid) == w.uuid)
+                    {
+                        self.close_app()?;
+                    }
+                }
+                WordType::VerseLine => {
+                    let n = w.word.replace("[line]", "");
+                    self.open_block("l", &[("n", n.as_str())])?;
+                }
+                WordType::ParaWithIndent => self.open_block("p", &[("rend", "indent")])?,
+                WordType::ParaNoIndent => self.open_block("p", &[("rend", "noindent")])?,
+                WordType::Section => {
+                    let n = w.word.replace("[section]", "");
+                    self.ensure_div()?;
+                    self.empty("milestone", &[("unit", "section"), ("n", n.as_str())])?;
+                }
+                WordType::WorkTitle | WordType::SectionTitle => {
+                    self.close_div()?;
+                    let div_type = if w.word_type == WordType::WorkTitle {
+                        "work"
+                    } else {
+                        "section"
+                    };
+                    self.start("div", &[("type", div_type)])?;
+                    self.div_open = true;
+                    self.element("head", &[], &w.word)?;
+                }
+                WordType::Speaker => {
+                    self.close_sp()?;
+                    self.ensure_div()?;
+                    self.start("sp", &[])?;
+                    self.sp_open = true;
+                    self.element("speaker", &[], &w.word)?;
+                }
+                WordType::InlineSpeaker | WordType::InlineVerseSpeaker => {
+                    self.ensure_block()?;
+                    self.element("label", &[("type", "speaker")], &w.word)?;
+                }
+                WordType::Desc => {
+                    self.ensure_div()?;
+                    self.element("note", &[("type", "desc")], &w.word)?;
+                }
+                WordType::PageBreak | WordType::InvalidType => (),
+            }
+        }
+        self.close_div()
+    }
+}
+
+fn write_tei<F>(title: &str, witnesses: &[&str], write_text: F) -> std::io::Result<String>
+where
+    F: FnOnce(&mut Writer<Cursor<Vec<u8>>>) -> std::io::Result<()>,
+{
+    let mut writer = Writer::new_with_indent(Cursor::new(Vec::new()), b' ', 2);
+    let mut tei = BytesStart::new("TEI");
+    tei.push_attribute(("xmlns", TEI_NS));
+    writer.write_event(Event::Start(tei))?;
+    writer
+        .create_element("teiHeader")
+        .write_inner_content(|w| {
+            w.create_element("fileDesc").write_inner_content(|w| {
+                w.create_element("titleStmt").write_inner_content(|w| {
+                    w.create_element("title")
+                        .write_text_content(BytesText::new(title))?;
+                    Ok(())
+                })?;
+                w.create_element("publicationStmt")
+                    .write_inner_content(|w| {
+                        w.create_element("p")
+                            .write_text_content(BytesText::new("Unpublished"))?;
+                        Ok(())
+                    })?;
+                w.create_element("sourceDesc").write_inner_content(|w| {
+                    w.create_element("p")
+                        .write_text_content(BytesText::new("Exported from gkvocab"))?;
+                    //the witnesses the apparatus' readings point to
+                    if !witnesses.is_empty() {
+                        w.create_element("listWit").write_inner_content(|w| {
+                            for sigil in witnesses {
+                                let id = witness_id(sigil);
+                                w.create_element("witness")
+                                    .with_attribute(("xml:id", id.as_str()))
+                                    .write_text_content(BytesText::new(sigil))?;
+                            }
+                            Ok(())
+                        })?;
+                    }
+                    Ok(())
+                })?;
+                Ok(())
+            })?;
+            Ok(())
+        })?;
+    writer.write_event(Event::Start(BytesStart::new("text")))?;
+    write_text(&mut writer)?;
+    writer.write_event(Event::End(BytesEnd::new("text")))?;
+    writer.write_event(Event::End(BytesEnd::new("TEI")))?;
+
+    let result = writer.into_inner().into_inner();
+    Ok(std::str::from_utf8(&result).unwrap().to_string())
+}
+
+fn write_body<'w>(
+    writer: &'w mut Writer<Cursor<Vec<u8>>>,
+    text: &'w Text,
+    page_breaks: HashMap<WordUuid, usize>,
+    glossary_in_document: bool,
+) -> std::io::Result<()> {
+    let mut body = TeiBody {
+        writer,
+        div_open: false,
+        sp_open: false,
+        block: None,
+        appcrits: text
+            .appcrits
+            .iter()
+            .flatten()
+            .map(|a| (a.word_uuid, a))
+            .collect(),
+        app_open: None,
+        page_breaks,
+        glossary_in_document,
+    };
+    body.start("body", &[])?;
+    body.write_text(text)?;
+    body.end("body")
+}
+
+impl Text {
+    //the text as a TEI P5 document. lemmaRef points to each gloss by its uuid
+    pub fn to_tei(&self) -> Result<String, quick_xml::Error> {
+        Ok(write_tei(
+            &self.text_name,
+            &witnesses([self].into_iter()),
+            |writer| write_body(writer, self, HashMap::default(), false),
+        )?)
+    }
+}
+
+impl Sequence {
+    //the displayed texts as a TEI P5 group with page breaks where make_document starts pages,
+    //followed by a glossary of every gloss those texts use. an entry's xr gives the word and page
+    //where the gloss is arrowed, when that word is in a displayed text
+    pub fn to_tei(&self) -> Result<String, GlosserError> {
+        let gloss_occurrances = self.process()?;
+        let glosses_hash = self.make_glosses_hash();
+        let map = self.make_page_map();
+
+        let displayed = |text_index: usize| {
+            self.sequence_description
+                .texts
+                .get(text_index)
+                .is_some_and(|d| d.display)
+        };
+
+        //only what the displayed texts use, so every entry and xr points at something written
+        let mut used: HashMap<GlossUuid, usize> = HashMap::default(); //gloss to total count
+        let mut written_words: HashSet<WordUuid> = HashSet::default();
+        for (text_index, t) in self.texts.iter().enumerate() {
+            if !displayed(text_index) {
+                continue;
+            }
+            written_words.extend(t.words.iter().map(|w| w.uuid));
+            for go in gloss_occurrances.get(text_index).into_iter().flatten() {
+                if let Some(g) = go.gloss {
+                    used.insert(g.uuid, go.total_count.unwrap_or(0));
+                }
+            }
+        }
+        let mut entries: Vec<_> = used
+            .iter()
+            .filter_map(|(uuid, count)| glosses_hash.get(uuid).map(|g| (*g, *count)))
+            .collect();
+        let collator = greek_collator();
+        entries.sort_by(|a, b| collator.as_borrowed().compare(&a.0.sort_key, &b.0.sort_key));
+        let arrows: HashMap<GlossUuid, WordUuid> = self
+            .sequence_description
+            .arrowed_words
+            .iter()
+            .filter(|a| written_words.contains(&a.word_uuid))
+            .map(|a| (a.gloss_uuid, a.word_uuid))
+            .collect();
+
+        let witnesses = witnesses(
+            self.texts
+                .iter()
+                .enumerate()
+                .filter(|(text_index, _)| displayed(*text_index))
+                .map(|(_, t)| t),
+        );
+        write_tei(&self.sequence_description.name, &witnesses, |writer| {
+            writer.write_event(Event::Start(BytesStart::new("group")))?;
+            for (text_index, t) in self.texts.iter().enumerate() {
+                if !displayed(text_index) {
+                    continue;
+                }
+                let page_breaks = map
+                    .text_pages(text_index)
+                    .map(|(page_number, page)| (page.first_word, page_number))
+                    .collect();
+                let id = format!("text-{}", text_index);
+                let mut text = BytesStart::new("text");
+                text.push_attribute(("xml:id", id.as_str()));
+                text.push_attribute(("n", t.text_name.as_str()));
+                writer.write_event(Event::Start(text))?;
+                write_body(writer, t, page_breaks, true)?;
+                writer.write_event(Event::End(BytesEnd::new("text")))?;
+            }
+            writer.write_event(Event::End(BytesEnd::new("group")))?;
+
+            writer.write_event(Event::Start(BytesStart::new("back")))?;
+            let mut div = BytesStart::new("div");
+            div.push_attribute(("type", "glossary"));
+            writer.write_event(Event::Start(div))?;
+            for (g, count) in &entries {
+                let id = gloss_id(&g.uuid);
+                let count = count.to_string();
+                writer
+                    .create_element("entry")
+                    .with_attribute(("xml:id", id.as_str()))
+                    .with_attribute(("sortKey", g.sort_key.as_str()))
+                    .write_inner_content(|w| {
+                        w.create_element("form").write_inner_content(|w| {
+                            w.create_element("orth")
+                                .write_text_content(BytesText::new(&g.lemma))?;
+                            Ok(())
+                        })?;
+                        w.create_element("gramGrp").write_inner_content(|w| {
+                            w.create_element("pos")
+                                .write_text_content(BytesText::new(&g.pos))?;
+                            Ok(())
+                        })?;
+                        w.create_element("sense").write_inner_content(|w| {
+                            w.create_element("def")
+                                .write_text_content(BytesText::new(&g.def))?;
+                            Ok(())
+                        })?;
+                        w.create_element("note")
+                            .with_attribute(("type", "count"))
+                            .write_text_content(BytesText::new(&count))?;
+                        if let Some(word_uuid) = arrows.get(&g.uuid) {
+                            let target = format!("#{}", word_id(word_uuid));
+                            let page = map
+                                .page_of_word(*word_uuid)
+                                .map(|p| p.to_string())
+                                .unwrap_or_default();
+                            w.create_element("xr")
+                                .with_attribute(("type", "arrow"))
+                                .with_attribute(("n", page.as_str()))
+                                .write_inner_content(|w| {
+                                    w.create_element("ptr")
+                                        .with_attribute(("target", target.as_str()))
+                                        .write_empty()?;
+                                    Ok(())
+                                })?;
+                        }
+                        Ok(())
+                    })?;
+            }
+            writer.write_event(Event::End(BytesEnd::new("div")))?;
+            writer.write_event(Event::End(BytesEnd::new("back")))?;
+            Ok(())
+        })
+        .map_err(|e| GlosserError::Other(format!("Error writing TEI: {}", e)))
+    }
+}
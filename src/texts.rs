@@ -2,11 +2,12 @@ use crate::{GlossUuid, WordUuid, get_entity};
 use quick_xml::Reader;
 use quick_xml::events::Event;
 use quick_xml::name::QName;
+use serde::{Deserialize, Serialize};
 use std::fmt;
 use std::str::FromStr;
 use uuid::Uuid;
 
-#[derive(Default, Clone, Copy, Debug, PartialEq)]
+#[derive(Default, Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
 pub enum WordType {
     Word = 0,
     Punctuation = 1,
@@ -70,13 +71,41 @@ impl fmt::Display for WordType {
     }
 }
 
-#[derive(Default, Clone, Debug, PartialEq)]
+#[derive(Default, Clone, Debug, PartialEq, Serialize, Deserialize)]
 pub struct AppCrit {
-    pub word_uuid: WordUuid,
-    pub entry: String,
+    pub word_uuid: WordUuid, //the word the note is on, or the first word of its span
+    #[serde(default)]
+    pub end_word_uuid: Option<WordUuid>, //the last word of the span, if the note has one
+    #[serde(default)]
+    pub lemma: String,
+    #[serde(default)]
+    pub reading: String,
+    #[serde(default)]
+    pub witnesses: Vec<String>,
+    pub entry: String, //free text, printed after the lemma, reading and witnesses
 }
 
-#[derive(Default, Clone, Debug, PartialEq)]
+impl AppCrit {
+    //the note as printed: lemma] reading witnesses, then the free text
+    pub fn text(&self) -> String {
+        let mut parts = vec![];
+        if !self.lemma.is_empty() {
+            parts.push(format!("{}]", self.lemma));
+        }
+        if !self.reading.is_empty() {
+            parts.push(self.reading.clone());
+        }
+        if !self.witnesses.is_empty() {
+            parts.push(self.witnesses.join(" "));
+        }
+        if !self.entry.is_empty() {
+            parts.push(self.entry.clone());
+        }
+        parts.join(" ")
+    }
+}
+
+#[derive(Default, Clone, Debug, PartialEq, Serialize, Deserialize)]
 pub struct Word {
     pub uuid: WordUuid,
     pub gloss_uuid: Option<GlossUuid>,
@@ -84,7 +113,7 @@ pub struct Word {
     pub word: String,
 }
 
-#[derive(Clone, Debug, PartialEq)]
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
 pub struct Text {
     pub text_name: String,
     pub words: Vec<Word>,
@@ -165,10 +194,23 @@ pub fn read_text_xml(
                     for attribute_result in e.attributes() {
                         match attribute_result {
                             Ok(attr) => {
+                                let value = attr
+                                    .decode_and_unescape_value(reader.decoder())
+                                    .unwrap_or_default();
                                 if attr.key == QName(b"word_uuid") {
-                                    current_appcrit.word_uuid =
-                                        Uuid::parse_str(std::str::from_utf8(&attr.value).unwrap())
-                                            .unwrap();
+                                    current_appcrit.word_uuid = Uuid::parse_str(&value).unwrap();
+                                } else if attr.key == QName(b"end_word_uuid") {
+                                    current_appcrit.end_word_uuid = Uuid::parse_str(&value).ok();
+                                } else if attr.key == QName(b"lemma") {
+                                    current_appcrit.lemma = value.to_string();
+                                } else if attr.key == QName(b"reading") {
+                                    current_appcrit.reading = value.to_string();
+                                } else if attr.key == QName(b"witnesses") {
+                                    current_appcrit.witnesses = value
+                                        .split(',')
+                                        .map(|w| w.trim().to_string())
+                                        .filter(|w| !w.is_empty())
+                                        .collect();
                                 }
                             }
                             Err(e) => eprintln!("Error reading attribute: {:?}", e),
@@ -298,10 +340,24 @@ pub fn write_text_xml(text: &Text) -> Result<String, quick_xml::Error> {
             writer.write_event(Event::Start(BytesStart::new("appcrits")))?;
         }
         for a in appcrits {
-            writer
+            //the structured parts are only written when there are any, as older files have none
+            let mut element = writer
                 .create_element("appcrit")
-                .with_attribute(("word_uuid", a.word_uuid.to_string().as_str()))
-                .write_text_content(BytesText::new(&a.entry))?;
+                .with_attribute(("word_uuid", a.word_uuid.to_string().as_str()));
+            if let Some(end_word_uuid) = a.end_word_uuid {
+                element =
+                    element.with_attribute(("end_word_uuid", end_word_uuid.to_string().as_str()));
+            }
+            if !a.lemma.is_empty() {
+                element = element.with_attribute(("lemma", a.lemma.as_str()));
+            }
+            if !a.reading.is_empty() {
+                element = element.with_attribute(("reading", a.reading.as_str()));
+            }
+            if !a.witnesses.is_empty() {
+                element = element.with_attribute(("witnesses", a.witnesses.join(",").as_str()));
+            }
+            element.write_text_content(BytesText::new(&a.entry))?;
         }
         if !appcrits.is_empty() {
             writer.write_event(Event::End(BytesEnd::new("appcrits")))?;
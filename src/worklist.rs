@@ -0,0 +1,151 @@
+use crate::{GlossUuid, Sequence, WordType, WordUuid, greek_collator};
+use std::collections::HashMap;
+
+//where an unglossed form occurs in the sequence
+#[derive(Clone, Debug, PartialEq)]
+pub struct WordPosition {
+    pub text_index: usize,
+    pub word_index: usize,
+    pub word_uuid: WordUuid,
+}
+
+//a gloss previously assigned to this surface form and how often
+#[derive(Clone, Debug, PartialEq)]
+pub struct LemmaCandidate {
+    pub gloss_uuid: GlossUuid,
+    pub count: usize,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct UnglossedForm {
+    pub form: String,
+    pub count: usize,
+    pub positions: Vec<WordPosition>,
+    pub candidates: Vec<LemmaCandidate>,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct TextGlossProgress {
+    pub text_name: String,
+    pub words: usize,
+    pub glossed: usize,
+}
+
+impl TextGlossProgress {
+    pub fn percent_glossed(&self) -> f64 {
+        if self.words == 0 {
+            100.0
+        } else {
+            self.glossed as f64 * 100.0 / self.words as f64
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum WorklistSort {
+    Frequency,       //most common unglossed forms first
+    Form,            //Greek alphabetical order
+    FirstOccurrence, //order in which the forms first appear in the sequence
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Worklist {
+    pub forms: Vec<UnglossedForm>,
+    pub texts: Vec<TextGlossProgress>,
+}
+
+fn rank_candidates(counts: &HashMap<GlossUuid, usize>, max: usize) -> Vec<LemmaCandidate> {
+    let mut candidates: Vec<LemmaCandidate> = counts
+        .iter()
+        .map(|(gloss_uuid, count)| LemmaCandidate {
+            gloss_uuid: *gloss_uuid,
+            count: *count,
+        })
+        .collect();
+    candidates.sort_by(|a, b| {
+        b.count
+            .cmp(&a.count)
+            .then_with(|| a.gloss_uuid.cmp(&b.gloss_uuid))
+    });
+    candidates.truncate(max);
+    candidates
+}
+
+impl Sequence {
+    //lists every WordType::Word without a gloss, grouped by surface form.
+    //candidates come from the glosses already assigned to the same form elsewhere in the sequence;
+    //if the exact form was never glossed, forms which only differ in case are used instead.
+    pub fn unglossed_worklist(&self, sort: WorklistSort, max_candidates: usize) -> Worklist {
+        let glosses_hash = self.make_glosses_hash();
+
+        let mut exact: HashMap<&str, HashMap<GlossUuid, usize>> = HashMap::default();
+        let mut lowercase: HashMap<String, HashMap<GlossUuid, usize>> = HashMap::default();
+        let mut forms: Vec<UnglossedForm> = vec![];
+        let mut form_index: HashMap<&str, usize> = HashMap::default();
+        let mut texts = vec![];
+
+        for (text_index, t) in self.texts.iter().enumerate() {
+            let mut progress = TextGlossProgress {
+                text_name: t.text_name.clone(),
+                words: 0,
+                glossed: 0,
+            };
+            for (word_index, w) in t.words.iter().enumerate() {
+                if w.word_type != WordType::Word {
+                    continue;
+                }
+                progress.words += 1;
+                if let Some(g) = w.gloss_uuid {
+                    progress.glossed += 1;
+                    if glosses_hash.get(&g).is_some_and(|gloss| gloss.status > 0) {
+                        *exact.entry(&w.word).or_default().entry(g).or_insert(0) += 1;
+                        *lowercase
+                            .entry(w.word.to_lowercase())
+                            .or_default()
+                            .entry(g)
+                            .or_insert(0) += 1;
+                    }
+                } else {
+                    let position = WordPosition {
+                        text_index,
+                        word_index,
+                        word_uuid: w.uuid,
+                    };
+                    if let Some(i) = form_index.get(w.word.as_str()) {
+                        forms[*i].count += 1;
+                        forms[*i].positions.push(position);
+                    } else {
+                        form_index.insert(&w.word, forms.len());
+                        forms.push(UnglossedForm {
+                            form: w.word.clone(),
+                            count: 1,
+                            positions: vec![position],
+                            candidates: vec![],
+                        });
+                    }
+                }
+            }
+            texts.push(progress);
+        }
+
+        for f in &mut forms {
+            if let Some(counts) = exact.get(f.form.as_str()) {
+                f.candidates = rank_candidates(counts, max_candidates);
+            } else if let Some(counts) = lowercase.get(&f.form.to_lowercase()) {
+                f.candidates = rank_candidates(counts, max_candidates);
+            }
+        }
+
+        //forms are already in order of first occurrence
+        match sort {
+            WorklistSort::Frequency => forms.sort_by_key(|f| std::cmp::Reverse(f.count)),
+            WorklistSort::Form => {
+                let collator = greek_collator();
+                forms.sort_by(|a, b| collator.as_borrowed().compare(&a.form, &b.form));
+            }
+            WorklistSort::FirstOccurrence => (),
+        }
+
+        Worklist { forms, texts }
+    }
+}